@@ -4,6 +4,8 @@ use sea_orm::entity::prelude::*;
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
+use crate::{money::Money, sea_orm_active_enums::BudgetPeriod};
+
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, DeriveEntityModel)]
 #[sea_orm(table_name = "budget")]
 pub struct Model {
@@ -11,16 +13,33 @@ pub struct Model {
     pub id: Uuid,
     pub user_id: Uuid,
     pub name: String,
-    #[sea_orm(column_type = "Double")]
-    pub total_amount: f64,
-    pub created_at: String,
-    pub updated_at: String,
+    /// Amount available in each period.
+    pub total_amount: Money,
+    /// ISO 4217 code of the currency `total_amount` and all summaries are in.
+    pub currency: String,
+    pub period: BudgetPeriod,
+    /// First day of the first period.
+    pub starts_on: Date,
+    /// Last day of a `custom` period; unset for recurring ones.
+    pub ends_on: Option<Date>,
+    /// Whether unspent (or overspent) amounts carry into the next period.
+    pub rollover: bool,
+    pub created_at: DateTimeUtc,
+    pub updated_at: DateTimeUtc,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
 pub enum Relation {
+    #[sea_orm(has_many = "super::budget_category::Entity")]
+    BudgetCategory,
     #[sea_orm(has_many = "super::expense::Entity")]
     Expense,
+    #[sea_orm(has_many = "super::expense_split::Entity")]
+    ExpenseSplit,
+    #[sea_orm(has_many = "super::income::Entity")]
+    Income,
+    #[sea_orm(has_many = "super::recurring_expense::Entity")]
+    RecurringExpense,
     #[sea_orm(
         belongs_to = "super::users::Entity",
         from = "Column::UserId",
@@ -31,12 +50,36 @@ pub enum Relation {
     Users,
 }
 
+impl Related<super::budget_category::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::BudgetCategory.def()
+    }
+}
+
 impl Related<super::expense::Entity> for Entity {
     fn to() -> RelationDef {
         Relation::Expense.def()
     }
 }
 
+impl Related<super::expense_split::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::ExpenseSplit.def()
+    }
+}
+
+impl Related<super::income::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Income.def()
+    }
+}
+
+impl Related<super::recurring_expense::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::RecurringExpense.def()
+    }
+}
+
 impl Related<super::users::Entity> for Entity {
     fn to() -> RelationDef {
         Relation::Users.def()
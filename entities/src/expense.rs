@@ -4,22 +4,38 @@ use sea_orm::entity::prelude::*;
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
+use crate::money::Money;
+
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, DeriveEntityModel)]
 #[sea_orm(table_name = "expense")]
 pub struct Model {
     #[sea_orm(primary_key, auto_increment = false)]
     pub id: Uuid,
     pub budget_id: Uuid,
-    #[sea_orm(column_type = "Double")]
-    pub amount: f64,
+    pub amount: Money,
+    /// ISO 4217 code; may differ from the budget's currency.
+    pub currency: String,
     pub description: String,
-    pub date: String,
-    pub created_at: String,
-    pub updated_at: String,
+    pub date: Date,
+    pub category_id: Option<Uuid>,
+    /// The template this expense was created from, if any.
+    pub recurring_expense_id: Option<Uuid>,
+    /// The account the expense was paid from, if any.
+    pub account_id: Option<Uuid>,
+    pub created_at: DateTimeUtc,
+    pub updated_at: DateTimeUtc,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
 pub enum Relation {
+    #[sea_orm(
+        belongs_to = "super::account::Entity",
+        from = "Column::AccountId",
+        to = "super::account::Column::Id",
+        on_update = "NoAction",
+        on_delete = "NoAction"
+    )]
+    Account,
     #[sea_orm(
         belongs_to = "super::budget::Entity",
         from = "Column::BudgetId",
@@ -28,6 +44,32 @@ pub enum Relation {
         on_delete = "NoAction"
     )]
     Budget,
+    #[sea_orm(
+        belongs_to = "super::category::Entity",
+        from = "Column::CategoryId",
+        to = "super::category::Column::Id",
+        on_update = "NoAction",
+        on_delete = "NoAction"
+    )]
+    Category,
+    #[sea_orm(has_many = "super::expense_split::Entity")]
+    ExpenseSplit,
+    #[sea_orm(has_many = "super::expense_tag::Entity")]
+    ExpenseTag,
+    #[sea_orm(
+        belongs_to = "super::recurring_expense::Entity",
+        from = "Column::RecurringExpenseId",
+        to = "super::recurring_expense::Column::Id",
+        on_update = "NoAction",
+        on_delete = "NoAction"
+    )]
+    RecurringExpense,
+}
+
+impl Related<super::account::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Account.def()
+    }
 }
 
 impl Related<super::budget::Entity> for Entity {
@@ -36,4 +78,38 @@ impl Related<super::budget::Entity> for Entity {
     }
 }
 
+impl Related<super::category::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Category.def()
+    }
+}
+
+impl Related<super::expense_split::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::ExpenseSplit.def()
+    }
+}
+
+impl Related<super::expense_tag::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::ExpenseTag.def()
+    }
+}
+
+impl Related<super::recurring_expense::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::RecurringExpense.def()
+    }
+}
+
+impl Related<super::tag::Entity> for Entity {
+    fn to() -> RelationDef {
+        super::expense_tag::Relation::Tag.def()
+    }
+
+    fn via() -> Option<RelationDef> {
+        Some(super::expense_tag::Relation::Expense.def().rev())
+    }
+}
+
 impl ActiveModelBehavior for ActiveModel {}
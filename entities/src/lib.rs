@@ -1,6 +1,25 @@
 //! `SeaORM` Entity. Generated by sea-orm-codegen 0.12.15
 
+pub mod account;
+pub mod api_key;
 pub mod budget;
+pub mod budget_category;
+pub mod category;
+pub mod email_token;
+pub mod exchange_rate;
 pub mod expense;
+pub mod expense_split;
+pub mod expense_tag;
+pub mod income;
+pub mod login_challenge;
+pub mod money;
 pub mod prelude;
+pub mod rate;
+pub mod recovery_code;
+pub mod recurring_expense;
+pub mod refresh_token;
+pub mod sea_orm_active_enums;
+pub mod tag;
+pub mod totp;
+pub mod transfer;
 pub mod users;
@@ -0,0 +1,185 @@
+//! Exact monetary amounts.
+//!
+//! Amounts are stored as a whole number of minor units (cents) in a
+//! `BIGINT` column, so sums computed by the database or in Rust never pick up
+//! floating point rounding errors. In JSON they are written as decimal
+//! strings (`"12.30"`); numbers are accepted on input as long as they have no
+//! more than two decimal places.
+
+use std::{
+    fmt,
+    iter::Sum,
+    ops::{Add, AddAssign, Neg, Sub, SubAssign},
+    str::FromStr,
+};
+
+use rust_decimal::Decimal;
+use sea_orm::DeriveValueType;
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash, DeriveValueType)]
+pub struct Money(i64);
+
+impl Money {
+    /// Number of decimal places kept.
+    pub const SCALE: u32 = 2;
+    pub const ZERO: Money = Money(0);
+
+    pub const fn from_minor_units(minor: i64) -> Self {
+        Money(minor)
+    }
+
+    pub const fn minor_units(self) -> i64 {
+        self.0
+    }
+
+    /// Converts `value`, failing if it has more than [`Money::SCALE`]
+    /// decimal places or does not fit.
+    pub fn from_decimal(value: Decimal) -> Result<Self, MoneyError> {
+        let value = value.normalize();
+        if value.scale() > Self::SCALE {
+            return Err(MoneyError::TooPrecise);
+        }
+        let minor = value
+            .checked_mul(Decimal::from(100))
+            .ok_or(MoneyError::OutOfRange)?;
+        i64::try_from(minor)
+            .map(Money)
+            .map_err(|_| MoneyError::OutOfRange)
+    }
+
+    pub fn to_decimal(self) -> Decimal {
+        Decimal::new(self.0, Self::SCALE)
+    }
+
+    pub fn is_positive(self) -> bool {
+        self.0 > 0
+    }
+
+    pub fn checked_add(self, other: Money) -> Option<Money> {
+        self.0.checked_add(other.0).map(Money)
+    }
+
+    pub fn checked_sub(self, other: Money) -> Option<Money> {
+        self.0.checked_sub(other.0).map(Money)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MoneyError {
+    Invalid,
+    TooPrecise,
+    OutOfRange,
+}
+
+impl fmt::Display for MoneyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MoneyError::Invalid => f.write_str("amount must be a decimal number"),
+            MoneyError::TooPrecise => f.write_str("amount must have at most 2 decimal places"),
+            MoneyError::OutOfRange => f.write_str("amount is out of range"),
+        }
+    }
+}
+
+impl std::error::Error for MoneyError {}
+
+impl fmt::Display for Money {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&self.to_decimal(), f)
+    }
+}
+
+impl FromStr for Money {
+    type Err = MoneyError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let value = Decimal::from_str_exact(s.trim()).map_err(|_| MoneyError::Invalid)?;
+        Money::from_decimal(value)
+    }
+}
+
+impl Add for Money {
+    type Output = Money;
+
+    fn add(self, other: Money) -> Money {
+        Money(self.0 + other.0)
+    }
+}
+
+impl AddAssign for Money {
+    fn add_assign(&mut self, other: Money) {
+        self.0 += other.0;
+    }
+}
+
+impl Sub for Money {
+    type Output = Money;
+
+    fn sub(self, other: Money) -> Money {
+        Money(self.0 - other.0)
+    }
+}
+
+impl SubAssign for Money {
+    fn sub_assign(&mut self, other: Money) {
+        self.0 -= other.0;
+    }
+}
+
+impl Neg for Money {
+    type Output = Money;
+
+    fn neg(self) -> Money {
+        Money(-self.0)
+    }
+}
+
+impl Sum for Money {
+    fn sum<I: Iterator<Item = Money>>(iter: I) -> Money {
+        iter.fold(Money::ZERO, Add::add)
+    }
+}
+
+impl Serialize for Money {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for Money {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_any(MoneyVisitor)
+    }
+}
+
+struct MoneyVisitor;
+
+impl<'de> de::Visitor<'de> for MoneyVisitor {
+    type Value = Money;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a decimal amount such as \"12.34\"")
+    }
+
+    fn visit_str<E: de::Error>(self, v: &str) -> Result<Money, E> {
+        v.parse().map_err(E::custom)
+    }
+
+    fn visit_i64<E: de::Error>(self, v: i64) -> Result<Money, E> {
+        Money::from_decimal(Decimal::from(v)).map_err(E::custom)
+    }
+
+    fn visit_u64<E: de::Error>(self, v: u64) -> Result<Money, E> {
+        Money::from_decimal(Decimal::from(v)).map_err(E::custom)
+    }
+
+    fn visit_f64<E: de::Error>(self, v: f64) -> Result<Money, E> {
+        // `f64`'s `Display` prints the shortest string that round-trips, so
+        // `12.34` is read back as exactly 12.34 rather than its binary value.
+        if !v.is_finite() {
+            return Err(E::custom(MoneyError::Invalid));
+        }
+        v.to_string().parse().map_err(E::custom)
+    }
+}
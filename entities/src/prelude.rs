@@ -1,5 +1,21 @@
 //! `SeaORM` Entity. Generated by sea-orm-codegen 0.12.15
 
+pub use super::account::Entity as Account;
+pub use super::api_key::Entity as ApiKey;
 pub use super::budget::Entity as Budget;
+pub use super::budget_category::Entity as BudgetCategory;
+pub use super::category::Entity as Category;
+pub use super::email_token::Entity as EmailToken;
+pub use super::exchange_rate::Entity as ExchangeRate;
 pub use super::expense::Entity as Expense;
+pub use super::expense_split::Entity as ExpenseSplit;
+pub use super::expense_tag::Entity as ExpenseTag;
+pub use super::income::Entity as Income;
+pub use super::login_challenge::Entity as LoginChallenge;
+pub use super::recovery_code::Entity as RecoveryCode;
+pub use super::recurring_expense::Entity as RecurringExpense;
+pub use super::refresh_token::Entity as RefreshToken;
+pub use super::tag::Entity as Tag;
+pub use super::totp::Entity as Totp;
+pub use super::transfer::Entity as Transfer;
 pub use super::users::Entity as Users;
@@ -0,0 +1,100 @@
+//! Exact exchange rates.
+//!
+//! Like [`Money`], rates are stored as a scaled integer so that converting an
+//! amount is exact integer arithmetic. Ten decimal places are kept, which is
+//! enough for the inverse of any rate in common use. In JSON they are written
+//! as decimal strings (`"1.0876"`).
+
+use std::{fmt, str::FromStr};
+
+use rust_decimal::Decimal;
+use sea_orm::DeriveValueType;
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::money::Money;
+
+/// How many units of the quote currency one unit of the base currency buys.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, DeriveValueType)]
+pub struct Rate(i64);
+
+const ONE: i128 = 10_000_000_000;
+
+impl Rate {
+    /// Number of decimal places kept.
+    pub const SCALE: u32 = 10;
+
+    /// Parses a positive decimal rate, failing if it has more than
+    /// [`Rate::SCALE`] decimal places or does not fit.
+    pub fn from_decimal(value: Decimal) -> Option<Self> {
+        let value = value.normalize();
+        if value.scale() > Self::SCALE || value <= Decimal::ZERO {
+            return None;
+        }
+        let scaled = value.checked_mul(Decimal::from(ONE as i64))?;
+        i64::try_from(scaled).ok().map(Rate)
+    }
+
+    pub fn to_decimal(self) -> Decimal {
+        Decimal::new(self.0, Self::SCALE)
+    }
+
+    /// `amount` in the quote currency, rounded half away from zero to whole
+    /// minor units.
+    pub fn convert(self, amount: Money) -> Option<Money> {
+        scale(i128::from(amount.minor_units()) * i128::from(self.0), ONE)
+    }
+
+    /// `amount` in the quote currency converted back to the base currency.
+    pub fn convert_inverse(self, amount: Money) -> Option<Money> {
+        scale(i128::from(amount.minor_units()) * ONE, i128::from(self.0))
+    }
+}
+
+fn scale(numerator: i128, denominator: i128) -> Option<Money> {
+    let half = denominator / 2;
+    let rounded = if numerator < 0 {
+        (numerator - half) / denominator
+    } else {
+        (numerator + half) / denominator
+    };
+    i64::try_from(rounded).ok().map(Money::from_minor_units)
+}
+
+impl fmt::Display for Rate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&self.to_decimal().normalize(), f)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct InvalidRate;
+
+impl fmt::Display for InvalidRate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("rate must be a positive decimal number with at most 10 decimal places")
+    }
+}
+
+impl std::error::Error for InvalidRate {}
+
+impl FromStr for Rate {
+    type Err = InvalidRate;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let value = Decimal::from_str_exact(s.trim()).map_err(|_| InvalidRate)?;
+        Rate::from_decimal(value).ok_or(InvalidRate)
+    }
+}
+
+impl Serialize for Rate {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for Rate {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        s.parse().map_err(de::Error::custom)
+    }
+}
@@ -12,12 +12,48 @@ pub struct Model {
     pub username: String,
     pub password_hash: String,
     pub email: String,
+    /// When the user proved `email` is theirs; cleared when it changes.
+    pub email_verified_at: Option<DateTimeUtc>,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
 pub enum Relation {
+    #[sea_orm(has_many = "super::account::Entity")]
+    Account,
+    #[sea_orm(has_many = "super::api_key::Entity")]
+    ApiKey,
     #[sea_orm(has_many = "super::budget::Entity")]
     Budget,
+    #[sea_orm(has_many = "super::category::Entity")]
+    Category,
+    #[sea_orm(has_many = "super::email_token::Entity")]
+    EmailToken,
+    #[sea_orm(has_many = "super::income::Entity")]
+    Income,
+    #[sea_orm(has_many = "super::login_challenge::Entity")]
+    LoginChallenge,
+    #[sea_orm(has_many = "super::recovery_code::Entity")]
+    RecoveryCode,
+    #[sea_orm(has_many = "super::refresh_token::Entity")]
+    RefreshToken,
+    #[sea_orm(has_many = "super::tag::Entity")]
+    Tag,
+    #[sea_orm(has_one = "super::totp::Entity")]
+    Totp,
+    #[sea_orm(has_many = "super::transfer::Entity")]
+    Transfer,
+}
+
+impl Related<super::account::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Account.def()
+    }
+}
+
+impl Related<super::api_key::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::ApiKey.def()
+    }
 }
 
 impl Related<super::budget::Entity> for Entity {
@@ -26,4 +62,58 @@ impl Related<super::budget::Entity> for Entity {
     }
 }
 
+impl Related<super::category::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Category.def()
+    }
+}
+
+impl Related<super::email_token::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::EmailToken.def()
+    }
+}
+
+impl Related<super::income::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Income.def()
+    }
+}
+
+impl Related<super::login_challenge::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::LoginChallenge.def()
+    }
+}
+
+impl Related<super::recovery_code::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::RecoveryCode.def()
+    }
+}
+
+impl Related<super::refresh_token::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::RefreshToken.def()
+    }
+}
+
+impl Related<super::tag::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Tag.def()
+    }
+}
+
+impl Related<super::totp::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Totp.def()
+    }
+}
+
+impl Related<super::transfer::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Transfer.def()
+    }
+}
+
 impl ActiveModelBehavior for ActiveModel {}
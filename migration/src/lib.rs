@@ -3,6 +3,20 @@ pub use sea_orm_migration::prelude::*;
 mod m20220101_000001_create_table_user;
 mod m20220101_000002_create_table_budget;
 mod m20220101_000003_create_table_expense;
+mod m20220101_000004_store_money_as_minor_units;
+mod m20220101_000005_normalize_timestamps;
+mod m20220101_000006_create_table_category;
+mod m20220101_000007_create_table_tag;
+mod m20220101_000008_add_budget_periods;
+mod m20220101_000009_add_currencies;
+mod m20220101_000010_create_table_recurring_expense;
+mod m20220101_000011_create_table_income;
+mod m20220101_000012_create_table_account;
+mod m20220101_000013_create_table_expense_split;
+mod m20220101_000014_create_table_refresh_token;
+mod m20220101_000015_create_table_api_key;
+mod m20220101_000016_create_table_totp;
+mod m20220101_000017_create_table_email_token;
 
 pub struct Migrator;
 
@@ -13,6 +27,20 @@ impl MigratorTrait for Migrator {
             Box::new(m20220101_000001_create_table_user::Migration),
             Box::new(m20220101_000002_create_table_budget::Migration),
             Box::new(m20220101_000003_create_table_expense::Migration),
+            Box::new(m20220101_000004_store_money_as_minor_units::Migration),
+            Box::new(m20220101_000005_normalize_timestamps::Migration),
+            Box::new(m20220101_000006_create_table_category::Migration),
+            Box::new(m20220101_000007_create_table_tag::Migration),
+            Box::new(m20220101_000008_add_budget_periods::Migration),
+            Box::new(m20220101_000009_add_currencies::Migration),
+            Box::new(m20220101_000010_create_table_recurring_expense::Migration),
+            Box::new(m20220101_000011_create_table_income::Migration),
+            Box::new(m20220101_000012_create_table_account::Migration),
+            Box::new(m20220101_000013_create_table_expense_split::Migration),
+            Box::new(m20220101_000014_create_table_refresh_token::Migration),
+            Box::new(m20220101_000015_create_table_api_key::Migration),
+            Box::new(m20220101_000016_create_table_totp::Migration),
+            Box::new(m20220101_000017_create_table_email_token::Migration),
         ]
     }
 }
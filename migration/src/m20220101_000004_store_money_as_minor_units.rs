@@ -0,0 +1,101 @@
+use sea_orm_migration::prelude::*;
+
+/// Budget and expense amounts used to be floating point columns. They are
+/// now whole numbers of cents; existing values are rounded to the nearest
+/// cent while being copied over.
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+const COLUMNS: [(Tables, Columns); 2] = [
+    (Tables::Budget, Columns::TotalAmount),
+    (Tables::Expense, Columns::Amount),
+];
+
+#[derive(Clone, Copy, Iden)]
+enum Tables {
+    Budget,
+    Expense,
+}
+
+#[derive(Clone, Copy, Iden)]
+enum Columns {
+    TotalAmount,
+    Amount,
+    MinorUnits,
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        for (table, column) in COLUMNS {
+            replace_column(
+                manager,
+                table,
+                column,
+                ColumnDef::new(Columns::MinorUnits)
+                    .big_integer()
+                    .not_null()
+                    .default(0)
+                    .to_owned(),
+                Expr::cust(format!(
+                    "CAST(ROUND({} * 100) AS INTEGER)",
+                    column.to_string()
+                )),
+            )
+            .await?;
+        }
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        for (table, column) in COLUMNS {
+            replace_column(
+                manager,
+                table,
+                column,
+                ColumnDef::new(Columns::MinorUnits)
+                    .double()
+                    .not_null()
+                    .default(0.0)
+                    .to_owned(),
+                Expr::cust(format!("{} / 100.0", column.to_string())),
+            )
+            .await?;
+        }
+        Ok(())
+    }
+}
+
+/// Adds `new` as a scratch column, fills it from `convert`, then drops
+/// `column` and renames the scratch column into its place. SQLite cannot
+/// change the type of an existing column.
+async fn replace_column(
+    manager: &SchemaManager<'_>,
+    table: Tables,
+    column: Columns,
+    mut new: ColumnDef,
+    convert: SimpleExpr,
+) -> Result<(), DbErr> {
+    manager
+        .alter_table(Table::alter().table(table).add_column(&mut new).to_owned())
+        .await?;
+    manager
+        .exec_stmt(
+            Query::update()
+                .table(table)
+                .value(Columns::MinorUnits, convert)
+                .to_owned(),
+        )
+        .await?;
+    manager
+        .alter_table(Table::alter().table(table).drop_column(column).to_owned())
+        .await?;
+    manager
+        .alter_table(
+            Table::alter()
+                .table(table)
+                .rename_column(Columns::MinorUnits, column)
+                .to_owned(),
+        )
+        .await
+}
@@ -0,0 +1,41 @@
+use sea_orm_migration::prelude::*;
+
+/// Timestamps used to be written as naive `YYYY-MM-DD HH:MM:SS.f` strings.
+/// Rewrites them as RFC 3339 UTC timestamps, the format the typed columns
+/// are stored in, so comparisons and ordering in SQL stay consistent.
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+const COLUMNS: [(&str, &str); 4] = [
+    ("budget", "created_at"),
+    ("budget", "updated_at"),
+    ("expense", "created_at"),
+    ("expense", "updated_at"),
+];
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let db = manager.get_connection();
+        for (table, column) in COLUMNS {
+            db.execute_unprepared(&format!(
+                "UPDATE {table} SET {column} = strftime('%Y-%m-%dT%H:%M:%f', {column}) || '+00:00' \
+                 WHERE {column} LIKE '____-__-__ __:__:__%'"
+            ))
+            .await?;
+        }
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let db = manager.get_connection();
+        for (table, column) in COLUMNS {
+            db.execute_unprepared(&format!(
+                "UPDATE {table} SET {column} = strftime('%Y-%m-%d %H:%M:%f', {column}) \
+                 WHERE {column} LIKE '____-__-__T%'"
+            ))
+            .await?;
+        }
+        Ok(())
+    }
+}
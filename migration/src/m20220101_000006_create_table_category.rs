@@ -0,0 +1,160 @@
+use sea_orm::Schema;
+use sea_orm_migration::prelude::*;
+
+pub mod category {
+    use crate::m20220101_000001_create_table_user::user;
+    use chrono::{DateTime, Utc};
+    use sea_orm::entity::prelude::*;
+    use sea_orm_migration::sea_orm;
+    use uuid::Uuid;
+
+    #[derive(Clone, Debug, PartialEq, Eq, DeriveEntityModel)]
+    #[sea_orm(table_name = "category")]
+    pub struct Model {
+        #[sea_orm(primary_key, auto_increment = false)]
+        pub id: Uuid,
+        pub user_id: Uuid,
+        pub parent_id: Option<Uuid>,
+        pub name: String,
+        pub created_at: DateTime<Utc>,
+        pub updated_at: DateTime<Utc>,
+    }
+
+    #[derive(Copy, Clone, Debug, EnumIter)]
+    pub enum Relation {
+        User,
+        Parent,
+    }
+
+    impl RelationTrait for Relation {
+        fn def(&self) -> RelationDef {
+            match self {
+                Self::User => Entity::belongs_to(user::Entity)
+                    .from(Column::UserId)
+                    .to(user::Column::Id)
+                    .into(),
+                Self::Parent => Entity::belongs_to(Entity)
+                    .from(Column::ParentId)
+                    .to(Column::Id)
+                    .into(),
+            }
+        }
+    }
+
+    impl Related<user::Entity> for Entity {
+        fn to() -> RelationDef {
+            Relation::User.def()
+        }
+    }
+
+    impl ActiveModelBehavior for ActiveModel {}
+}
+
+pub mod budget_category {
+    use super::category;
+    use crate::m20220101_000002_create_table_budget::budgets;
+    use sea_orm::entity::prelude::*;
+    use sea_orm_migration::sea_orm;
+    use uuid::Uuid;
+
+    #[derive(Clone, Debug, PartialEq, Eq, DeriveEntityModel)]
+    #[sea_orm(table_name = "budget_category")]
+    pub struct Model {
+        #[sea_orm(primary_key, auto_increment = false)]
+        pub budget_id: Uuid,
+        #[sea_orm(primary_key, auto_increment = false)]
+        pub category_id: Uuid,
+        /// Allocated amount in cents.
+        pub amount: i64,
+    }
+
+    #[derive(Copy, Clone, Debug, EnumIter)]
+    pub enum Relation {
+        Budget,
+        Category,
+    }
+
+    impl RelationTrait for Relation {
+        fn def(&self) -> RelationDef {
+            match self {
+                Self::Budget => Entity::belongs_to(budgets::Entity)
+                    .from(Column::BudgetId)
+                    .to(budgets::Column::Id)
+                    .into(),
+                Self::Category => Entity::belongs_to(category::Entity)
+                    .from(Column::CategoryId)
+                    .to(category::Column::Id)
+                    .into(),
+            }
+        }
+    }
+
+    impl Related<budgets::Entity> for Entity {
+        fn to() -> RelationDef {
+            Relation::Budget.def()
+        }
+    }
+
+    impl Related<category::Entity> for Entity {
+        fn to() -> RelationDef {
+            Relation::Category.def()
+        }
+    }
+
+    impl ActiveModelBehavior for ActiveModel {}
+}
+
+#[derive(Iden)]
+enum Expense {
+    Table,
+    CategoryId,
+}
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let builder = manager.get_database_backend();
+        let schema = Schema::new(builder);
+        manager
+            .create_table(schema.create_table_from_entity(category::Entity))
+            .await?;
+        manager
+            .create_table(schema.create_table_from_entity(budget_category::Entity))
+            .await?;
+        // SQLite cannot add a foreign key to an existing table, only a
+        // column that references one.
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Expense::Table)
+                    .add_column(
+                        ColumnDef::new(Expense::CategoryId)
+                            .uuid()
+                            .null()
+                            .extra("REFERENCES category (id)"),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Expense::Table)
+                    .drop_column(Expense::CategoryId)
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .drop_table(Table::drop().table(budget_category::Entity).to_owned())
+            .await?;
+        manager
+            .drop_table(Table::drop().table(category::Entity).to_owned())
+            .await
+    }
+}
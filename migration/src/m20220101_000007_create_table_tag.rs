@@ -0,0 +1,142 @@
+use sea_orm::Schema;
+use sea_orm_migration::prelude::*;
+
+pub mod tag {
+    use crate::m20220101_000001_create_table_user::user;
+    use chrono::{DateTime, Utc};
+    use sea_orm::entity::prelude::*;
+    use sea_orm_migration::sea_orm;
+    use uuid::Uuid;
+
+    #[derive(Clone, Debug, PartialEq, Eq, DeriveEntityModel)]
+    #[sea_orm(table_name = "tag")]
+    pub struct Model {
+        #[sea_orm(primary_key, auto_increment = false)]
+        pub id: Uuid,
+        pub user_id: Uuid,
+        pub name: String,
+        pub created_at: DateTime<Utc>,
+    }
+
+    #[derive(Copy, Clone, Debug, EnumIter)]
+    pub enum Relation {
+        User,
+    }
+
+    impl RelationTrait for Relation {
+        fn def(&self) -> RelationDef {
+            match self {
+                Self::User => Entity::belongs_to(user::Entity)
+                    .from(Column::UserId)
+                    .to(user::Column::Id)
+                    .into(),
+            }
+        }
+    }
+
+    impl Related<user::Entity> for Entity {
+        fn to() -> RelationDef {
+            Relation::User.def()
+        }
+    }
+
+    impl ActiveModelBehavior for ActiveModel {}
+}
+
+pub mod expense_tag {
+    use super::tag;
+    use crate::m20220101_000003_create_table_expense::expense;
+    use sea_orm::entity::prelude::*;
+    use sea_orm_migration::sea_orm;
+    use uuid::Uuid;
+
+    #[derive(Clone, Debug, PartialEq, Eq, DeriveEntityModel)]
+    #[sea_orm(table_name = "expense_tag")]
+    pub struct Model {
+        #[sea_orm(primary_key, auto_increment = false)]
+        pub expense_id: Uuid,
+        #[sea_orm(primary_key, auto_increment = false)]
+        pub tag_id: Uuid,
+    }
+
+    #[derive(Copy, Clone, Debug, EnumIter)]
+    pub enum Relation {
+        Expense,
+        Tag,
+    }
+
+    impl RelationTrait for Relation {
+        fn def(&self) -> RelationDef {
+            match self {
+                Self::Expense => Entity::belongs_to(expense::Entity)
+                    .from(Column::ExpenseId)
+                    .to(expense::Column::Id)
+                    .into(),
+                Self::Tag => Entity::belongs_to(tag::Entity)
+                    .from(Column::TagId)
+                    .to(tag::Column::Id)
+                    .into(),
+            }
+        }
+    }
+
+    impl Related<expense::Entity> for Entity {
+        fn to() -> RelationDef {
+            Relation::Expense.def()
+        }
+    }
+
+    impl Related<tag::Entity> for Entity {
+        fn to() -> RelationDef {
+            Relation::Tag.def()
+        }
+    }
+
+    impl ActiveModelBehavior for ActiveModel {}
+}
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let builder = manager.get_database_backend();
+        let schema = Schema::new(builder);
+        manager
+            .create_table(schema.create_table_from_entity(tag::Entity))
+            .await?;
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-tag-user_id-name")
+                    .table(tag::Entity)
+                    .col(tag::Column::UserId)
+                    .col(tag::Column::Name)
+                    .unique()
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_table(schema.create_table_from_entity(expense_tag::Entity))
+            .await?;
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-expense_tag-tag_id")
+                    .table(expense_tag::Entity)
+                    .col(expense_tag::Column::TagId)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(expense_tag::Entity).to_owned())
+            .await?;
+        manager
+            .drop_table(Table::drop().table(tag::Entity).to_owned())
+            .await
+    }
+}
@@ -0,0 +1,84 @@
+use sea_orm_migration::prelude::*;
+
+/// Gives every budget a period. Existing budgets become monthly budgets
+/// starting on the day they were created, without rollover.
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[derive(Iden)]
+enum Budget {
+    Table,
+    Period,
+    StartsOn,
+    EndsOn,
+    Rollover,
+    CreatedAt,
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // SQLite only adds one column per statement.
+        for mut column in [
+            ColumnDef::new(Budget::Period)
+                .string_len(16)
+                .not_null()
+                .default("monthly")
+                .to_owned(),
+            ColumnDef::new(Budget::StartsOn)
+                .date()
+                .not_null()
+                .default("1970-01-01")
+                .to_owned(),
+            ColumnDef::new(Budget::EndsOn).date().null().to_owned(),
+            ColumnDef::new(Budget::Rollover)
+                .boolean()
+                .not_null()
+                .default(false)
+                .to_owned(),
+        ] {
+            manager
+                .alter_table(
+                    Table::alter()
+                        .table(Budget::Table)
+                        .add_column(&mut column)
+                        .to_owned(),
+                )
+                .await?;
+        }
+
+        manager
+            .exec_stmt(
+                Query::update()
+                    .table(Budget::Table)
+                    .value(
+                        Budget::StartsOn,
+                        Func::cust(Alias::new("substr"))
+                            .arg(Expr::col(Budget::CreatedAt))
+                            .arg(1)
+                            .arg(10),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        for column in [
+            Budget::Period,
+            Budget::StartsOn,
+            Budget::EndsOn,
+            Budget::Rollover,
+        ] {
+            manager
+                .alter_table(
+                    Table::alter()
+                        .table(Budget::Table)
+                        .drop_column(column)
+                        .to_owned(),
+                )
+                .await?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,100 @@
+use sea_orm::Schema;
+use sea_orm_migration::prelude::*;
+
+pub mod exchange_rate {
+    use chrono::NaiveDate;
+    use sea_orm::entity::prelude::*;
+    use sea_orm_migration::sea_orm;
+
+    #[derive(Clone, Debug, PartialEq, Eq, DeriveEntityModel)]
+    #[sea_orm(table_name = "exchange_rate")]
+    pub struct Model {
+        #[sea_orm(primary_key, auto_increment = false)]
+        pub base_currency: String,
+        #[sea_orm(primary_key, auto_increment = false)]
+        pub quote_currency: String,
+        #[sea_orm(primary_key, auto_increment = false)]
+        pub effective_on: NaiveDate,
+        /// Scaled by 10^10.
+        pub rate: i64,
+    }
+
+    #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+    pub enum Relation {}
+
+    impl ActiveModelBehavior for ActiveModel {}
+}
+
+/// Gives budgets and expenses a currency and adds the exchange rate table.
+/// Existing amounts are assumed to be in US dollars.
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[derive(Iden)]
+enum Budget {
+    Table,
+    Currency,
+}
+
+#[derive(Iden)]
+enum Expense {
+    Table,
+    Currency,
+}
+
+fn currency<T: Iden + 'static>(column: T) -> ColumnDef {
+    ColumnDef::new(column)
+        .string_len(3)
+        .not_null()
+        .default("USD")
+        .to_owned()
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Budget::Table)
+                    .add_column(&mut currency(Budget::Currency))
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Expense::Table)
+                    .add_column(&mut currency(Expense::Currency))
+                    .to_owned(),
+            )
+            .await?;
+
+        let schema = Schema::new(manager.get_database_backend());
+        manager
+            .create_table(schema.create_table_from_entity(exchange_rate::Entity))
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(exchange_rate::Entity).to_owned())
+            .await?;
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Expense::Table)
+                    .drop_column(Expense::Currency)
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Budget::Table)
+                    .drop_column(Budget::Currency)
+                    .to_owned(),
+            )
+            .await
+    }
+}
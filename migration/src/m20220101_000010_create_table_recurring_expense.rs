@@ -0,0 +1,122 @@
+use sea_orm::Schema;
+use sea_orm_migration::prelude::*;
+
+pub mod recurring_expense {
+    use crate::{
+        m20220101_000002_create_table_budget::budgets,
+        m20220101_000006_create_table_category::category,
+    };
+    use chrono::{DateTime, NaiveDate, Utc};
+    use sea_orm::entity::prelude::*;
+    use sea_orm_migration::sea_orm;
+    use uuid::Uuid;
+
+    #[derive(Clone, Debug, PartialEq, Eq, DeriveEntityModel)]
+    #[sea_orm(table_name = "recurring_expense")]
+    pub struct Model {
+        #[sea_orm(primary_key, auto_increment = false)]
+        pub id: Uuid,
+        pub budget_id: Uuid,
+        pub amount: i64,
+        #[sea_orm(column_type = "String(Some(3))")]
+        pub currency: String,
+        pub description: String,
+        pub category_id: Option<Uuid>,
+        #[sea_orm(column_type = "String(Some(16))")]
+        pub frequency: String,
+        pub interval: i32,
+        pub starts_on: NaiveDate,
+        pub ends_on: Option<NaiveDate>,
+        pub count: Option<i32>,
+        pub occurrences: i32,
+        pub next_occurrence: Option<NaiveDate>,
+        pub created_at: DateTime<Utc>,
+        pub updated_at: DateTime<Utc>,
+    }
+
+    #[derive(Copy, Clone, Debug, EnumIter)]
+    pub enum Relation {
+        Budget,
+        Category,
+    }
+
+    impl RelationTrait for Relation {
+        fn def(&self) -> RelationDef {
+            match self {
+                Self::Budget => Entity::belongs_to(budgets::Entity)
+                    .from(Column::BudgetId)
+                    .to(budgets::Column::Id)
+                    .into(),
+                Self::Category => Entity::belongs_to(category::Entity)
+                    .from(Column::CategoryId)
+                    .to(category::Column::Id)
+                    .into(),
+            }
+        }
+    }
+
+    impl Related<budgets::Entity> for Entity {
+        fn to() -> RelationDef {
+            Relation::Budget.def()
+        }
+    }
+
+    impl ActiveModelBehavior for ActiveModel {}
+}
+
+/// Adds recurring expense templates and links the expenses created from
+/// them back to their template.
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[derive(Iden)]
+enum Expense {
+    Table,
+    RecurringExpenseId,
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let schema = Schema::new(manager.get_database_backend());
+        manager
+            .create_table(schema.create_table_from_entity(recurring_expense::Entity))
+            .await?;
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-recurring_expense-next_occurrence")
+                    .table(recurring_expense::Entity)
+                    .col(recurring_expense::Column::NextOccurrence)
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Expense::Table)
+                    .add_column(
+                        ColumnDef::new(Expense::RecurringExpenseId)
+                            .uuid()
+                            .null()
+                            .extra("REFERENCES recurring_expense (id)"),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Expense::Table)
+                    .drop_column(Expense::RecurringExpenseId)
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .drop_table(Table::drop().table(recurring_expense::Entity).to_owned())
+            .await
+    }
+}
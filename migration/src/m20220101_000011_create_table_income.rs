@@ -0,0 +1,86 @@
+use sea_orm::Schema;
+use sea_orm_migration::prelude::*;
+
+pub mod income {
+    use crate::{
+        m20220101_000001_create_table_user::user, m20220101_000002_create_table_budget::budgets,
+    };
+    use chrono::{DateTime, NaiveDate, Utc};
+    use sea_orm::entity::prelude::*;
+    use sea_orm_migration::sea_orm;
+    use uuid::Uuid;
+
+    #[derive(Clone, Debug, PartialEq, Eq, DeriveEntityModel)]
+    #[sea_orm(table_name = "income")]
+    pub struct Model {
+        #[sea_orm(primary_key, auto_increment = false)]
+        pub id: Uuid,
+        pub user_id: Uuid,
+        pub budget_id: Option<Uuid>,
+        pub source: String,
+        pub amount: i64,
+        #[sea_orm(column_type = "String(Some(3))")]
+        pub currency: String,
+        pub date: NaiveDate,
+        pub created_at: DateTime<Utc>,
+        pub updated_at: DateTime<Utc>,
+    }
+
+    #[derive(Copy, Clone, Debug, EnumIter)]
+    pub enum Relation {
+        User,
+        Budget,
+    }
+
+    impl RelationTrait for Relation {
+        fn def(&self) -> RelationDef {
+            match self {
+                Self::User => Entity::belongs_to(user::Entity)
+                    .from(Column::UserId)
+                    .to(user::Column::Id)
+                    .into(),
+                Self::Budget => Entity::belongs_to(budgets::Entity)
+                    .from(Column::BudgetId)
+                    .to(budgets::Column::Id)
+                    .into(),
+            }
+        }
+    }
+
+    impl Related<user::Entity> for Entity {
+        fn to() -> RelationDef {
+            Relation::User.def()
+        }
+    }
+
+    impl ActiveModelBehavior for ActiveModel {}
+}
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let schema = Schema::new(manager.get_database_backend());
+        manager
+            .create_table(schema.create_table_from_entity(income::Entity))
+            .await?;
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-income-user_id-date")
+                    .table(income::Entity)
+                    .col(income::Column::UserId)
+                    .col(income::Column::Date)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(income::Entity).to_owned())
+            .await
+    }
+}
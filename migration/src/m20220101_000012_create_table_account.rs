@@ -0,0 +1,185 @@
+use sea_orm::Schema;
+use sea_orm_migration::prelude::*;
+
+pub mod account {
+    use crate::m20220101_000001_create_table_user::user;
+    use chrono::{DateTime, NaiveDate, Utc};
+    use sea_orm::entity::prelude::*;
+    use sea_orm_migration::sea_orm;
+    use uuid::Uuid;
+
+    #[derive(Clone, Debug, PartialEq, Eq, DeriveEntityModel)]
+    #[sea_orm(table_name = "account")]
+    pub struct Model {
+        #[sea_orm(primary_key, auto_increment = false)]
+        pub id: Uuid,
+        pub user_id: Uuid,
+        pub name: String,
+        #[sea_orm(column_type = "String(Some(16))")]
+        pub kind: String,
+        #[sea_orm(column_type = "String(Some(3))")]
+        pub currency: String,
+        pub opening_balance: i64,
+        pub opened_on: NaiveDate,
+        pub created_at: DateTime<Utc>,
+        pub updated_at: DateTime<Utc>,
+    }
+
+    #[derive(Copy, Clone, Debug, EnumIter)]
+    pub enum Relation {
+        User,
+    }
+
+    impl RelationTrait for Relation {
+        fn def(&self) -> RelationDef {
+            match self {
+                Self::User => Entity::belongs_to(user::Entity)
+                    .from(Column::UserId)
+                    .to(user::Column::Id)
+                    .into(),
+            }
+        }
+    }
+
+    impl Related<user::Entity> for Entity {
+        fn to() -> RelationDef {
+            Relation::User.def()
+        }
+    }
+
+    impl ActiveModelBehavior for ActiveModel {}
+}
+
+pub mod transfer {
+    use super::account;
+    use crate::m20220101_000001_create_table_user::user;
+    use chrono::{DateTime, NaiveDate, Utc};
+    use sea_orm::entity::prelude::*;
+    use sea_orm_migration::sea_orm;
+    use uuid::Uuid;
+
+    #[derive(Clone, Debug, PartialEq, Eq, DeriveEntityModel)]
+    #[sea_orm(table_name = "transfer")]
+    pub struct Model {
+        #[sea_orm(primary_key, auto_increment = false)]
+        pub id: Uuid,
+        pub user_id: Uuid,
+        pub from_account_id: Uuid,
+        pub to_account_id: Uuid,
+        pub amount: i64,
+        pub to_amount: i64,
+        pub description: String,
+        pub date: NaiveDate,
+        pub created_at: DateTime<Utc>,
+        pub updated_at: DateTime<Utc>,
+    }
+
+    #[derive(Copy, Clone, Debug, EnumIter)]
+    pub enum Relation {
+        User,
+        FromAccount,
+        ToAccount,
+    }
+
+    impl RelationTrait for Relation {
+        fn def(&self) -> RelationDef {
+            match self {
+                Self::User => Entity::belongs_to(user::Entity)
+                    .from(Column::UserId)
+                    .to(user::Column::Id)
+                    .into(),
+                Self::FromAccount => Entity::belongs_to(account::Entity)
+                    .from(Column::FromAccountId)
+                    .to(account::Column::Id)
+                    .into(),
+                Self::ToAccount => Entity::belongs_to(account::Entity)
+                    .from(Column::ToAccountId)
+                    .to(account::Column::Id)
+                    .into(),
+            }
+        }
+    }
+
+    impl ActiveModelBehavior for ActiveModel {}
+}
+
+/// Adds accounts and transfers between them, and lets expenses and income
+/// name the account they were paid from or into.
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[derive(Iden)]
+enum Expense {
+    Table,
+    AccountId,
+}
+
+#[derive(Iden)]
+enum Income {
+    Table,
+    AccountId,
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let schema = Schema::new(manager.get_database_backend());
+        manager
+            .create_table(schema.create_table_from_entity(account::Entity))
+            .await?;
+        manager
+            .create_table(schema.create_table_from_entity(transfer::Entity))
+            .await?;
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Expense::Table)
+                    .add_column(
+                        ColumnDef::new(Expense::AccountId)
+                            .uuid()
+                            .null()
+                            .extra("REFERENCES account (id)"),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Income::Table)
+                    .add_column(
+                        ColumnDef::new(Income::AccountId)
+                            .uuid()
+                            .null()
+                            .extra("REFERENCES account (id)"),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Income::Table)
+                    .drop_column(Income::AccountId)
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Expense::Table)
+                    .drop_column(Expense::AccountId)
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .drop_table(Table::drop().table(transfer::Entity).to_owned())
+            .await?;
+        manager
+            .drop_table(Table::drop().table(account::Entity).to_owned())
+            .await
+    }
+}
@@ -0,0 +1,92 @@
+use sea_orm::Schema;
+use sea_orm_migration::prelude::*;
+
+pub mod expense_split {
+    use crate::{
+        m20220101_000002_create_table_budget::budgets,
+        m20220101_000003_create_table_expense::expense,
+        m20220101_000006_create_table_category::category,
+    };
+    use sea_orm::entity::prelude::*;
+    use sea_orm_migration::sea_orm;
+    use uuid::Uuid;
+
+    #[derive(Clone, Debug, PartialEq, Eq, DeriveEntityModel)]
+    #[sea_orm(table_name = "expense_split")]
+    pub struct Model {
+        #[sea_orm(primary_key, auto_increment = false)]
+        pub id: Uuid,
+        pub expense_id: Uuid,
+        pub budget_id: Uuid,
+        pub category_id: Option<Uuid>,
+        pub amount: i64,
+        pub position: i32,
+    }
+
+    #[derive(Copy, Clone, Debug, EnumIter)]
+    pub enum Relation {
+        Expense,
+        Budget,
+        Category,
+    }
+
+    impl RelationTrait for Relation {
+        fn def(&self) -> RelationDef {
+            match self {
+                Self::Expense => Entity::belongs_to(expense::Entity)
+                    .from(Column::ExpenseId)
+                    .to(expense::Column::Id)
+                    .into(),
+                Self::Budget => Entity::belongs_to(budgets::Entity)
+                    .from(Column::BudgetId)
+                    .to(budgets::Column::Id)
+                    .into(),
+                Self::Category => Entity::belongs_to(category::Entity)
+                    .from(Column::CategoryId)
+                    .to(category::Column::Id)
+                    .into(),
+            }
+        }
+    }
+
+    impl ActiveModelBehavior for ActiveModel {}
+}
+
+/// Lets an expense be split into lines charged to different budgets and
+/// categories.
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let schema = Schema::new(manager.get_database_backend());
+        manager
+            .create_table(schema.create_table_from_entity(expense_split::Entity))
+            .await?;
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-expense_split-expense_id")
+                    .table(expense_split::Entity)
+                    .col(expense_split::Column::ExpenseId)
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-expense_split-budget_id")
+                    .table(expense_split::Entity)
+                    .col(expense_split::Column::BudgetId)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(expense_split::Entity).to_owned())
+            .await
+    }
+}
@@ -0,0 +1,465 @@
+use actix_web::{web, HttpResponse};
+use chrono::{DateTime, NaiveDate, Utc};
+use entities::{account, expense, income, money::Money, transfer};
+use sea_orm::{
+    entity::*, sea_query::Expr, Condition, ConnectionTrait, DbErr, QueryFilter, QueryOrder,
+    QuerySelect, TransactionTrait,
+};
+use serde::Serialize;
+use uuid::Uuid;
+
+use crate::utility::{
+    cache::{self, CacheKey},
+    currency::{self, Rates},
+    db_structs::{BalanceQuery, LedgerQuery, NewAccount, UpdateAccount},
+    error::AppError,
+    pagination,
+    state::AppState,
+    validation::ValidatedJson,
+};
+
+/// An account's balance at the end of a day.
+#[derive(Serialize)]
+struct Balance {
+    account_id: Uuid,
+    currency: String,
+    on: NaiveDate,
+    balance: Money,
+}
+
+#[derive(Clone, Copy, Serialize)]
+#[serde(rename_all = "snake_case")]
+enum EntryKind {
+    Expense,
+    Income,
+    TransferOut,
+    TransferIn,
+}
+
+/// One movement of money in or out of an account.
+#[derive(Serialize)]
+struct LedgerEntry {
+    /// Id of the expense, income or transfer.
+    id: Uuid,
+    kind: EntryKind,
+    date: NaiveDate,
+    description: String,
+    /// In the account's currency; negative for money going out.
+    amount: Money,
+    /// The account's balance after this entry.
+    balance: Money,
+    #[serde(skip)]
+    created_at: DateTime<Utc>,
+}
+
+#[derive(Serialize)]
+struct Ledger {
+    account_id: Uuid,
+    currency: String,
+    from: NaiveDate,
+    to: NaiveDate,
+    /// Balance at the end of the day before `from`.
+    opening_balance: Money,
+    /// Balance at the end of `to`.
+    closing_balance: Money,
+    /// Oldest first.
+    entries: Vec<LedgerEntry>,
+}
+
+async fn find_owned<C: ConnectionTrait>(
+    db: &C,
+    user_id: Uuid,
+    account_id: Uuid,
+) -> Result<Option<account::Model>, DbErr> {
+    account::Entity::find_by_id(account_id)
+        .filter(account::Column::UserId.eq(user_id))
+        .one(db)
+        .await
+}
+
+/// Loads one of the user's accounts named in the request body under `field`.
+pub(crate) async fn account_field<C: ConnectionTrait>(
+    db: &C,
+    user_id: Uuid,
+    field: &str,
+    account_id: Uuid,
+) -> Result<account::Model, AppError> {
+    find_owned(db, user_id, account_id)
+        .await?
+        .ok_or_else(|| AppError::field(field, "does not name one of your accounts"))
+}
+
+/// Checks that `account_id`, if given, names one of the user's accounts.
+pub(crate) async fn check_account_field<C: ConnectionTrait>(
+    db: &C,
+    user_id: Uuid,
+    field: &str,
+    account_id: Option<Uuid>,
+) -> Result<(), AppError> {
+    if let Some(account_id) = account_id {
+        account_field(db, user_id, field, account_id).await?;
+    }
+    Ok(())
+}
+
+/// The balance of `account` at the end of `on`: its opening balance plus
+/// money in minus money out up to and including that day. Expenses and income
+/// in other currencies are converted at the rate of their date.
+async fn balance_on<C: ConnectionTrait>(
+    db: &C,
+    account: &account::Model,
+    on: NaiveDate,
+) -> Result<Money, AppError> {
+    let spending: Vec<(NaiveDate, String, Money)> = expense::Entity::find()
+        .select_only()
+        .column(expense::Column::Date)
+        .column(expense::Column::Currency)
+        .column_as(expense::Column::Amount.sum(), "spent")
+        .filter(expense::Column::AccountId.eq(account.id))
+        .filter(expense::Column::Date.lte(on))
+        .group_by(expense::Column::Date)
+        .group_by(expense::Column::Currency)
+        .into_tuple()
+        .all(db)
+        .await?;
+    let earnings: Vec<(NaiveDate, String, Money)> = income::Entity::find()
+        .select_only()
+        .column(income::Column::Date)
+        .column(income::Column::Currency)
+        .column_as(income::Column::Amount.sum(), "earned")
+        .filter(income::Column::AccountId.eq(account.id))
+        .filter(income::Column::Date.lte(on))
+        .group_by(income::Column::Date)
+        .group_by(income::Column::Currency)
+        .into_tuple()
+        .all(db)
+        .await?;
+    let transferred_out: Option<Money> = transfer::Entity::find()
+        .select_only()
+        .column_as(transfer::Column::Amount.sum(), "amount")
+        .filter(transfer::Column::FromAccountId.eq(account.id))
+        .filter(transfer::Column::Date.lte(on))
+        .into_tuple()
+        .one(db)
+        .await?
+        .flatten();
+    let transferred_in: Option<Money> = transfer::Entity::find()
+        .select_only()
+        .column_as(transfer::Column::ToAmount.sum(), "amount")
+        .filter(transfer::Column::ToAccountId.eq(account.id))
+        .filter(transfer::Column::Date.lte(on))
+        .into_tuple()
+        .one(db)
+        .await?
+        .flatten();
+
+    let rates = Rates::load(
+        db,
+        &account.currency,
+        spending
+            .iter()
+            .chain(&earnings)
+            .map(|(_, currency, _)| currency),
+    )
+    .await?;
+
+    let mut balance = account.opening_balance + transferred_in.unwrap_or(Money::ZERO)
+        - transferred_out.unwrap_or(Money::ZERO);
+    for (date, currency, amount) in earnings {
+        balance += rates.convert(amount, &currency, date)?;
+    }
+    for (date, currency, amount) in spending {
+        balance -= rates.convert(amount, &currency, date)?;
+    }
+    Ok(balance)
+}
+
+pub(super) async fn get_accounts(
+    user_id: web::ReqData<Uuid>,
+    state: web::Data<AppState>,
+) -> Result<HttpResponse, AppError> {
+    let accounts = account::Entity::find()
+        .filter(account::Column::UserId.eq(user_id.into_inner()))
+        .order_by_asc(account::Column::Name)
+        .all(&state.db)
+        .await?;
+
+    Ok(HttpResponse::Ok().json(accounts))
+}
+
+pub(super) async fn get_account(
+    user_id: web::ReqData<Uuid>,
+    account_id: web::Path<Uuid>,
+    state: web::Data<AppState>,
+) -> Result<HttpResponse, AppError> {
+    let account = find_owned(&state.db, *user_id, *account_id)
+        .await?
+        .ok_or(AppError::NotFound("account"))?;
+
+    Ok(HttpResponse::Ok().json(account))
+}
+
+pub(super) async fn post_account(
+    user_id: web::ReqData<Uuid>,
+    state: web::Data<AppState>,
+    form: ValidatedJson<NewAccount>,
+) -> Result<HttpResponse, AppError> {
+    let form = form.into_inner();
+    let now = state.clock.now();
+
+    let account = account::ActiveModel {
+        id: Set(Uuid::new_v4()),
+        user_id: Set(user_id.into_inner()),
+        name: Set(form.name),
+        kind: Set(form.kind),
+        currency: Set(form
+            .currency
+            .unwrap_or_else(|| currency::DEFAULT_CURRENCY.to_string())),
+        opening_balance: Set(form.opening_balance),
+        opened_on: Set(form.opened_on.unwrap_or_else(|| now.date_naive())),
+        created_at: Set(now),
+        updated_at: Set(now),
+    }
+    .insert(&state.db)
+    .await?;
+
+    Ok(HttpResponse::Ok().json(account))
+}
+
+pub(super) async fn update_account(
+    user_id: web::ReqData<Uuid>,
+    account_id: web::Path<Uuid>,
+    state: web::Data<AppState>,
+    form: ValidatedJson<UpdateAccount>,
+) -> Result<HttpResponse, AppError> {
+    let account = find_owned(&state.db, *user_id, *account_id)
+        .await?
+        .ok_or(AppError::NotFound("account"))?;
+    let mut account: account::ActiveModel = account.into();
+    let form = form.into_inner();
+
+    if let Some(name) = form.name {
+        account.name = Set(name);
+    }
+
+    if let Some(kind) = form.kind {
+        account.kind = Set(kind);
+    }
+
+    if let Some(opening_balance) = form.opening_balance {
+        account.opening_balance = Set(opening_balance);
+    }
+
+    if let Some(opened_on) = form.opened_on {
+        account.opened_on = Set(opened_on);
+    }
+
+    account.updated_at = Set(state.clock.now());
+    let account = account.update(&state.db).await?;
+
+    Ok(HttpResponse::Ok().json(account))
+}
+
+/// Deletes an account that no transfer refers to; while any do, this fails
+/// with a conflict. Its expenses and income are kept but no longer belong to
+/// an account.
+pub(super) async fn delete_account(
+    user_id: web::ReqData<Uuid>,
+    account_id: web::Path<Uuid>,
+    state: web::Data<AppState>,
+) -> Result<HttpResponse, AppError> {
+    let account = find_owned(&state.db, *user_id, *account_id)
+        .await?
+        .ok_or(AppError::NotFound("account"))?;
+
+    // Transfers are history for the other account too, so they are never
+    // deleted along with this one.
+    let has_transfers = transfer::Entity::find()
+        .filter(
+            Condition::any()
+                .add(transfer::Column::FromAccountId.eq(account.id))
+                .add(transfer::Column::ToAccountId.eq(account.id)),
+        )
+        .one(&state.db)
+        .await?
+        .is_some();
+    if has_transfers {
+        return Err(AppError::Conflict("delete its transfers first".to_string()));
+    }
+
+    let affected: Vec<(Uuid, Uuid)> = expense::Entity::find()
+        .select_only()
+        .column(expense::Column::BudgetId)
+        .column(expense::Column::Id)
+        .filter(expense::Column::AccountId.eq(account.id))
+        .into_tuple()
+        .all(&state.db)
+        .await?;
+    for (budget_id, expense_id) in affected {
+        cache::invalidate(
+            state.cache.as_ref(),
+            &CacheKey::expense(&state.settings.cache, *user_id, budget_id, expense_id),
+        )
+        .await;
+    }
+
+    state
+        .db
+        .transaction::<_, _, DbErr>(|txn| {
+            Box::pin(async move {
+                expense::Entity::update_many()
+                    .col_expr(
+                        expense::Column::AccountId,
+                        Expr::value(Option::<Uuid>::None),
+                    )
+                    .filter(expense::Column::AccountId.eq(account.id))
+                    .exec(txn)
+                    .await?;
+                income::Entity::update_many()
+                    .col_expr(income::Column::AccountId, Expr::value(Option::<Uuid>::None))
+                    .filter(income::Column::AccountId.eq(account.id))
+                    .exec(txn)
+                    .await?;
+                account::Entity::delete_by_id(account.id).exec(txn).await
+            })
+        })
+        .await?;
+
+    Ok(HttpResponse::Ok().finish())
+}
+
+pub(super) async fn get_account_balance(
+    user_id: web::ReqData<Uuid>,
+    account_id: web::Path<Uuid>,
+    query: web::Query<BalanceQuery>,
+    state: web::Data<AppState>,
+) -> Result<HttpResponse, AppError> {
+    let account = find_owned(&state.db, *user_id, *account_id)
+        .await?
+        .ok_or(AppError::NotFound("account"))?;
+    let on = query.on.unwrap_or_else(|| state.clock.now().date_naive());
+    let balance = balance_on(&state.db, &account, on).await?;
+
+    Ok(HttpResponse::Ok().json(Balance {
+        account_id: account.id,
+        currency: account.currency,
+        on,
+        balance,
+    }))
+}
+
+/// The account's expenses, income and transfers between two dates, each with
+/// the balance it left the account at.
+pub(super) async fn get_account_ledger(
+    user_id: web::ReqData<Uuid>,
+    account_id: web::Path<Uuid>,
+    query: web::Query<LedgerQuery>,
+    state: web::Data<AppState>,
+) -> Result<HttpResponse, AppError> {
+    let account = find_owned(&state.db, *user_id, *account_id)
+        .await?
+        .ok_or(AppError::NotFound("account"))?;
+    let from = query.from.unwrap_or(account.opened_on);
+    let to = query.to.unwrap_or_else(|| state.clock.now().date_naive());
+    pagination::check_range(Some(from), Some(to), "to", "must not be before from")?;
+
+    let opening_balance = match from.pred_opt() {
+        Some(before) => balance_on(&state.db, &account, before).await?,
+        None => account.opening_balance,
+    };
+
+    let expenses = expense::Entity::find()
+        .filter(expense::Column::AccountId.eq(account.id))
+        .filter(expense::Column::Date.between(from, to))
+        .all(&state.db)
+        .await?;
+    let incomes = income::Entity::find()
+        .filter(income::Column::AccountId.eq(account.id))
+        .filter(income::Column::Date.between(from, to))
+        .all(&state.db)
+        .await?;
+    let transfers = transfer::Entity::find()
+        .filter(
+            Condition::any()
+                .add(transfer::Column::FromAccountId.eq(account.id))
+                .add(transfer::Column::ToAccountId.eq(account.id)),
+        )
+        .filter(transfer::Column::Date.between(from, to))
+        .all(&state.db)
+        .await?;
+
+    let rates = Rates::load(
+        &state.db,
+        &account.currency,
+        expenses
+            .iter()
+            .map(|e| &e.currency)
+            .chain(incomes.iter().map(|i| &i.currency)),
+    )
+    .await?;
+
+    let mut entries = Vec::with_capacity(expenses.len() + incomes.len() + transfers.len());
+    let entry = |id, kind, date, description, amount, created_at| LedgerEntry {
+        id,
+        kind,
+        date,
+        description,
+        amount,
+        balance: Money::ZERO,
+        created_at,
+    };
+    for e in expenses {
+        let amount = rates.convert(e.amount, &e.currency, e.date)?;
+        entries.push(entry(
+            e.id,
+            EntryKind::Expense,
+            e.date,
+            e.description,
+            -amount,
+            e.created_at,
+        ));
+    }
+    for i in incomes {
+        let amount = rates.convert(i.amount, &i.currency, i.date)?;
+        entries.push(entry(
+            i.id,
+            EntryKind::Income,
+            i.date,
+            i.source,
+            amount,
+            i.created_at,
+        ));
+    }
+    for t in transfers {
+        let (kind, amount) = if t.from_account_id == account.id {
+            (EntryKind::TransferOut, -t.amount)
+        } else {
+            (EntryKind::TransferIn, t.to_amount)
+        };
+        entries.push(entry(
+            t.id,
+            kind,
+            t.date,
+            t.description,
+            amount,
+            t.created_at,
+        ));
+    }
+    entries.sort_by_key(|e| (e.date, e.created_at, e.id));
+
+    let mut running = opening_balance;
+    for entry in &mut entries {
+        running += entry.amount;
+        entry.balance = running;
+    }
+
+    Ok(HttpResponse::Ok().json(Ledger {
+        account_id: account.id,
+        currency: account.currency,
+        from,
+        to,
+        opening_balance,
+        closing_balance: running,
+        entries,
+    }))
+}
@@ -0,0 +1,137 @@
+use actix_web::{web, HttpResponse};
+use entities::{api_key, sea_orm_active_enums::ApiKeyScope};
+use sea_orm::{entity::*, QueryFilter, QueryOrder};
+use serde::Serialize;
+use uuid::Uuid;
+
+use crate::{
+    middleware::auth::AccessTokenId,
+    utility::{
+        db_structs::{NewApiKey, UpdateApiKey},
+        error::AppError,
+        state::AppState,
+        token::{hash_token, new_api_key},
+        validation::ValidatedJson,
+    },
+};
+
+/// Characters of a key kept in `prefix`: `pbk_` and the first eight random
+/// ones.
+const PREFIX_LEN: usize = 12;
+
+/// A newly created key. This is the only time `key` is shown.
+#[derive(Serialize)]
+struct CreatedApiKey {
+    #[serde(flatten)]
+    api_key: api_key::Model,
+    key: String,
+}
+
+async fn find_owned(
+    state: &AppState,
+    user_id: Uuid,
+    api_key_id: Uuid,
+) -> Result<api_key::Model, AppError> {
+    api_key::Entity::find_by_id(api_key_id)
+        .filter(api_key::Column::UserId.eq(user_id))
+        .one(&state.db)
+        .await?
+        .ok_or(AppError::NotFound("API key"))
+}
+
+// Keys are managed only after logging in, so a leaked key cannot be used to
+// create others or to hide that it was used.
+
+pub(super) async fn get_api_keys(
+    _session: AccessTokenId,
+    user_id: web::ReqData<Uuid>,
+    state: web::Data<AppState>,
+) -> Result<HttpResponse, AppError> {
+    let api_keys = api_key::Entity::find()
+        .filter(api_key::Column::UserId.eq(user_id.into_inner()))
+        .order_by_asc(api_key::Column::CreatedAt)
+        .order_by_asc(api_key::Column::Id)
+        .all(&state.db)
+        .await?;
+
+    Ok(HttpResponse::Ok().json(api_keys))
+}
+
+pub(super) async fn get_api_key(
+    _session: AccessTokenId,
+    user_id: web::ReqData<Uuid>,
+    api_key_id: web::Path<Uuid>,
+    state: web::Data<AppState>,
+) -> Result<HttpResponse, AppError> {
+    let api_key = find_owned(&state, *user_id, *api_key_id).await?;
+
+    Ok(HttpResponse::Ok().json(api_key))
+}
+
+pub(super) async fn post_api_key(
+    _session: AccessTokenId,
+    user_id: web::ReqData<Uuid>,
+    state: web::Data<AppState>,
+    form: ValidatedJson<NewApiKey>,
+) -> Result<HttpResponse, AppError> {
+    let form = form.into_inner();
+    let now = state.clock.now();
+    if form.expires_at.is_some_and(|expires_at| expires_at <= now) {
+        return Err(AppError::field("expires_at", "must be in the future"));
+    }
+
+    let key = new_api_key();
+    let api_key = api_key::ActiveModel {
+        id: Set(Uuid::new_v4()),
+        user_id: Set(user_id.into_inner()),
+        name: Set(form.name),
+        prefix: Set(key[..PREFIX_LEN].to_string()),
+        key_hash: Set(hash_token(&key)),
+        scope: Set(form.scope.unwrap_or(ApiKeyScope::Read)),
+        expires_at: Set(form.expires_at),
+        last_used_at: Set(None),
+        created_at: Set(now),
+    }
+    .insert(&state.db)
+    .await?;
+
+    Ok(HttpResponse::Ok().json(CreatedApiKey { api_key, key }))
+}
+
+pub(super) async fn update_api_key(
+    _session: AccessTokenId,
+    user_id: web::ReqData<Uuid>,
+    api_key_id: web::Path<Uuid>,
+    state: web::Data<AppState>,
+    form: ValidatedJson<UpdateApiKey>,
+) -> Result<HttpResponse, AppError> {
+    let api_key = find_owned(&state, *user_id, *api_key_id).await?;
+    let form = form.into_inner();
+
+    let mut api_key: api_key::ActiveModel = api_key.into();
+    if let Some(name) = form.name {
+        api_key.name = Set(name);
+    }
+    let api_key = api_key.update(&state.db).await?;
+
+    Ok(HttpResponse::Ok().json(api_key))
+}
+
+/// Revokes a key; requests made with it fail from now on.
+pub(super) async fn delete_api_key(
+    _session: AccessTokenId,
+    user_id: web::ReqData<Uuid>,
+    api_key_id: web::Path<Uuid>,
+    state: web::Data<AppState>,
+) -> Result<HttpResponse, AppError> {
+    let res = api_key::Entity::delete_many()
+        .filter(api_key::Column::UserId.eq(user_id.into_inner()))
+        .filter(api_key::Column::Id.eq(api_key_id.into_inner()))
+        .exec(&state.db)
+        .await?;
+
+    if res.rows_affected == 0 {
+        return Err(AppError::NotFound("API key"));
+    }
+    Ok(HttpResponse::Ok().finish())
+}
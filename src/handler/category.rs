@@ -0,0 +1,440 @@
+use std::collections::HashMap;
+
+use actix_web::{web, HttpResponse};
+use entities::{
+    budget_category, category, expense, expense_split, money::Money, recurring_expense,
+};
+use sea_orm::{
+    entity::*,
+    sea_query::{Expr, Query},
+    Condition, ConnectionTrait, DbErr, QueryFilter, QueryOrder, QuerySelect, TransactionTrait,
+};
+use serde::Serialize;
+use uuid::Uuid;
+
+use crate::utility::{
+    cache::{self, CacheKey},
+    currency::Rates,
+    db_structs::{CategoryAllocation, NewCategory, UpdateCategory},
+    error::AppError,
+    ownership::OwnedBudget,
+    period::{self, Period},
+    spending,
+    state::AppState,
+    validation::ValidatedJson,
+};
+
+/// Spent vs allocated in a budget's current period, broken down by
+/// category.
+#[derive(Serialize)]
+struct BudgetBreakdown {
+    budget_id: Uuid,
+    #[serde(flatten)]
+    period: Period,
+    total_amount: Money,
+    /// Currency of every amount in the breakdown.
+    currency: String,
+    /// Sum of all category allocations; may exceed `total_amount`.
+    allocated: Money,
+    unallocated: Money,
+    spent: Money,
+    uncategorized_spent: Money,
+    categories: Vec<CategoryLine>,
+}
+
+#[derive(Serialize)]
+struct CategoryLine {
+    category_id: Uuid,
+    parent_id: Option<Uuid>,
+    name: String,
+    allocated: Option<Money>,
+    /// Spending in this category and all of its subcategories.
+    spent: Money,
+    remaining: Option<Money>,
+}
+
+/// Loads a category owned by `user_id`.
+pub(crate) async fn find_owned<C: ConnectionTrait>(
+    db: &C,
+    user_id: Uuid,
+    category_id: Uuid,
+) -> Result<Option<category::Model>, DbErr> {
+    category::Entity::find_by_id(category_id)
+        .filter(category::Column::UserId.eq(user_id))
+        .one(db)
+        .await
+}
+
+/// Checks that a `category_id` from a request body names one of the user's
+/// categories, reporting it as a field error otherwise.
+pub(crate) async fn check_category_field<C: ConnectionTrait>(
+    db: &C,
+    user_id: Uuid,
+    field: &str,
+    category_id: Option<Uuid>,
+) -> Result<(), AppError> {
+    if let Some(category_id) = category_id {
+        if find_owned(db, user_id, category_id).await?.is_none() {
+            return Err(AppError::field(
+                field,
+                "does not name one of your categories",
+            ));
+        }
+    }
+    Ok(())
+}
+
+async fn check_unique_name(
+    state: &AppState,
+    user_id: Uuid,
+    parent_id: Option<Uuid>,
+    name: &str,
+    except: Option<Uuid>,
+) -> Result<(), AppError> {
+    let mut query = category::Entity::find()
+        .filter(category::Column::UserId.eq(user_id))
+        .filter(category::Column::Name.eq(name));
+    query = match parent_id {
+        Some(parent_id) => query.filter(category::Column::ParentId.eq(parent_id)),
+        None => query.filter(category::Column::ParentId.is_null()),
+    };
+    if let Some(id) = except {
+        query = query.filter(category::Column::Id.ne(id));
+    }
+    if query.one(&state.db).await?.is_some() {
+        return Err(AppError::Conflict(format!(
+            "a category named \"{}\" already exists here",
+            name
+        )));
+    }
+    Ok(())
+}
+
+pub(super) async fn get_categories(
+    user_id: web::ReqData<Uuid>,
+    state: web::Data<AppState>,
+) -> Result<HttpResponse, AppError> {
+    let categories = category::Entity::find()
+        .filter(category::Column::UserId.eq(user_id.into_inner()))
+        .order_by_asc(category::Column::Name)
+        .all(&state.db)
+        .await?;
+
+    Ok(HttpResponse::Ok().json(categories))
+}
+
+pub(super) async fn get_category(
+    user_id: web::ReqData<Uuid>,
+    category_id: web::Path<Uuid>,
+    state: web::Data<AppState>,
+) -> Result<HttpResponse, AppError> {
+    let category = find_owned(&state.db, *user_id, *category_id)
+        .await?
+        .ok_or(AppError::NotFound("category"))?;
+
+    Ok(HttpResponse::Ok().json(category))
+}
+
+pub(super) async fn post_category(
+    user_id: web::ReqData<Uuid>,
+    state: web::Data<AppState>,
+    form: ValidatedJson<NewCategory>,
+) -> Result<HttpResponse, AppError> {
+    let user_id = user_id.into_inner();
+    check_category_field(&state.db, user_id, "parent_id", form.parent_id).await?;
+    check_unique_name(&state, user_id, form.parent_id, &form.name, None).await?;
+
+    let now = state.clock.now();
+    let new_category = category::ActiveModel {
+        id: Set(Uuid::new_v4()),
+        user_id: Set(user_id),
+        parent_id: Set(form.parent_id),
+        name: Set(form.name.clone()),
+        created_at: Set(now),
+        updated_at: Set(now),
+    };
+
+    let category = new_category.insert(&state.db).await?;
+
+    Ok(HttpResponse::Ok().json(category))
+}
+
+pub(super) async fn update_category(
+    user_id: web::ReqData<Uuid>,
+    category_id: web::Path<Uuid>,
+    state: web::Data<AppState>,
+    form: ValidatedJson<UpdateCategory>,
+) -> Result<HttpResponse, AppError> {
+    let user_id = user_id.into_inner();
+    let category = find_owned(&state.db, user_id, *category_id)
+        .await?
+        .ok_or(AppError::NotFound("category"))?;
+
+    let parent_id = form.parent_id.unwrap_or(category.parent_id);
+    if let Some(new_parent) = parent_id {
+        check_category_field(&state.db, user_id, "parent_id", Some(new_parent)).await?;
+        let parents = parent_map(&state, user_id).await?;
+        if ancestors(&parents, new_parent).any(|id| id == category.id) {
+            return Err(AppError::field(
+                "parent_id",
+                "must not be the category itself or one of its subcategories",
+            ));
+        }
+    }
+    let name = form.name.clone().unwrap_or_else(|| category.name.clone());
+    check_unique_name(&state, user_id, parent_id, &name, Some(category.id)).await?;
+
+    let mut category: category::ActiveModel = category.into();
+    category.name = Set(name);
+    category.parent_id = Set(parent_id);
+    category.updated_at = Set(state.clock.now());
+
+    let category = category.update(&state.db).await?;
+
+    Ok(HttpResponse::Ok().json(category))
+}
+
+/// Deletes a category that has no subcategories. Its expenses become
+/// uncategorized and its allocations are dropped.
+pub(super) async fn delete_category(
+    user_id: web::ReqData<Uuid>,
+    category_id: web::Path<Uuid>,
+    state: web::Data<AppState>,
+) -> Result<HttpResponse, AppError> {
+    let user_id = user_id.into_inner();
+    let category = find_owned(&state.db, user_id, *category_id)
+        .await?
+        .ok_or(AppError::NotFound("category"))?;
+
+    let has_children = category::Entity::find()
+        .filter(category::Column::ParentId.eq(category.id))
+        .one(&state.db)
+        .await?
+        .is_some();
+    if has_children {
+        return Err(AppError::Conflict(
+            "move or delete its subcategories first".to_string(),
+        ));
+    }
+
+    let affected: Vec<(Uuid, Uuid)> = expense::Entity::find()
+        .select_only()
+        .column(expense::Column::BudgetId)
+        .column(expense::Column::Id)
+        .filter(
+            Condition::any()
+                .add(expense::Column::CategoryId.eq(category.id))
+                .add(
+                    expense::Column::Id.in_subquery(
+                        Query::select()
+                            .column(expense_split::Column::ExpenseId)
+                            .from(expense_split::Entity)
+                            .and_where(expense_split::Column::CategoryId.eq(category.id))
+                            .to_owned(),
+                    ),
+                ),
+        )
+        .into_tuple()
+        .all(&state.db)
+        .await?;
+    for (budget_id, expense_id) in affected {
+        cache::invalidate(
+            state.cache.as_ref(),
+            &CacheKey::expense(&state.settings.cache, user_id, budget_id, expense_id),
+        )
+        .await;
+    }
+
+    let category_id = category.id;
+    state
+        .db
+        .transaction::<_, _, DbErr>(|txn| {
+            Box::pin(async move {
+                expense::Entity::update_many()
+                    .col_expr(expense::Column::CategoryId, Expr::value(None::<Uuid>))
+                    .filter(expense::Column::CategoryId.eq(category_id))
+                    .exec(txn)
+                    .await?;
+                expense_split::Entity::update_many()
+                    .col_expr(expense_split::Column::CategoryId, Expr::value(None::<Uuid>))
+                    .filter(expense_split::Column::CategoryId.eq(category_id))
+                    .exec(txn)
+                    .await?;
+                recurring_expense::Entity::update_many()
+                    .col_expr(
+                        recurring_expense::Column::CategoryId,
+                        Expr::value(None::<Uuid>),
+                    )
+                    .filter(recurring_expense::Column::CategoryId.eq(category_id))
+                    .exec(txn)
+                    .await?;
+                budget_category::Entity::delete_many()
+                    .filter(budget_category::Column::CategoryId.eq(category_id))
+                    .exec(txn)
+                    .await?;
+                category::Entity::delete_by_id(category_id).exec(txn).await
+            })
+        })
+        .await?;
+
+    Ok(HttpResponse::Ok().finish())
+}
+
+pub(super) async fn get_budget_categories(
+    budget: OwnedBudget,
+    state: web::Data<AppState>,
+) -> Result<HttpResponse, AppError> {
+    let categories: HashMap<Uuid, category::Model> = category::Entity::find()
+        .filter(category::Column::UserId.eq(budget.user_id))
+        .all(&state.db)
+        .await?
+        .into_iter()
+        .map(|c| (c.id, c))
+        .collect();
+    let parents: HashMap<Uuid, Option<Uuid>> =
+        categories.values().map(|c| (c.id, c.parent_id)).collect();
+
+    let allocations: HashMap<Uuid, Money> = budget_category::Entity::find()
+        .filter(budget_category::Column::BudgetId.eq(budget.id))
+        .all(&state.db)
+        .await?
+        .into_iter()
+        .map(|a| (a.category_id, a.amount))
+        .collect();
+
+    // Allocations are per period, so only the current period's spending
+    // counts against them.
+    let today = state.clock.now().date_naive();
+    let current = period::nth(&budget, period::index_of(&budget, today))
+        .ok_or(AppError::NotFound("period"))?;
+
+    // Grouped by currency and date too, so that foreign spending can be
+    // converted at the rate of the day. Split lines count towards their own
+    // category.
+    let spending =
+        spending::budget_spending(&state.db, budget.id, Some(current.start), Some(current.end))
+            .await?;
+    let rates = Rates::load(
+        &state.db,
+        &budget.currency,
+        spending.iter().map(|(_, currency, _, _)| currency),
+    )
+    .await?;
+
+    let mut spent = Money::ZERO;
+    let mut uncategorized_spent = Money::ZERO;
+    let mut rolled_up: HashMap<Uuid, Money> = HashMap::new();
+    for (category_id, currency, date, amount) in spending {
+        let amount = rates.convert(amount, &currency, date)?;
+        spent += amount;
+        match category_id {
+            Some(category_id) => {
+                for id in ancestors(&parents, category_id) {
+                    *rolled_up.entry(id).or_default() += amount;
+                }
+            }
+            None => uncategorized_spent += amount,
+        }
+    }
+
+    let mut lines: Vec<CategoryLine> = categories
+        .values()
+        .filter(|c| allocations.contains_key(&c.id) || rolled_up.contains_key(&c.id))
+        .map(|c| {
+            let allocated = allocations.get(&c.id).copied();
+            let spent = rolled_up.get(&c.id).copied().unwrap_or_default();
+            CategoryLine {
+                category_id: c.id,
+                parent_id: c.parent_id,
+                name: c.name.clone(),
+                allocated,
+                spent,
+                remaining: allocated.map(|allocated| allocated - spent),
+            }
+        })
+        .collect();
+    lines.sort_by(|a, b| a.name.cmp(&b.name).then(a.category_id.cmp(&b.category_id)));
+
+    let allocated: Money = allocations.values().copied().sum();
+    Ok(HttpResponse::Ok().json(BudgetBreakdown {
+        budget_id: budget.id,
+        period: current,
+        total_amount: budget.total_amount,
+        currency: budget.currency.clone(),
+        allocated,
+        unallocated: budget.total_amount - allocated,
+        spent,
+        uncategorized_spent,
+        categories: lines,
+    }))
+}
+
+/// Sets (or replaces) the amount a budget allocates to a category.
+pub(super) async fn put_budget_category(
+    budget: OwnedBudget,
+    path: web::Path<(Uuid, Uuid)>,
+    state: web::Data<AppState>,
+    form: ValidatedJson<CategoryAllocation>,
+) -> Result<HttpResponse, AppError> {
+    let (_, category_id) = path.into_inner();
+    find_owned(&state.db, budget.user_id, category_id)
+        .await?
+        .ok_or(AppError::NotFound("category"))?;
+
+    let existing = budget_category::Entity::find_by_id((budget.id, category_id))
+        .one(&state.db)
+        .await?;
+    let allocation = match existing {
+        Some(existing) => {
+            let mut allocation: budget_category::ActiveModel = existing.into();
+            allocation.amount = Set(form.amount);
+            allocation.update(&state.db).await?
+        }
+        None => {
+            budget_category::ActiveModel {
+                budget_id: Set(budget.id),
+                category_id: Set(category_id),
+                amount: Set(form.amount),
+            }
+            .insert(&state.db)
+            .await?
+        }
+    };
+
+    Ok(HttpResponse::Ok().json(allocation))
+}
+
+pub(super) async fn delete_budget_category(
+    budget: OwnedBudget,
+    path: web::Path<(Uuid, Uuid)>,
+    state: web::Data<AppState>,
+) -> Result<HttpResponse, AppError> {
+    let (_, category_id) = path.into_inner();
+
+    let res = budget_category::Entity::delete_by_id((budget.id, category_id))
+        .exec(&state.db)
+        .await?;
+
+    if res.rows_affected == 0 {
+        return Err(AppError::NotFound("category allocation"));
+    }
+    Ok(HttpResponse::Ok().finish())
+}
+
+async fn parent_map(state: &AppState, user_id: Uuid) -> Result<HashMap<Uuid, Option<Uuid>>, DbErr> {
+    let rows: Vec<(Uuid, Option<Uuid>)> = category::Entity::find()
+        .select_only()
+        .column(category::Column::Id)
+        .column(category::Column::ParentId)
+        .filter(category::Column::UserId.eq(user_id))
+        .into_tuple()
+        .all(&state.db)
+        .await?;
+    Ok(rows.into_iter().collect())
+}
+
+/// `id` followed by its parent, grandparent and so on. Stops after visiting
+/// every category once, so a corrupt cycle cannot loop forever.
+fn ancestors(parents: &HashMap<Uuid, Option<Uuid>>, id: Uuid) -> impl Iterator<Item = Uuid> + '_ {
+    std::iter::successors(Some(id), |id| parents.get(id).copied().flatten()).take(parents.len() + 1)
+}
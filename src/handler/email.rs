@@ -0,0 +1,232 @@
+use actix_web::{web, HttpResponse};
+use bcrypt::{hash, DEFAULT_COST};
+use chrono::{DateTime, Duration, Utc};
+use entities::{email_token, sea_orm_active_enums::EmailTokenPurpose, users};
+use log::warn;
+use sea_orm::{entity::*, sea_query::Expr, QueryFilter};
+use uuid::Uuid;
+
+use crate::utility::{
+    cache::{self, CacheKey},
+    db_structs::{EmailTokenForm, ForgotPassword, ResetPassword},
+    error::AppError,
+    mailer::Email,
+    session::revoke_user,
+    state::AppState,
+    token::{hash_token, random_token},
+    validation::ValidatedJson,
+};
+
+/// Stores a new `purpose` token for `user`'s current email address, replacing
+/// any earlier one that was not used, and returns it.
+async fn issue(
+    state: &AppState,
+    user: &users::Model,
+    purpose: EmailTokenPurpose,
+    ttl_secs: i64,
+) -> Result<(String, DateTime<Utc>), AppError> {
+    let now = state.clock.now();
+    email_token::Entity::delete_many()
+        .filter(email_token::Column::UserId.eq(user.id))
+        .filter(email_token::Column::Purpose.eq(purpose))
+        .filter(email_token::Column::UsedAt.is_null())
+        .exec(&state.db)
+        .await?;
+
+    let token = random_token();
+    let expires_at = now + Duration::seconds(ttl_secs);
+    email_token::ActiveModel {
+        id: Set(Uuid::new_v4()),
+        user_id: Set(user.id),
+        purpose: Set(purpose),
+        email: Set(user.email.clone()),
+        token_hash: Set(hash_token(&token)),
+        expires_at: Set(expires_at),
+        used_at: Set(None),
+        created_at: Set(now),
+    }
+    .insert(&state.db)
+    .await?;
+
+    Ok((token, expires_at))
+}
+
+/// Uses up `token` if it is a `purpose` token that has not expired, and
+/// returns the user it was sent to if their email address is still the same.
+async fn redeem(
+    state: &AppState,
+    purpose: EmailTokenPurpose,
+    token: &str,
+) -> Result<users::Model, AppError> {
+    let now = state.clock.now();
+    let invalid = || AppError::field("token", "is invalid or has expired");
+
+    let token = email_token::Entity::find()
+        .filter(email_token::Column::TokenHash.eq(hash_token(token)))
+        .filter(email_token::Column::Purpose.eq(purpose))
+        .filter(email_token::Column::UsedAt.is_null())
+        .filter(email_token::Column::ExpiresAt.gt(now))
+        .one(&state.db)
+        .await?
+        .ok_or_else(invalid)?;
+
+    // Only one of two requests racing with the same token gets through.
+    let used = email_token::Entity::update_many()
+        .col_expr(email_token::Column::UsedAt, Expr::value(now))
+        .filter(email_token::Column::Id.eq(token.id))
+        .filter(email_token::Column::UsedAt.is_null())
+        .exec(&state.db)
+        .await?;
+    if used.rows_affected == 0 {
+        return Err(invalid());
+    }
+
+    users::Entity::find_by_id(token.user_id)
+        .one(&state.db)
+        .await?
+        .filter(|user| user.email == token.email)
+        .ok_or_else(invalid)
+}
+
+/// Sends `email` in the background, so that the request neither waits for
+/// the mail server nor takes longer when there is something to send. Failures
+/// are only logged; the user can ask for another one.
+fn send(state: &AppState, email: Email) {
+    let mailer = state.mailer.clone();
+    actix_web::rt::spawn(async move {
+        if let Err(e) = mailer.send(&email).await {
+            warn!("cannot send mail to {}: {}", email.to, e);
+        }
+    });
+}
+
+/// Mails `user` a token that confirms their email address.
+pub(crate) async fn send_verification(
+    state: &AppState,
+    user: &users::Model,
+) -> Result<(), AppError> {
+    let (token, expires_at) = issue(
+        state,
+        user,
+        EmailTokenPurpose::VerifyEmail,
+        state.settings.mail.verification_ttl_secs,
+    )
+    .await?;
+
+    let body = format!(
+        "Hi {},\n\nTo confirm that {} is your email address, send this token to \
+         POST /api/verify-email:\n\n{}\n\nIt expires at {}.\n",
+        user.username,
+        user.email,
+        token,
+        expires_at.to_rfc3339()
+    );
+    send(
+        state,
+        Email {
+            to: user.email.clone(),
+            subject: "Confirm your email address".to_string(),
+            body,
+        },
+    );
+    Ok(())
+}
+
+pub(super) async fn verify_email(
+    state: web::Data<AppState>,
+    form: ValidatedJson<EmailTokenForm>,
+) -> Result<HttpResponse, AppError> {
+    let user = redeem(&state, EmailTokenPurpose::VerifyEmail, &form.token).await?;
+
+    // Whoever holds the token need not be logged in, so nothing about the
+    // user is sent back.
+    if user.email_verified_at.is_none() {
+        let mut user: users::ActiveModel = user.into();
+        user.email_verified_at = Set(Some(state.clock.now()));
+        let user = user.update(&state.db).await?;
+
+        let key = CacheKey::profile(&state.settings.cache, user.id);
+        cache::put(state.cache.as_ref(), &key, &user).await;
+    }
+
+    Ok(HttpResponse::NoContent().finish())
+}
+
+pub(super) async fn resend_verification(
+    user_id: web::ReqData<Uuid>,
+    state: web::Data<AppState>,
+) -> Result<HttpResponse, AppError> {
+    let user = users::Entity::find_by_id(user_id.into_inner())
+        .one(&state.db)
+        .await?
+        .ok_or(AppError::NotFound("user"))?;
+    if user.email_verified_at.is_some() {
+        return Err(AppError::Conflict(
+            "email address is already verified".to_string(),
+        ));
+    }
+    send_verification(&state, &user).await?;
+
+    Ok(HttpResponse::Ok().finish())
+}
+
+/// Mails a password reset token to every account with the address. The
+/// answer is the same whether there are any, so it does not reveal who has
+/// an account.
+pub(super) async fn forgot_password(
+    state: web::Data<AppState>,
+    form: ValidatedJson<ForgotPassword>,
+) -> Result<HttpResponse, AppError> {
+    let users = users::Entity::find()
+        .filter(users::Column::Email.eq(form.email.clone()))
+        .all(&state.db)
+        .await?;
+
+    for user in users {
+        let (token, expires_at) = issue(
+            &state,
+            &user,
+            EmailTokenPurpose::ResetPassword,
+            state.settings.mail.reset_ttl_secs,
+        )
+        .await?;
+
+        let body = format!(
+            "Hi {},\n\nSomeone asked to reset the password of your account. To choose a new \
+             one, send this token with it to POST /api/password/reset:\n\n{}\n\n\
+             It expires at {}. If it was not you, ignore this message.\n",
+            user.username,
+            token,
+            expires_at.to_rfc3339()
+        );
+        send(
+            &state,
+            Email {
+                to: user.email.clone(),
+                subject: "Reset your password".to_string(),
+                body,
+            },
+        );
+    }
+
+    Ok(HttpResponse::Ok().finish())
+}
+
+/// Sets a new password and logs the user out everywhere.
+pub(super) async fn reset_password(
+    state: web::Data<AppState>,
+    form: ValidatedJson<ResetPassword>,
+) -> Result<HttpResponse, AppError> {
+    let form = form.into_inner();
+    let user = redeem(&state, EmailTokenPurpose::ResetPassword, &form.token).await?;
+
+    let mut user: users::ActiveModel = user.into();
+    user.password_hash = Set(hash(&form.password, DEFAULT_COST)?);
+    let user = user.update(&state.db).await?;
+
+    let key = CacheKey::profile(&state.settings.cache, user.id);
+    cache::put(state.cache.as_ref(), &key, &user).await;
+    revoke_user(&state.db, user.id, state.clock.now()).await?;
+
+    Ok(HttpResponse::Ok().finish())
+}
@@ -0,0 +1,37 @@
+use actix_web::{web, HttpRequest, HttpResponse};
+use entities::exchange_rate;
+use sea_orm::{entity::*, QueryFilter, QueryOrder, QueryTrait};
+
+use crate::utility::{
+    db_structs::ExchangeRateFilter,
+    error::AppError,
+    pagination::{self, PageRequest, Paginated},
+    state::AppState,
+};
+
+/// The stored exchange rates, newest first within each currency pair.
+pub(super) async fn get_exchange_rates(
+    req: HttpRequest,
+    filter: web::Query<ExchangeRateFilter>,
+    state: web::Data<AppState>,
+) -> Result<HttpResponse, AppError> {
+    let page = PageRequest::new(filter.page, filter.limit)?;
+
+    let query = exchange_rate::Entity::find()
+        .apply_if(filter.base.as_deref(), |q, base| {
+            q.filter(exchange_rate::Column::BaseCurrency.eq(base))
+        })
+        .apply_if(filter.quote.as_deref(), |q, quote| {
+            q.filter(exchange_rate::Column::QuoteCurrency.eq(quote))
+        })
+        .order_by_asc(exchange_rate::Column::BaseCurrency)
+        .order_by_asc(exchange_rate::Column::QuoteCurrency)
+        .order_by_desc(exchange_rate::Column::EffectiveOn);
+    let (rates, meta) = pagination::fetch(&state.db, query, page).await?;
+
+    Ok(Paginated {
+        data: rates,
+        pagination: meta,
+    }
+    .respond(&req))
+}
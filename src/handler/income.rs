@@ -0,0 +1,184 @@
+use actix_web::{web, HttpRequest, HttpResponse};
+use entities::{budget, income};
+use sea_orm::{entity::*, sea_query::Expr, QueryFilter, QueryOrder, QueryTrait};
+use uuid::Uuid;
+
+use super::account;
+use crate::utility::{
+    currency,
+    db_structs::{IncomeFilter, NewIncome, SortField, UpdateIncome},
+    error::AppError,
+    ownership,
+    pagination::{self, PageRequest, Paginated},
+    state::AppState,
+    validation::ValidatedJson,
+};
+
+async fn find_owned(
+    state: &AppState,
+    user_id: Uuid,
+    income_id: Uuid,
+) -> Result<income::Model, AppError> {
+    income::Entity::find_by_id(income_id)
+        .filter(income::Column::UserId.eq(user_id))
+        .one(&state.db)
+        .await?
+        .ok_or(AppError::NotFound("income"))
+}
+
+pub(super) async fn get_incomes(
+    req: HttpRequest,
+    user_id: web::ReqData<Uuid>,
+    filter: web::Query<IncomeFilter>,
+    state: web::Data<AppState>,
+) -> Result<HttpResponse, AppError> {
+    let page = PageRequest::new(filter.page, filter.limit)?;
+    pagination::check_range(filter.from, filter.to, "to", "must not be before from")?;
+    pagination::check_range(
+        filter.min_amount,
+        filter.max_amount,
+        "max_amount",
+        "must not be less than min_amount",
+    )?;
+
+    let sort = match filter.sort {
+        SortField::Date => income::Column::Date,
+        SortField::Amount => income::Column::Amount,
+        SortField::CreatedAt => income::Column::CreatedAt,
+    };
+    let query = income::Entity::find()
+        .filter(income::Column::UserId.eq(user_id.into_inner()))
+        .apply_if(filter.budget_id, |q, budget_id| {
+            q.filter(income::Column::BudgetId.eq(budget_id))
+        })
+        .apply_if(filter.from, |q, from| {
+            q.filter(income::Column::Date.gte(from))
+        })
+        .apply_if(filter.to, |q, to| q.filter(income::Column::Date.lte(to)))
+        .apply_if(filter.min_amount, |q, min| {
+            q.filter(income::Column::Amount.gte(min))
+        })
+        .apply_if(filter.max_amount, |q, max| {
+            q.filter(income::Column::Amount.lte(max))
+        })
+        .apply_if(filter.q.as_deref(), |q, needle| {
+            q.filter(Expr::col(income::Column::Source).like(pagination::contains(needle)))
+        })
+        .order_by(sort, filter.order.into())
+        .order_by(income::Column::Id, filter.order.into());
+    let (incomes, meta) = pagination::fetch(&state.db, query, page).await?;
+
+    Ok(Paginated {
+        data: incomes,
+        pagination: meta,
+    }
+    .respond(&req))
+}
+
+pub(super) async fn get_income(
+    user_id: web::ReqData<Uuid>,
+    income_id: web::Path<Uuid>,
+    state: web::Data<AppState>,
+) -> Result<HttpResponse, AppError> {
+    let income = find_owned(&state, *user_id, *income_id).await?;
+
+    Ok(HttpResponse::Ok().json(income))
+}
+
+pub(super) async fn post_income(
+    user_id: web::ReqData<Uuid>,
+    state: web::Data<AppState>,
+    form: ValidatedJson<NewIncome>,
+) -> Result<HttpResponse, AppError> {
+    let user_id = user_id.into_inner();
+    ownership::check_budget_field(&state.db, user_id, "budget_id", form.budget_id).await?;
+    account::check_account_field(&state.db, user_id, "account_id", form.account_id).await?;
+    let form = form.into_inner();
+
+    let currency = match (form.currency, form.budget_id) {
+        (Some(currency), _) => currency,
+        (None, Some(budget_id)) => budget::Entity::find_by_id(budget_id)
+            .one(&state.db)
+            .await?
+            .map(|budget| budget.currency)
+            .ok_or(AppError::NotFound("budget"))?,
+        (None, None) => currency::DEFAULT_CURRENCY.to_string(),
+    };
+
+    let now = state.clock.now();
+    let income = income::ActiveModel {
+        id: Set(Uuid::new_v4()),
+        user_id: Set(user_id),
+        budget_id: Set(form.budget_id),
+        account_id: Set(form.account_id),
+        source: Set(form.source),
+        amount: Set(form.amount),
+        currency: Set(currency),
+        date: Set(form.date.unwrap_or_else(|| now.date_naive())),
+        created_at: Set(now),
+        updated_at: Set(now),
+    }
+    .insert(&state.db)
+    .await?;
+
+    Ok(HttpResponse::Ok().json(income))
+}
+
+pub(super) async fn update_income(
+    user_id: web::ReqData<Uuid>,
+    income_id: web::Path<Uuid>,
+    state: web::Data<AppState>,
+    form: ValidatedJson<UpdateIncome>,
+) -> Result<HttpResponse, AppError> {
+    let income = find_owned(&state, *user_id, *income_id).await?;
+    let mut income: income::ActiveModel = income.into();
+    let form = form.into_inner();
+
+    if let Some(source) = form.source {
+        income.source = Set(source);
+    }
+
+    if let Some(amount) = form.amount {
+        income.amount = Set(amount);
+    }
+
+    if let Some(currency) = form.currency {
+        income.currency = Set(currency);
+    }
+
+    if let Some(date) = form.date {
+        income.date = Set(date);
+    }
+
+    if let Some(budget_id) = form.budget_id {
+        ownership::check_budget_field(&state.db, *user_id, "budget_id", budget_id).await?;
+        income.budget_id = Set(budget_id);
+    }
+
+    if let Some(account_id) = form.account_id {
+        account::check_account_field(&state.db, *user_id, "account_id", account_id).await?;
+        income.account_id = Set(account_id);
+    }
+
+    income.updated_at = Set(state.clock.now());
+    let income = income.update(&state.db).await?;
+
+    Ok(HttpResponse::Ok().json(income))
+}
+
+pub(super) async fn delete_income(
+    user_id: web::ReqData<Uuid>,
+    income_id: web::Path<Uuid>,
+    state: web::Data<AppState>,
+) -> Result<HttpResponse, AppError> {
+    let res = income::Entity::delete_many()
+        .filter(income::Column::UserId.eq(user_id.into_inner()))
+        .filter(income::Column::Id.eq(income_id.into_inner()))
+        .exec(&state.db)
+        .await?;
+
+    if res.rows_affected == 0 {
+        return Err(AppError::NotFound("income"));
+    }
+    Ok(HttpResponse::Ok().finish())
+}
@@ -1,40 +1,146 @@
-use actix_web::{http::Error, middleware::Compress, web, HttpResponse};
+mod account;
+mod api_key;
+mod category;
+mod email;
+mod exchange_rate;
+mod income;
+mod period;
+mod recurring;
+mod report;
+mod session;
+mod split;
+mod tag;
+mod transfer;
+mod two_factor;
+
+use actix_web::{middleware::Compress, web, HttpRequest, HttpResponse};
 use bcrypt::{hash, verify, DEFAULT_COST};
-use chrono::Utc;
-use redis::Commands;
 
 use crate::{
-    middleware::auth::Auth,
+    middleware::auth::{AccessTokenId, Auth},
     utility::{
+        cache::{self, CacheKey},
+        currency,
         db_structs::{
-            LoginInfo, NewBudget, NewExpense, NewUser, UpdateBudget, UpdateExpense, UpdateUser,
-        }, redis::get_redis_connection, token::sign_jwt
+            BudgetFilter, ExpenseFilter, LoginInfo, NewBudget, NewExpense, NewUser, SortField,
+            UpdateBudget, UpdateExpense, UpdateUser,
+        },
+        error::{json_error_handler, path_error_handler, query_error_handler, AppError},
+        ownership::OwnedBudget,
+        pagination::{self, PageRequest, Paginated},
+        session::{issue_tokens, revoke_user},
+        state::AppState,
+        validation::ValidatedJson,
     },
 };
-use entities::{budget, expense, users};
-use sea_orm::{entity::*, DatabaseConnection, QueryFilter};
+use entities::{
+    budget, budget_category, expense, expense_split, expense_tag, recurring_expense,
+    sea_orm_active_enums::BudgetPeriod, users,
+};
+use sea_orm::{
+    entity::*,
+    sea_query::{Expr, Query},
+    Condition, ConnectionTrait, DbErr, QueryFilter, QueryOrder, QuerySelect, QueryTrait,
+    TransactionTrait,
+};
+use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
+/// Request bodies are small JSON documents; anything bigger is rejected before
+/// it is buffered.
+const MAX_BODY_BYTES: usize = 64 * 1024;
+
 pub fn init(cfg: &mut web::ServiceConfig) {
-    cfg.service(
+    cfg.app_data(
+        web::JsonConfig::default()
+            .limit(MAX_BODY_BYTES)
+            .error_handler(json_error_handler),
+    )
+    .app_data(web::PathConfig::default().error_handler(path_error_handler))
+    .app_data(web::QueryConfig::default().error_handler(query_error_handler))
+    .service(
         web::scope("/api")
-            .service(
-                web::scope("")
-                    .route("/register", web::post().to(register))
-                    .route("/login", web::post().to(login)),
-            )
+            .route("/register", web::post().to(register))
+            .route("/login", web::post().to(login))
+            .route("/login/2fa", web::post().to(two_factor::login_two_factor))
+            .route("/verify-email", web::post().to(email::verify_email))
+            .route("/password/forgot", web::post().to(email::forgot_password))
+            .route("/password/reset", web::post().to(email::reset_password))
+            .route("/token/refresh", web::post().to(session::refresh))
             .service(
                 web::scope("")
                     .wrap(Auth)
                     .wrap(Compress::default())
+                    .route("/logout", web::post().to(session::logout))
+                    .route("/logout/all", web::post().to(session::logout_everywhere))
                     .route("/profile", web::get().to(get_profile))
+                    .route("/2fa", web::get().to(two_factor::get_two_factor))
+                    .route("/2fa/totp", web::post().to(two_factor::post_totp))
+                    .route("/2fa/totp", web::delete().to(two_factor::delete_totp))
+                    .route(
+                        "/2fa/totp/confirm",
+                        web::post().to(two_factor::confirm_totp),
+                    )
+                    .route("/api-keys", web::get().to(api_key::get_api_keys))
+                    .route("/api-keys", web::post().to(api_key::post_api_key))
+                    .route("/api-keys/{id}", web::get().to(api_key::get_api_key))
+                    .route("/api-keys/{id}", web::put().to(api_key::update_api_key))
+                    .route("/api-keys/{id}", web::delete().to(api_key::delete_api_key))
                     .route("/profile", web::put().to(update_profile))
+                    .route(
+                        "/verify-email/resend",
+                        web::post().to(email::resend_verification),
+                    )
                     .route("/budget", web::get().to(get_budgets))
                     .route("/budget", web::post().to(post_budget))
                     .route("/budget/{id}", web::get().to(get_budget))
                     .route("/budget/{id}", web::post().to(post_budget))
                     .route("/budget/{id}", web::put().to(update_budget))
                     .route("/budget/{id}", web::delete().to(delete_budget))
+                    .route(
+                        "/budget/{id}/periods",
+                        web::get().to(period::get_period_history),
+                    )
+                    .route(
+                        "/budget/{id}/periods/current",
+                        web::get().to(period::get_current_period),
+                    )
+                    .route(
+                        "/budget/{id}/summary",
+                        web::get().to(period::get_budget_summary),
+                    )
+                    .route(
+                        "/budget/{id}/categories",
+                        web::get().to(category::get_budget_categories),
+                    )
+                    .route(
+                        "/budget/{id}/categories/{category_id}",
+                        web::put().to(category::put_budget_category),
+                    )
+                    .route(
+                        "/budget/{id}/categories/{category_id}",
+                        web::delete().to(category::delete_budget_category),
+                    )
+                    .route(
+                        "/budget/{id}/recurring",
+                        web::get().to(recurring::get_recurring_expenses),
+                    )
+                    .route(
+                        "/budget/{id}/recurring",
+                        web::post().to(recurring::post_recurring_expense),
+                    )
+                    .route(
+                        "/budget/{id}/recurring/{recurring_id}",
+                        web::get().to(recurring::get_recurring_expense),
+                    )
+                    .route(
+                        "/budget/{id}/recurring/{recurring_id}",
+                        web::put().to(recurring::update_recurring_expense),
+                    )
+                    .route(
+                        "/budget/{id}/recurring/{recurring_id}",
+                        web::delete().to(recurring::delete_recurring_expense),
+                    )
                     .route("/budget/{id}/expenses", web::get().to(get_expenses))
                     .route("/budget/{id}/expenses", web::post().to(post_expense))
                     .route(
@@ -48,463 +154,755 @@ pub fn init(cfg: &mut web::ServiceConfig) {
                     .route(
                         "/budget/{id}/expenses/{expense_id}",
                         web::delete().to(delete_expense),
+                    )
+                    .route(
+                        "/exchange-rates",
+                        web::get().to(exchange_rate::get_exchange_rates),
+                    )
+                    .route("/accounts", web::get().to(account::get_accounts))
+                    .route("/accounts", web::post().to(account::post_account))
+                    .route("/accounts/{id}", web::get().to(account::get_account))
+                    .route("/accounts/{id}", web::put().to(account::update_account))
+                    .route("/accounts/{id}", web::delete().to(account::delete_account))
+                    .route(
+                        "/accounts/{id}/balance",
+                        web::get().to(account::get_account_balance),
+                    )
+                    .route(
+                        "/accounts/{id}/transactions",
+                        web::get().to(account::get_account_ledger),
+                    )
+                    .route("/transfers", web::get().to(transfer::get_transfers))
+                    .route("/transfers", web::post().to(transfer::post_transfer))
+                    .route("/transfers/{id}", web::get().to(transfer::get_transfer))
+                    .route("/transfers/{id}", web::put().to(transfer::update_transfer))
+                    .route(
+                        "/transfers/{id}",
+                        web::delete().to(transfer::delete_transfer),
+                    )
+                    .route("/income", web::get().to(income::get_incomes))
+                    .route("/income", web::post().to(income::post_income))
+                    .route("/income/{id}", web::get().to(income::get_income))
+                    .route("/income/{id}", web::put().to(income::update_income))
+                    .route("/income/{id}", web::delete().to(income::delete_income))
+                    .route("/reports/cash-flow", web::get().to(report::get_cash_flow))
+                    .route("/tags", web::get().to(tag::get_tags))
+                    .route("/tags", web::post().to(tag::post_tag))
+                    .route("/tags/{id}", web::get().to(tag::get_tag))
+                    .route("/tags/{id}", web::put().to(tag::update_tag))
+                    .route("/tags/{id}", web::delete().to(tag::delete_tag))
+                    .route("/categories", web::get().to(category::get_categories))
+                    .route("/categories", web::post().to(category::post_category))
+                    .route("/categories/{id}", web::get().to(category::get_category))
+                    .route("/categories/{id}", web::put().to(category::update_category))
+                    .route(
+                        "/categories/{id}",
+                        web::delete().to(category::delete_category),
                     ),
             ),
     );
 }
 
 async fn register(
-    pool: web::Data<DatabaseConnection>,
-    form: web::Json<NewUser>,
-) -> Result<HttpResponse, Error> {
-    let hashed_passowrd = hash(&form.password, DEFAULT_COST).unwrap();
+    state: web::Data<AppState>,
+    form: ValidatedJson<NewUser>,
+) -> Result<HttpResponse, AppError> {
+    let taken = users::Entity::find()
+        .filter(users::Column::Username.eq(form.username.clone()))
+        .one(&state.db)
+        .await?;
+    if taken.is_some() {
+        return Err(AppError::Conflict("username is already taken".to_string()));
+    }
+
+    let hashed_passowrd = hash(&form.password, DEFAULT_COST)?;
 
     let new_user = users::ActiveModel {
         id: Set(Uuid::new_v4()),
         username: Set(form.username.clone()),
         password_hash: Set(hashed_passowrd),
         email: Set(form.email.clone()),
-        ..Default::default()
+        email_verified_at: Set(None),
     };
 
-    let res = new_user.insert(pool.get_ref()).await;
-
-    match res {
-        Ok(insert_result) => {
-            let user = users::Entity::find_by_id(insert_result.id)
-                .one(pool.get_ref())
-                .await
-                .unwrap();
+    let user = new_user.insert(&state.db).await?;
+    email::send_verification(&state, &user).await?;
 
-            match user {
-                Some(user) => Ok(HttpResponse::Ok().json(user)),
-                None => Ok(HttpResponse::InternalServerError().finish()),
-            }
-        }
-        Err(_) => Ok(HttpResponse::InternalServerError().finish()),
-    }
+    Ok(HttpResponse::Ok().json(user))
 }
 
 async fn login(
-    pool: web::Data<DatabaseConnection>,
-    form: web::Json<LoginInfo>,
-) -> Result<HttpResponse, Error> {
+    state: web::Data<AppState>,
+    form: ValidatedJson<LoginInfo>,
+) -> Result<HttpResponse, AppError> {
+    let invalid = || AppError::Unauthorized("invalid username or password".to_string());
+
     let user = users::Entity::find()
         .filter(users::Column::Username.eq(form.username.clone()))
-        .one(pool.get_ref())
-        .await;
+        .one(&state.db)
+        .await?
+        .ok_or_else(invalid)?;
 
-    match user {
-        Ok(Some(user)) => {
-            if verify(&form.password, &user.password_hash).unwrap() {
-                let token = sign_jwt(user.id).unwrap();
-                Ok(HttpResponse::Ok().json(token))
-            } else {
-                Ok(HttpResponse::Unauthorized().finish())
-            }
-        }
-        Ok(None) => Ok(HttpResponse::NotFound().finish()),
-        Err(_) => Ok(HttpResponse::InternalServerError().finish()),
+    if !verify(&form.password, &user.password_hash)? {
+        return Err(invalid());
+    }
+
+    if two_factor::confirmed_totp(&state.db, user.id)
+        .await?
+        .is_some()
+    {
+        let challenge = two_factor::challenge(&state, user.id).await?;
+        return Ok(HttpResponse::Ok().json(challenge));
     }
+
+    let tokens = issue_tokens(
+        &state.db,
+        &state.settings.jwt,
+        state.clock.now(),
+        user.id,
+        Uuid::new_v4(),
+    )
+    .await?;
+    Ok(HttpResponse::Ok().json(tokens))
 }
 
 async fn get_profile(
     user_id: web::ReqData<Uuid>,
-    pool: web::Data<DatabaseConnection>,
-) -> Result<HttpResponse, Error> {
-    let mut conn = get_redis_connection();
+    state: web::Data<AppState>,
+) -> Result<HttpResponse, AppError> {
+    let key = CacheKey::profile(&state.settings.cache, *user_id);
 
-    let cached_profile: Option<String> = conn.get(format!("user_profile_{}", user_id.to_string())).ok(); 
-    if let Some(profile_json) = cached_profile {
-        return Ok(HttpResponse::Ok().json(profile_json));
-    }
-
-    let user = users::Entity::find_by_id(user_id.into_inner().clone())
-        .one(pool.get_ref())
-        .await;
+    let user = cache::get_or_load(state.cache.as_ref(), &key, || {
+        users::Entity::find_by_id(*user_id).one(&state.db)
+    })
+    .await?
+    .ok_or(AppError::NotFound("user"))?;
 
-    match user {
-        Ok(Some(user)) => {
-            let active_user = user.clone().into_active_model();
-
-            let _: () = conn.set_ex(
-            format!("user_profile_{}", active_user.id.clone().unwrap().to_string()),
-            format!("id: {}, username: {}, email: {}", active_user.id.unwrap().to_string(), active_user.username.unwrap().to_string(), active_user.email.unwrap().to_string()),
-            86400,
-            )
-            .unwrap();
-            
-            Ok(HttpResponse::Ok().json(user))
-        },
-        Ok(None) => Ok(HttpResponse::NotFound().finish()),
-        Err(_) => Ok(HttpResponse::InternalServerError().finish()),
-    }
+    Ok(HttpResponse::Ok().json(user))
 }
 
+/// Not available with an API key, which must not be able to take over the
+/// account.
 async fn update_profile(
+    _session: AccessTokenId,
     user_id: web::ReqData<Uuid>,
-    pool: web::Data<DatabaseConnection>,
-    form: web::Json<UpdateUser>,
-) -> Result<HttpResponse, Error> {
-    let mut conn = get_redis_connection();
-    let _: () = conn.del(format!("user_profile_{}", user_id.to_string())).unwrap();
-
-    let user = users::Entity::find_by_id(user_id.into_inner().clone())
-        .one(pool.get_ref())
-        .await;
-
-    match user {
-        Ok(Some(user)) => {
-            let mut user: users::ActiveModel = user.into();
-
-            if let Some(username) = &form.username {
-                user.username = Set(username.clone());
-            }
-
-            if let Some(password) = &form.password {
-                let hashed_password = hash(password, DEFAULT_COST).unwrap();
-                user.password_hash = Set(hashed_password);
-            }
+    state: web::Data<AppState>,
+    form: ValidatedJson<UpdateUser>,
+) -> Result<HttpResponse, AppError> {
+    let key = CacheKey::profile(&state.settings.cache, *user_id);
+    cache::invalidate(state.cache.as_ref(), &key).await;
+
+    let user = users::Entity::find_by_id(user_id.into_inner())
+        .one(&state.db)
+        .await?
+        .ok_or(AppError::NotFound("user"))?;
+
+    let mut user: users::ActiveModel = user.into();
+
+    if let Some(username) = &form.username {
+        let taken = users::Entity::find()
+            .filter(users::Column::Username.eq(username.clone()))
+            .filter(users::Column::Id.ne(user.id.clone().unwrap()))
+            .one(&state.db)
+            .await?;
+        if taken.is_some() {
+            return Err(AppError::Conflict("username is already taken".to_string()));
+        }
+        user.username = Set(username.clone());
+    }
 
-            if let Some(email) = &form.email {
-                user.email = Set(email.clone())
-            }
+    if let Some(password) = &form.password {
+        let hashed_password = hash(password, DEFAULT_COST)?;
+        user.password_hash = Set(hashed_password);
+    }
 
-            let res = user.update(pool.get_ref()).await;
+    // A new address has to be verified again.
+    let email_changed = match &form.email {
+        Some(email) if *email != *user.email.as_ref() => {
+            user.email = Set(email.clone());
+            user.email_verified_at = Set(None);
+            true
+        }
+        _ => false,
+    };
 
-            match res {
-                Ok(user) => {
-                    let active_user = user.clone().into_active_model();
+    let user = user.update(&state.db).await?;
+    cache::put(state.cache.as_ref(), &key, &user).await;
 
-                    let _: () = conn.set_ex(
-                    format!("user_profile_{}", active_user.id.clone().unwrap().to_string()),
-                    format!("id: {}, username: {}, email: {}", active_user.id.unwrap().to_string(), active_user.username.unwrap().to_string(), active_user.email.unwrap().to_string()),
-                    86400,
-                    )
-                    .unwrap();
+    if email_changed {
+        email::send_verification(&state, &user).await?;
+    }
 
-                    Ok(HttpResponse::Ok().json(user))
-                },
-                Err(_) => Ok(HttpResponse::InternalServerError().finish()),
-            }
-        }
-        Ok(None) => Ok(HttpResponse::NotFound().finish()),
-        Err(_) => Ok(HttpResponse::InternalServerError().finish()),
+    // Whoever knew the old password must not stay logged in.
+    if form.password.is_some() {
+        revoke_user(&state.db, user.id, state.clock.now()).await?;
     }
+
+    Ok(HttpResponse::Ok().json(user))
 }
 
 async fn get_budgets(
+    req: HttpRequest,
     user_id: web::ReqData<Uuid>,
-    pool: web::Data<DatabaseConnection>,
-) -> Result<HttpResponse, Error> {
-    let all_budgets = budget::Entity::find()
-        .filter(budget::Column::UserId.eq(user_id.into_inner().clone()))
-        .all(pool.get_ref())
-        .await;
-
-    match all_budgets {
-        Ok(all_budgets) => Ok(HttpResponse::Ok().json(all_budgets)),
-        Err(_) => Ok(HttpResponse::InternalServerError().finish()),
+    filter: web::Query<BudgetFilter>,
+    state: web::Data<AppState>,
+) -> Result<HttpResponse, AppError> {
+    let page = PageRequest::new(filter.page, filter.limit)?;
+    pagination::check_range(filter.from, filter.to, "to", "must not be before from")?;
+    pagination::check_range(
+        filter.min_amount,
+        filter.max_amount,
+        "max_amount",
+        "must not be less than min_amount",
+    )?;
+
+    let sort = match filter.sort {
+        SortField::Date => budget::Column::StartsOn,
+        SortField::Amount => budget::Column::TotalAmount,
+        SortField::CreatedAt => budget::Column::CreatedAt,
+    };
+    let query = budget::Entity::find()
+        .filter(budget::Column::UserId.eq(user_id.into_inner()))
+        .apply_if(filter.from, |q, from| {
+            q.filter(budget::Column::StartsOn.gte(from))
+        })
+        .apply_if(filter.to, |q, to| {
+            q.filter(budget::Column::StartsOn.lte(to))
+        })
+        .apply_if(filter.min_amount, |q, min| {
+            q.filter(budget::Column::TotalAmount.gte(min))
+        })
+        .apply_if(filter.max_amount, |q, max| {
+            q.filter(budget::Column::TotalAmount.lte(max))
+        })
+        .apply_if(filter.q.as_deref(), |q, needle| {
+            q.filter(Expr::col(budget::Column::Name).like(pagination::contains(needle)))
+        })
+        .order_by(sort, filter.order.into())
+        .order_by(budget::Column::Id, filter.order.into());
+    let (budgets, meta) = pagination::fetch(&state.db, query, page).await?;
+
+    Ok(Paginated {
+        data: budgets,
+        pagination: meta,
     }
+    .respond(&req))
 }
 
-async fn get_budget(
-    user_id: web::ReqData<Uuid>,
-    budget_id: web::Path<Uuid>,
-    pool: web::Data<DatabaseConnection>,
-) -> Result<HttpResponse, Error> {
-    let mut conn = get_redis_connection();
-
-    let cached_budget: Option<String> = conn.get(format!("budget_{}_{}", user_id.to_string(), budget_id.to_string())).ok();
-    if let Some(budget_json) = cached_budget {
-        return Ok(HttpResponse::Ok().json(budget_json));
-    }
-
-    let budget = budget::Entity::find()
-        .filter(budget::Column::Id.eq(budget_id.into_inner().clone()))
-        .filter(users::Column::Id.eq(user_id.into_inner().clone()))
-        .one(pool.get_ref())
-        .await;
-
-    match budget {
-        Ok(Some(budget)) => {
-            let active_budget = budget.clone().into_active_model();
-            
-            let _: () = conn.set_ex(
-                format!("budget_{}_{}", active_budget.user_id.clone().unwrap().to_string(), active_budget.id.clone().unwrap().to_string()),
-                format!("id: {}, user_id: {}, name: {}, total_amount:{}, created_at:{}, updated_at:{}", active_budget.id.unwrap().to_string(), active_budget.user_id.unwrap().to_string(), active_budget.name.unwrap().to_string(), active_budget.total_amount.unwrap().to_string(), active_budget.created_at.unwrap().to_string(), active_budget.updated_at.unwrap().to_string()),
-                86400,
-            ).unwrap();
-
-            Ok(HttpResponse::Ok().json(budget))
-        },
-        Ok(None) => Ok(HttpResponse::NotFound().finish()),
-        Err(_) => Ok(HttpResponse::InternalServerError().finish()),
-    }
+async fn get_budget(budget: OwnedBudget) -> Result<HttpResponse, AppError> {
+    Ok(HttpResponse::Ok().json(budget.into_inner()))
 }
 
 async fn post_budget(
     user_id: web::ReqData<Uuid>,
-    pool: web::Data<DatabaseConnection>,
-    form: web::Json<NewBudget>,
-) -> Result<HttpResponse, Error> {
+    state: web::Data<AppState>,
+    form: ValidatedJson<NewBudget>,
+) -> Result<HttpResponse, AppError> {
+    let now = state.clock.now();
+    let budget_period = form.period.unwrap_or(BudgetPeriod::Monthly);
+    let starts_on = form.starts_on.unwrap_or_else(|| now.date_naive());
+    period::check_period(budget_period, starts_on, form.ends_on)?;
+
     let new_budget = budget::ActiveModel {
         id: Set(Uuid::new_v4()),
-        user_id: Set(user_id.into_inner().clone()),
+        user_id: Set(user_id.into_inner()),
         name: Set(form.name.clone()),
-        total_amount: Set(form.total_amount.clone()),
-        created_at: Set(Utc::now().naive_utc().to_string()),
-        updated_at: Set(Utc::now().naive_utc().to_string()),
-        ..Default::default()
+        total_amount: Set(form.total_amount),
+        currency: Set(form
+            .currency
+            .clone()
+            .unwrap_or_else(|| currency::DEFAULT_CURRENCY.to_string())),
+        period: Set(budget_period),
+        starts_on: Set(starts_on),
+        ends_on: Set(form.ends_on),
+        rollover: Set(form.rollover),
+        created_at: Set(now),
+        updated_at: Set(now),
     };
 
-    let res = new_budget.insert(pool.get_ref()).await;
-
-    match res {
-        Ok(insert_budget) => {
-            let budget = budget::Entity::find_by_id(insert_budget.id)
-                .one(pool.get_ref())
-                .await
-                .unwrap();
+    let budget = new_budget.insert(&state.db).await?;
 
-            match budget {
-                Some(budget) => Ok(HttpResponse::Ok().json(budget)),
-                None => Ok(HttpResponse::InternalServerError().finish()),
-            }
-        }
-        Err(_) => Ok(HttpResponse::InternalServerError().finish()),
-    }
+    Ok(HttpResponse::Ok().json(budget))
 }
 
 async fn update_budget(
-    user_id: web::ReqData<Uuid>,
-    budget_id: web::Path<Uuid>,
-    pool: web::Data<DatabaseConnection>,
-    form: web::Data<UpdateBudget>,
-) -> Result<HttpResponse, Error> {
-    let mut conn = get_redis_connection();
-    let _: () = conn.del(format!("budget_{}_{}", user_id.to_string(), budget_id.to_string())).unwrap();
-
-    let budget = budget::Entity::find()
-        .filter(users::Column::Id.eq(user_id.into_inner().clone()))
-        .filter(budget::Column::Id.eq(budget_id.into_inner().clone()))
-        .one(pool.get_ref())
-        .await;
+    budget: OwnedBudget,
+    state: web::Data<AppState>,
+    form: ValidatedJson<UpdateBudget>,
+) -> Result<HttpResponse, AppError> {
+    let key = CacheKey::budget(&state.settings.cache, budget.user_id, budget.id);
+    cache::invalidate(state.cache.as_ref(), &key).await;
+
+    let budget_period = form.period.unwrap_or(budget.period);
+    let starts_on = form.starts_on.unwrap_or(budget.starts_on);
+    let ends_on = match form.ends_on {
+        Some(ends_on) => ends_on,
+        // Switching to a recurring period drops the old custom end date.
+        None if budget_period != BudgetPeriod::Custom => None,
+        None => budget.ends_on,
+    };
+    period::check_period(budget_period, starts_on, ends_on)?;
 
-    match budget {
-        Ok(Some(budget)) => {
-            let mut budget: budget::ActiveModel = budget.into();
+    let mut budget: budget::ActiveModel = budget.into_inner().into();
+    budget.period = Set(budget_period);
+    budget.starts_on = Set(starts_on);
+    budget.ends_on = Set(ends_on);
 
-            if let Some(name) = &form.name {
-                budget.name = Set(name.clone());
-            }
+    if let Some(rollover) = form.rollover {
+        budget.rollover = Set(rollover);
+    }
 
-            if let Some(total_amount) = &form.total_amount {
-                budget.total_amount = Set(total_amount.clone());
-            }
+    if let Some(name) = &form.name {
+        budget.name = Set(name.clone());
+    }
 
-            budget.updated_at = Set(Utc::now().naive_utc().to_string());
-
-            let res = budget.update(pool.get_ref()).await;
-
-            match res {
-                Ok(budget) => {
-                    let active_budget = budget.clone().into_active_model();
-            
-                    let _: () = conn.set_ex(
-                        format!("budget_{}_{}", active_budget.user_id.clone().unwrap().to_string(), active_budget.id.clone().unwrap().to_string()),
-                        format!("id: {}, user_id: {}, name: {}, total_amount:{}, created_at:{}, updated_at:{}", active_budget.id.unwrap().to_string(), active_budget.user_id.unwrap().to_string(), active_budget.name.unwrap().to_string(), active_budget.total_amount.unwrap().to_string(), active_budget.created_at.unwrap().to_string(), active_budget.updated_at.unwrap().to_string()),
-                        86400,
-                    ).unwrap();
-                    Ok(HttpResponse::Ok().json(budget))
-                },
-                Err(_) => Ok(HttpResponse::InternalServerError().finish()),
-            }
-        }
-        Ok(None) => Ok(HttpResponse::NotFound().finish()),
-        Err(_) => Ok(HttpResponse::InternalServerError().finish()),
+    if let Some(total_amount) = &form.total_amount {
+        budget.total_amount = Set(*total_amount);
+    }
+
+    if let Some(currency) = &form.currency {
+        budget.currency = Set(currency.clone());
     }
+
+    budget.updated_at = Set(state.clock.now());
+
+    let budget = budget.update(&state.db).await?;
+    cache::put(state.cache.as_ref(), &key, &budget).await;
+
+    Ok(HttpResponse::Ok().json(budget))
 }
 
 async fn delete_budget(
-    user_id: web::ReqData<Uuid>,
-    budget_id: web::Path<Uuid>,
-    pool: web::Data<DatabaseConnection>,
-) -> Result<HttpResponse, Error> {
-    let mut conn  = get_redis_connection();
-    let _: () = conn.del(format!("budget_{}_{}", user_id.to_string(), budget_id.to_string())).unwrap();
-
-    let res = budget::Entity::delete_many()
-        .filter(users::Column::Id.eq(user_id.into_inner().clone()))
-        .filter(budget::Column::Id.eq(budget_id.into_inner().clone()))
-        .exec(pool.get_ref())
+    budget: OwnedBudget,
+    state: web::Data<AppState>,
+) -> Result<HttpResponse, AppError> {
+    let OwnedBudget(budget::Model {
+        id: budget_id,
+        user_id,
+        ..
+    }) = budget;
+
+    cache::invalidate(
+        state.cache.as_ref(),
+        &CacheKey::budget(&state.settings.cache, user_id, budget_id),
+    )
+    .await;
+
+    // Lines that expenses elsewhere charged to this budget are charged back to
+    // the expense's own budget; their other lines stay as they are.
+    let affected: Vec<(Uuid, Uuid)> = expense::Entity::find()
+        .select_only()
+        .column(expense::Column::BudgetId)
+        .column(expense::Column::Id)
+        .filter(
+            Condition::any()
+                .add(expense::Column::BudgetId.eq(budget_id))
+                .add(
+                    expense::Column::Id.in_subquery(
+                        Query::select()
+                            .column(expense_split::Column::ExpenseId)
+                            .from(expense_split::Entity)
+                            .and_where(expense_split::Column::BudgetId.eq(budget_id))
+                            .to_owned(),
+                    ),
+                ),
+        )
+        .into_tuple()
+        .all(&state.db)
+        .await?;
+    for &(expense_budget_id, expense_id) in &affected {
+        cache::invalidate(
+            state.cache.as_ref(),
+            &CacheKey::expense(
+                &state.settings.cache,
+                user_id,
+                expense_budget_id,
+                expense_id,
+            ),
+        )
         .await;
+    }
 
-    match res {
-        Ok(_) => Ok(HttpResponse::Ok().finish()),
-        Err(_) => Ok(HttpResponse::InternalServerError().finish()),
+    let res = state
+        .db
+        .transaction::<_, _, DbErr>(|txn| {
+            Box::pin(async move {
+                for (expense_budget_id, expense_id) in affected {
+                    if expense_budget_id == budget_id {
+                        continue;
+                    }
+                    expense_split::Entity::update_many()
+                        .col_expr(
+                            expense_split::Column::BudgetId,
+                            Expr::value(expense_budget_id),
+                        )
+                        .filter(expense_split::Column::ExpenseId.eq(expense_id))
+                        .filter(expense_split::Column::BudgetId.eq(budget_id))
+                        .exec(txn)
+                        .await?;
+                }
+                expense_split::Entity::delete_many()
+                    .filter(
+                        expense_split::Column::ExpenseId.in_subquery(
+                            Query::select()
+                                .column(expense::Column::Id)
+                                .from(expense::Entity)
+                                .and_where(expense::Column::BudgetId.eq(budget_id))
+                                .to_owned(),
+                        ),
+                    )
+                    .exec(txn)
+                    .await?;
+                expense_tag::Entity::delete_many()
+                    .filter(
+                        expense_tag::Column::ExpenseId.in_subquery(
+                            Query::select()
+                                .column(expense::Column::Id)
+                                .from(expense::Entity)
+                                .and_where(expense::Column::BudgetId.eq(budget_id))
+                                .to_owned(),
+                        ),
+                    )
+                    .exec(txn)
+                    .await?;
+                expense::Entity::delete_many()
+                    .filter(expense::Column::BudgetId.eq(budget_id))
+                    .exec(txn)
+                    .await?;
+                recurring_expense::Entity::delete_many()
+                    .filter(recurring_expense::Column::BudgetId.eq(budget_id))
+                    .exec(txn)
+                    .await?;
+                // Income outlives the budget it was meant for.
+                entities::income::Entity::update_many()
+                    .col_expr(
+                        entities::income::Column::BudgetId,
+                        Expr::value(Option::<Uuid>::None),
+                    )
+                    .filter(entities::income::Column::BudgetId.eq(budget_id))
+                    .exec(txn)
+                    .await?;
+                budget_category::Entity::delete_many()
+                    .filter(budget_category::Column::BudgetId.eq(budget_id))
+                    .exec(txn)
+                    .await?;
+
+                budget::Entity::delete_by_id(budget_id).exec(txn).await
+            })
+        })
+        .await?;
+
+    if res.rows_affected == 0 {
+        return Err(AppError::NotFound("budget"));
     }
+    Ok(HttpResponse::Ok().finish())
 }
 
-async fn get_expenses(
-    user_id: web::ReqData<Uuid>,
-    budget_id: web::Path<Uuid>,
-    pool: web::Data<DatabaseConnection>,
-) -> Result<HttpResponse, Error> {
-    let expenses = expense::Entity::find()
-        .filter(users::Column::Id.eq(user_id.into_inner().clone()))
-        .filter(budget::Column::Id.eq(budget_id.into_inner().clone()))
-        .all(pool.get_ref())
-        .await;
+/// An expense as returned by the API, together with its tag names and split
+/// lines.
+#[derive(Serialize, Deserialize)]
+struct ExpenseView {
+    #[serde(flatten)]
+    expense: expense::Model,
+    tags: Vec<String>,
+    splits: Vec<expense_split::Model>,
+}
 
-    match expenses {
-        Ok(expenses) => Ok(HttpResponse::Ok().json(expenses)),
-        Err(_) => Ok(HttpResponse::InternalServerError().finish()),
-    }
+async fn expense_view<C: ConnectionTrait>(
+    db: &C,
+    expense: expense::Model,
+) -> Result<ExpenseView, DbErr> {
+    let tags = tag::tag_names(db, &[expense.id])
+        .await?
+        .remove(&expense.id)
+        .unwrap_or_default();
+    let splits = split::split_lines(db, &[expense.id])
+        .await?
+        .remove(&expense.id)
+        .unwrap_or_default();
+    Ok(ExpenseView {
+        expense,
+        tags,
+        splits,
+    })
 }
 
-async fn get_expense(
-    user_id: web::ReqData<Uuid>,
-    budget_id: web::Path<Uuid>,
-    expense_id: web::Path<Uuid>,
-    pool: web::Data<DatabaseConnection>,
-) -> Result<HttpResponse, Error> {
-    let mut conn = get_redis_connection();
-    
-    let cached_budget: Option<String> = conn.get(format!("expense_{}_{}_{}", user_id.to_string(), budget_id.to_string(), expense_id.to_string())).ok();
-    if let Some(budget_json) = cached_budget {
-        return Ok(HttpResponse::Ok().json(budget_json));
+async fn get_expenses(
+    req: HttpRequest,
+    budget: OwnedBudget,
+    filter: web::Query<ExpenseFilter>,
+    state: web::Data<AppState>,
+) -> Result<HttpResponse, AppError> {
+    let page = PageRequest::new(filter.page, filter.limit)?;
+    pagination::check_range(filter.from, filter.to, "to", "must not be before from")?;
+    pagination::check_range(
+        filter.min_amount,
+        filter.max_amount,
+        "max_amount",
+        "must not be less than min_amount",
+    )?;
+
+    let sort = match filter.sort {
+        SortField::Date => expense::Column::Date,
+        SortField::Amount => expense::Column::Amount,
+        SortField::CreatedAt => expense::Column::CreatedAt,
+    };
+    let query = expense::Entity::find()
+        .filter(expense::Column::BudgetId.eq(budget.id))
+        .apply_if(filter.tag.as_deref(), |q, name| {
+            q.filter(expense::Column::Id.in_subquery(tag::tagged(budget.user_id, name)))
+        })
+        .apply_if(filter.from, |q, from| {
+            q.filter(expense::Column::Date.gte(from))
+        })
+        .apply_if(filter.to, |q, to| q.filter(expense::Column::Date.lte(to)))
+        .apply_if(filter.min_amount, |q, min| {
+            q.filter(expense::Column::Amount.gte(min))
+        })
+        .apply_if(filter.max_amount, |q, max| {
+            q.filter(expense::Column::Amount.lte(max))
+        })
+        .apply_if(filter.q.as_deref(), |q, needle| {
+            q.filter(Expr::col(expense::Column::Description).like(pagination::contains(needle)))
+        })
+        .order_by(sort, filter.order.into())
+        .order_by(expense::Column::Id, filter.order.into());
+    let (expenses, meta) = pagination::fetch(&state.db, query, page).await?;
+
+    let ids: Vec<Uuid> = expenses.iter().map(|e| e.id).collect();
+    let mut tags = tag::tag_names(&state.db, &ids).await?;
+    let mut splits = split::split_lines(&state.db, &ids).await?;
+    let expenses: Vec<ExpenseView> = expenses
+        .into_iter()
+        .map(|expense| ExpenseView {
+            tags: tags.remove(&expense.id).unwrap_or_default(),
+            splits: splits.remove(&expense.id).unwrap_or_default(),
+            expense,
+        })
+        .collect();
+
+    Ok(Paginated {
+        data: expenses,
+        pagination: meta,
     }
+    .respond(&req))
+}
 
-    let expense = expense::Entity::find()
-        .filter(users::Column::Id.eq(user_id.clone().into_inner().clone()))
-        .filter(budget::Column::Id.eq(budget_id.into_inner().clone()))
-        .filter(expense::Column::Id.eq(expense_id.into_inner().clone()))
-        .one(pool.get_ref())
-        .await;
+async fn get_expense(
+    budget: OwnedBudget,
+    path: web::Path<(Uuid, Uuid)>,
+    state: web::Data<AppState>,
+) -> Result<HttpResponse, AppError> {
+    let (_, expense_id) = path.into_inner();
+    let key = CacheKey::expense(&state.settings.cache, budget.user_id, budget.id, expense_id);
+
+    let expense = cache::get_or_load(state.cache.as_ref(), &key, || async {
+        let expense = expense::Entity::find()
+            .filter(expense::Column::BudgetId.eq(budget.id))
+            .filter(expense::Column::Id.eq(expense_id))
+            .one(&state.db)
+            .await?;
+        match expense {
+            Some(expense) => expense_view(&state.db, expense).await.map(Some),
+            None => Ok(None),
+        }
+    })
+    .await?
+    .ok_or(AppError::NotFound("expense"))?;
 
-    match expense {
-        Ok(Some(expense)) => {
-            let active_expense = expense.clone().into_active_model();
-            
-            let _: () = conn.set_ex(
-                format!("expense_{}_{}_{}", user_id.to_string(), active_expense.clone().budget_id.unwrap().to_string(), active_expense.id.clone().unwrap().to_string()),
-                format!("id: {}, budget_id: {}, amount: {}, description: {}, date: {}, created_at: {}, updated_at:{}", active_expense.id.unwrap().to_string(), active_expense.budget_id.unwrap().to_string(), active_expense.amount.unwrap().to_string(), active_expense.description.unwrap().to_string(), active_expense.date.unwrap().to_string(), active_expense.created_at.unwrap().to_string(), active_expense.updated_at.unwrap().to_string()),
-                86400
-            ).unwrap();
-
-            Ok(HttpResponse::Ok().json(expense))
-        },
-        Ok(None) => Ok(HttpResponse::NotFound().finish()),
-        Err(_) => Ok(HttpResponse::InternalServerError().finish()),
-    }
+    Ok(HttpResponse::Ok().json(expense))
 }
 
 async fn post_expense(
-    budget_id: web::Path<Uuid>,
-    form: web::Json<NewExpense>,
-    pool: web::Data<DatabaseConnection>,
-) -> Result<HttpResponse, Error> {
+    budget: OwnedBudget,
+    form: ValidatedJson<NewExpense>,
+    state: web::Data<AppState>,
+) -> Result<HttpResponse, AppError> {
+    category::check_category_field(&state.db, budget.user_id, "category_id", form.category_id)
+        .await?;
+    account::check_account_field(&state.db, budget.user_id, "account_id", form.account_id).await?;
+    split::check_splits(&state.db, budget.user_id, form.amount, &form.splits).await?;
+    let now = state.clock.now();
+    let user_id = budget.user_id;
+    let form = form.into_inner();
+
     let new_expense = expense::ActiveModel {
         id: Set(Uuid::new_v4()),
-        budget_id: Set(budget_id.into_inner().clone()),
-        amount: Set(form.amount.clone()),
-        description: Set(form.description.clone()),
-        date: Set(Utc::now().date_naive().to_string()),
-        created_at: Set(Utc::now().naive_utc().to_string()),
-        updated_at: Set(Utc::now().naive_utc().to_string()),
-        ..Default::default()
+        budget_id: Set(budget.id),
+        amount: Set(form.amount),
+        currency: Set(form.currency.unwrap_or_else(|| budget.currency.clone())),
+        description: Set(form.description),
+        date: Set(form.date.unwrap_or_else(|| now.date_naive())),
+        category_id: Set(form.category_id),
+        recurring_expense_id: Set(None),
+        account_id: Set(form.account_id),
+        created_at: Set(now),
+        updated_at: Set(now),
     };
 
-    let res = new_expense.insert(pool.get_ref()).await;
+    let expense = state
+        .db
+        .transaction::<_, _, DbErr>(|txn| {
+            Box::pin(async move {
+                let expense = new_expense.insert(txn).await?;
+                let tags = tag::assign_tags(txn, user_id, expense.id, &form.tags, now).await?;
+                let splits =
+                    split::assign_splits(txn, expense.id, expense.budget_id, &form.splits).await?;
+                Ok(ExpenseView {
+                    expense,
+                    tags,
+                    splits,
+                })
+            })
+        })
+        .await?;
+
+    Ok(HttpResponse::Ok().json(expense))
+}
 
-    match res {
-        Ok(insert_expense) => {
-            let expense = expense::Entity::find_by_id(insert_expense.id)
-                .one(pool.get_ref())
-                .await
-                .unwrap();
+async fn update_expense(
+    budget: OwnedBudget,
+    path: web::Path<(Uuid, Uuid)>,
+    form: ValidatedJson<UpdateExpense>,
+    state: web::Data<AppState>,
+) -> Result<HttpResponse, AppError> {
+    let (_, expense_id) = path.into_inner();
+    let key = CacheKey::expense(&state.settings.cache, budget.user_id, budget.id, expense_id);
+    cache::invalidate(state.cache.as_ref(), &key).await;
 
-            match expense {
-                Some(expense) => Ok(HttpResponse::Ok().json(expense)),
-                None => Ok(HttpResponse::InternalServerError().finish()),
+    let expense = expense::Entity::find()
+        .filter(expense::Column::BudgetId.eq(budget.id))
+        .filter(expense::Column::Id.eq(expense_id))
+        .one(&state.db)
+        .await?
+        .ok_or(AppError::NotFound("expense"))?;
+
+    let form = form.into_inner();
+    let amount = form.amount.unwrap_or(expense.amount);
+    match &form.splits {
+        Some(splits) => split::check_splits(&state.db, budget.user_id, amount, splits).await?,
+        None if amount != expense.amount => {
+            let splits = split::split_lines(&state.db, &[expense.id])
+                .await?
+                .remove(&expense.id)
+                .unwrap_or_default();
+            if !splits.is_empty() {
+                return Err(AppError::field(
+                    "splits",
+                    "must be sent again to add up to the new amount",
+                ));
             }
         }
-        Err(_) => Ok(HttpResponse::InternalServerError().finish()),
+        None => {}
     }
-}
-
-async fn update_expense(
-    user_id: web::ReqData<Uuid>,
-    budget_id: web::Path<Uuid>,
-    expense_id: web::Path<Uuid>,
-    form: web::Data<UpdateExpense>,
-    pool: web::Data<DatabaseConnection>,
-) -> Result<HttpResponse, Error> {
-    let mut conn = get_redis_connection();
-    let _:() = conn.del(format!("expense_{}_{}_{}", user_id.to_string(), budget_id.to_string(), expense_id.to_string())).unwrap();
-
-    let expense: Result<Option<expense::Model>, prelude::DbErr> = expense::Entity::find()
-        .filter(users::Column::Id.eq(user_id.clone().into_inner().clone()))
-        .filter(budget::Column::Id.eq(budget_id.into_inner().clone()))
-        .filter(expense::Column::Id.eq(expense_id.into_inner().clone()))
-        .one(pool.get_ref())
-        .await;
 
-    match expense {
-        Ok(Some(expense)) => {
-            let mut expense: expense::ActiveModel = expense.into();
+    let mut expense: expense::ActiveModel = expense.into();
 
-            if let Some(amount) = &form.amount {
-                expense.amount = Set(amount.clone());
-            }
+    if let Some(amount) = form.amount {
+        expense.amount = Set(amount);
+    }
 
-            if let Some(description) = &form.description {
-                expense.description = Set(description.clone());
-            }
+    if let Some(currency) = form.currency {
+        expense.currency = Set(currency);
+    }
 
-            expense.updated_at = Set(Utc::now().naive_utc().to_string());
+    if let Some(description) = form.description {
+        expense.description = Set(description);
+    }
 
-            let res = expense.update(pool.as_ref()).await;
+    if let Some(date) = form.date {
+        expense.date = Set(date);
+    }
 
-            match res {
-                Ok(expense) => {
-                    let active_expense = expense.clone().into_active_model();
-            
-                    let _: () = conn.set_ex(
-                    format!("expense_{}_{}_{}", user_id.to_string(), active_expense.clone().budget_id.unwrap().to_string(), active_expense.id.clone().unwrap().to_string()),
-                    format!("id: {}, budget_id: {}, amount: {}, description: {}, date: {}, created_at: {}, updated_at:{}", active_expense.id.unwrap().to_string(), active_expense.budget_id.unwrap().to_string(), active_expense.amount.unwrap().to_string(), active_expense.description.unwrap().to_string(), active_expense.date.unwrap().to_string(), active_expense.created_at.unwrap().to_string(), active_expense.updated_at.unwrap().to_string()),
-                    86400
-                    ).unwrap();
+    if let Some(category_id) = form.category_id {
+        category::check_category_field(&state.db, budget.user_id, "category_id", category_id)
+            .await?;
+        expense.category_id = Set(category_id);
+    }
 
-                    Ok(HttpResponse::Ok().json(expense))
-                },
-                Err(_) => Ok(HttpResponse::InternalServerError().finish()),
-            }
-        }
-        Ok(None) => Ok(HttpResponse::NotFound().finish()),
-        Err(_) => Ok(HttpResponse::InternalServerError().finish()),
+    if let Some(account_id) = form.account_id {
+        account::check_account_field(&state.db, budget.user_id, "account_id", account_id).await?;
+        expense.account_id = Set(account_id);
     }
+
+    let now = state.clock.now();
+    expense.updated_at = Set(now);
+
+    let user_id = budget.user_id;
+    let expense = state
+        .db
+        .transaction::<_, _, DbErr>(|txn| {
+            Box::pin(async move {
+                let expense = expense.update(txn).await?;
+                if let Some(tags) = form.tags {
+                    tag::assign_tags(txn, user_id, expense.id, &tags, now).await?;
+                }
+                if let Some(splits) = form.splits {
+                    split::assign_splits(txn, expense.id, expense.budget_id, &splits).await?;
+                }
+                expense_view(txn, expense).await
+            })
+        })
+        .await?;
+    cache::put(state.cache.as_ref(), &key, &expense).await;
+
+    Ok(HttpResponse::Ok().json(expense))
 }
 
 async fn delete_expense(
-    user_id: web::ReqData<Uuid>,
-    budget_id: web::Path<Uuid>,
-    expense_id: web::Path<Uuid>,
-    pool: web::Data<DatabaseConnection>,
-) -> Result<HttpResponse, Error> {
-    let mut conn = get_redis_connection();
-    let _:() = conn.del(format!("expense_{}_{}_{}", user_id.to_string(), budget_id.to_string(), expense_id.to_string())).unwrap();
-
-    let res = budget::Entity::delete_many()
-        .filter(users::Column::Id.eq(user_id.into_inner().clone()))
-        .filter(budget::Column::Id.eq(budget_id.into_inner().clone()))
-        .filter(expense::Column::Id.eq(expense_id.into_inner().clone()))
-        .exec(pool.get_ref())
-        .await;
-
-    match res {
-        Ok(_) => Ok(HttpResponse::Ok().finish()),
-        Err(_) => Ok(HttpResponse::InternalServerError().finish()),
+    budget: OwnedBudget,
+    path: web::Path<(Uuid, Uuid)>,
+    state: web::Data<AppState>,
+) -> Result<HttpResponse, AppError> {
+    let (_, expense_id) = path.into_inner();
+    cache::invalidate(
+        state.cache.as_ref(),
+        &CacheKey::expense(&state.settings.cache, budget.user_id, budget.id, expense_id),
+    )
+    .await;
+
+    let budget_id = budget.id;
+    let res = state
+        .db
+        .transaction::<_, _, DbErr>(|txn| {
+            Box::pin(async move {
+                expense_tag::Entity::delete_many()
+                    .filter(
+                        expense_tag::Column::ExpenseId.in_subquery(
+                            Query::select()
+                                .column(expense::Column::Id)
+                                .from(expense::Entity)
+                                .and_where(expense::Column::BudgetId.eq(budget_id))
+                                .and_where(expense::Column::Id.eq(expense_id))
+                                .to_owned(),
+                        ),
+                    )
+                    .exec(txn)
+                    .await?;
+                expense_split::Entity::delete_many()
+                    .filter(
+                        expense_split::Column::ExpenseId.in_subquery(
+                            Query::select()
+                                .column(expense::Column::Id)
+                                .from(expense::Entity)
+                                .and_where(expense::Column::BudgetId.eq(budget_id))
+                                .and_where(expense::Column::Id.eq(expense_id))
+                                .to_owned(),
+                        ),
+                    )
+                    .exec(txn)
+                    .await?;
+                expense::Entity::delete_many()
+                    .filter(expense::Column::BudgetId.eq(budget_id))
+                    .filter(expense::Column::Id.eq(expense_id))
+                    .exec(txn)
+                    .await
+            })
+        })
+        .await?;
+
+    if res.rows_affected == 0 {
+        return Err(AppError::NotFound("expense"));
     }
-}
\ No newline at end of file
+    Ok(HttpResponse::Ok().finish())
+}
@@ -0,0 +1,194 @@
+use actix_web::{web, HttpResponse};
+use chrono::NaiveDate;
+use entities::{budget, money::Money, sea_orm_active_enums::BudgetPeriod};
+use serde::Serialize;
+use uuid::Uuid;
+
+use crate::utility::{
+    currency::Rates,
+    db_structs::PeriodHistoryQuery,
+    error::AppError,
+    ownership::OwnedBudget,
+    period::{self, Period, PeriodSummary},
+    spending,
+    state::AppState,
+};
+
+const DEFAULT_HISTORY: u32 = 12;
+const MAX_HISTORY: u32 = 120;
+
+/// Where a budget stands in its current period.
+#[derive(Serialize)]
+struct BudgetSummary {
+    budget_id: Uuid,
+    /// Currency of every amount in the summary.
+    currency: String,
+    #[serde(flatten)]
+    period: Period,
+    /// The allowance plus anything carried over from the previous period.
+    available: Money,
+    spent: Money,
+    remaining: Money,
+    /// `spent` as a percentage of `available`, to two decimal places; `null`
+    /// when nothing is available.
+    percent_used: Option<f64>,
+    average_daily_spend: Money,
+    /// Spending by the end of the period if the average daily spend holds.
+    projected_spend: Money,
+    /// Days of the period up to and including today.
+    days_elapsed: i64,
+    /// Days of the period after today.
+    days_remaining: i64,
+}
+
+/// Checks that `ends_on` is set exactly for custom periods and does not come
+/// before `starts_on`.
+pub(crate) fn check_period(
+    period: BudgetPeriod,
+    starts_on: NaiveDate,
+    ends_on: Option<NaiveDate>,
+) -> Result<(), AppError> {
+    match (period, ends_on) {
+        (BudgetPeriod::Custom, None) => {
+            Err(AppError::field("ends_on", "is required for custom periods"))
+        }
+        (BudgetPeriod::Custom, Some(ends_on)) if ends_on < starts_on => {
+            Err(AppError::field("ends_on", "must not be before starts_on"))
+        }
+        (BudgetPeriod::Custom, Some(_)) | (_, None) => Ok(()),
+        (_, Some(_)) => Err(AppError::field(
+            "ends_on",
+            "is only allowed for custom periods",
+        )),
+    }
+}
+
+/// Summaries of every period up to and including the current one, oldest
+/// first. Split expenses count only with their lines for this budget.
+/// Expenses in other currencies are converted into the budget's
+/// currency at the rate in effect on their date.
+pub(crate) async fn summaries(
+    state: &AppState,
+    budget: &budget::Model,
+) -> Result<Vec<PeriodSummary>, AppError> {
+    let today = state.clock.now().date_naive();
+    let current = period::index_of(budget, today);
+    let until = period::nth(budget, current).map_or(today, |p| p.end);
+
+    let spending =
+        spending::budget_spending(&state.db, budget.id, Some(budget.starts_on), Some(until))
+            .await?;
+
+    let rates = Rates::load(
+        &state.db,
+        &budget.currency,
+        spending.iter().map(|(_, currency, _, _)| currency),
+    )
+    .await?;
+    let mut spending = spending
+        .into_iter()
+        .map(|(_, currency, date, amount)| Ok((date, rates.convert(amount, &currency, date)?)))
+        .collect::<Result<Vec<_>, AppError>>()?;
+    spending.sort_by_key(|(date, _)| *date);
+
+    Ok(period::history(budget, &spending, current))
+}
+
+pub(super) async fn get_current_period(
+    budget: OwnedBudget,
+    state: web::Data<AppState>,
+) -> Result<HttpResponse, AppError> {
+    let current = summaries(&state, &budget)
+        .await?
+        .pop()
+        .ok_or(AppError::NotFound("period"))?;
+
+    Ok(HttpResponse::Ok().json(current))
+}
+
+/// Past periods and the current one, newest first.
+pub(super) async fn get_period_history(
+    budget: OwnedBudget,
+    query: web::Query<PeriodHistoryQuery>,
+    state: web::Data<AppState>,
+) -> Result<HttpResponse, AppError> {
+    let limit = query.limit.unwrap_or(DEFAULT_HISTORY);
+    if !(1..=MAX_HISTORY).contains(&limit) {
+        return Err(AppError::field("limit", "must be between 1 and 120"));
+    }
+
+    let history: Vec<PeriodSummary> = summaries(&state, &budget)
+        .await?
+        .into_iter()
+        .rev()
+        .take(limit as usize)
+        .collect();
+
+    Ok(HttpResponse::Ok().json(history))
+}
+
+/// `numerator / denominator`, rounded half away from zero.
+fn div_round(numerator: i128, denominator: i128) -> Money {
+    let half = denominator / 2;
+    let rounded = if numerator < 0 {
+        (numerator - half) / denominator
+    } else {
+        (numerator + half) / denominator
+    };
+    Money::from_minor_units(i64::try_from(rounded).unwrap_or(if rounded < 0 {
+        i64::MIN
+    } else {
+        i64::MAX
+    }))
+}
+
+pub(super) async fn get_budget_summary(
+    budget: OwnedBudget,
+    state: web::Data<AppState>,
+) -> Result<HttpResponse, AppError> {
+    let current = summaries(&state, &budget)
+        .await?
+        .pop()
+        .ok_or(AppError::NotFound("period"))?;
+    let Period { start, end } = current.period;
+
+    // Before the period starts none of it has elapsed; after it ends all of it
+    // has.
+    let today = state
+        .clock
+        .now()
+        .date_naive()
+        .clamp(start.pred_opt().unwrap_or(start), end);
+    let length = (end - start).num_days() + 1;
+    let days_elapsed = (today - start).num_days() + 1;
+    let days_remaining = length - days_elapsed;
+
+    let spent = i128::from(current.spent.minor_units());
+    let available = current.available;
+    let percent_used = available.is_positive().then(|| {
+        let percent = current.spent.minor_units() as f64 / available.minor_units() as f64 * 100.0;
+        (percent * 100.0).round() / 100.0
+    });
+    let (average_daily_spend, projected_spend) = if days_elapsed > 0 {
+        (
+            div_round(spent, i128::from(days_elapsed)),
+            div_round(spent * i128::from(length), i128::from(days_elapsed)),
+        )
+    } else {
+        (Money::ZERO, current.spent)
+    };
+
+    Ok(HttpResponse::Ok().json(BudgetSummary {
+        budget_id: budget.id,
+        currency: budget.currency.clone(),
+        period: current.period,
+        available,
+        spent: current.spent,
+        remaining: current.remaining,
+        percent_used,
+        average_daily_spend,
+        projected_spend,
+        days_elapsed,
+        days_remaining,
+    }))
+}
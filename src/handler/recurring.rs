@@ -0,0 +1,223 @@
+use actix_web::{web, HttpResponse};
+use chrono::NaiveDate;
+use entities::{expense, recurring_expense};
+use sea_orm::{
+    entity::*, sea_query::Expr, ConnectionTrait, DbErr, QueryFilter, QueryOrder, QuerySelect,
+    TransactionTrait,
+};
+use uuid::Uuid;
+
+use super::category;
+use crate::utility::{
+    cache::{self, CacheKey},
+    db_structs::{NewRecurringExpense, UpdateRecurringExpense},
+    error::AppError,
+    ownership::OwnedBudget,
+    recurring,
+    state::AppState,
+    validation::ValidatedJson,
+};
+
+/// Loads a template of `budget_id`.
+async fn find_template<C: ConnectionTrait>(
+    db: &C,
+    budget_id: Uuid,
+    template_id: Uuid,
+) -> Result<recurring_expense::Model, AppError> {
+    recurring_expense::Entity::find_by_id(template_id)
+        .filter(recurring_expense::Column::BudgetId.eq(budget_id))
+        .one(db)
+        .await?
+        .ok_or(AppError::NotFound("recurring expense"))
+}
+
+/// Checks that a template ends in at most one way, and not before it starts.
+fn check_end(
+    starts_on: NaiveDate,
+    ends_on: Option<NaiveDate>,
+    count: Option<i32>,
+) -> Result<(), AppError> {
+    if ends_on.is_some() && count.is_some() {
+        return Err(AppError::field("count", "cannot be combined with ends_on"));
+    }
+    if ends_on.is_some_and(|ends_on| ends_on < starts_on) {
+        return Err(AppError::field("ends_on", "must not be before starts_on"));
+    }
+    if count.is_some_and(|count| !(1..=10_000).contains(&count)) {
+        return Err(AppError::field("count", "must be between 1 and 10000"));
+    }
+    Ok(())
+}
+
+/// Creates whatever has come due right away instead of waiting for the
+/// scheduler, and returns the template as it is afterwards.
+async fn catch_up(
+    state: &AppState,
+    template: recurring_expense::Model,
+) -> Result<recurring_expense::Model, AppError> {
+    let now = state.clock.now();
+    if recurring::materialize(&state.db, &template, now.date_naive(), now).await? == 0 {
+        return Ok(template);
+    }
+    find_template(&state.db, template.budget_id, template.id).await
+}
+
+pub(super) async fn get_recurring_expenses(
+    budget: OwnedBudget,
+    state: web::Data<AppState>,
+) -> Result<HttpResponse, AppError> {
+    let templates = recurring_expense::Entity::find()
+        .filter(recurring_expense::Column::BudgetId.eq(budget.id))
+        .order_by_asc(recurring_expense::Column::CreatedAt)
+        .order_by_asc(recurring_expense::Column::Id)
+        .all(&state.db)
+        .await?;
+
+    Ok(HttpResponse::Ok().json(templates))
+}
+
+pub(super) async fn get_recurring_expense(
+    budget: OwnedBudget,
+    path: web::Path<(Uuid, Uuid)>,
+    state: web::Data<AppState>,
+) -> Result<HttpResponse, AppError> {
+    let (_, template_id) = path.into_inner();
+    let template = find_template(&state.db, budget.id, template_id).await?;
+
+    Ok(HttpResponse::Ok().json(template))
+}
+
+pub(super) async fn post_recurring_expense(
+    budget: OwnedBudget,
+    form: ValidatedJson<NewRecurringExpense>,
+    state: web::Data<AppState>,
+) -> Result<HttpResponse, AppError> {
+    category::check_category_field(&state.db, budget.user_id, "category_id", form.category_id)
+        .await?;
+    let now = state.clock.now();
+    let form = form.into_inner();
+    let starts_on = form.starts_on.unwrap_or_else(|| now.date_naive());
+    check_end(starts_on, form.ends_on, form.count)?;
+
+    let mut template = recurring_expense::Model {
+        id: Uuid::new_v4(),
+        budget_id: budget.id,
+        amount: form.amount,
+        currency: form.currency.unwrap_or_else(|| budget.currency.clone()),
+        description: form.description,
+        category_id: form.category_id,
+        frequency: form.frequency,
+        interval: form.interval.unwrap_or(1),
+        starts_on,
+        ends_on: form.ends_on,
+        count: form.count,
+        occurrences: 0,
+        next_occurrence: None,
+        created_at: now,
+        updated_at: now,
+    };
+    template.next_occurrence = recurring::nth(&template, 0);
+    let template = recurring_expense::ActiveModel::from(template)
+        .insert(&state.db)
+        .await?;
+    let template = catch_up(&state, template).await?;
+
+    Ok(HttpResponse::Ok().json(template))
+}
+
+pub(super) async fn update_recurring_expense(
+    budget: OwnedBudget,
+    path: web::Path<(Uuid, Uuid)>,
+    form: ValidatedJson<UpdateRecurringExpense>,
+    state: web::Data<AppState>,
+) -> Result<HttpResponse, AppError> {
+    let (_, template_id) = path.into_inner();
+    let template = find_template(&state.db, budget.id, template_id).await?;
+    let form = form.into_inner();
+    if let Some(category_id) = form.category_id {
+        category::check_category_field(&state.db, budget.user_id, "category_id", category_id)
+            .await?;
+    }
+
+    let ends_on = form.ends_on.unwrap_or(template.ends_on);
+    let count = form.count.unwrap_or(template.count);
+    check_end(template.starts_on, ends_on, count)?;
+
+    let mut updated = template.clone();
+    updated.ends_on = ends_on;
+    updated.count = count;
+    let next_occurrence =
+        recurring::nth(&updated, u32::try_from(template.occurrences).unwrap_or(0));
+
+    let mut template: recurring_expense::ActiveModel = template.into();
+    if let Some(amount) = form.amount {
+        template.amount = Set(amount);
+    }
+    if let Some(currency) = form.currency {
+        template.currency = Set(currency);
+    }
+    if let Some(description) = form.description {
+        template.description = Set(description);
+    }
+    if let Some(category_id) = form.category_id {
+        template.category_id = Set(category_id);
+    }
+    template.ends_on = Set(ends_on);
+    template.count = Set(count);
+    template.next_occurrence = Set(next_occurrence);
+    template.updated_at = Set(state.clock.now());
+
+    let template = template.update(&state.db).await?;
+    let template = catch_up(&state, template).await?;
+
+    Ok(HttpResponse::Ok().json(template))
+}
+
+/// Stops the template. Expenses already created from it are kept.
+pub(super) async fn delete_recurring_expense(
+    budget: OwnedBudget,
+    path: web::Path<(Uuid, Uuid)>,
+    state: web::Data<AppState>,
+) -> Result<HttpResponse, AppError> {
+    let (_, template_id) = path.into_inner();
+    let template = find_template(&state.db, budget.id, template_id).await?;
+
+    let affected: Vec<(Uuid, Uuid)> = state
+        .db
+        .transaction::<_, _, DbErr>(|txn| {
+            Box::pin(async move {
+                let affected = expense::Entity::find()
+                    .select_only()
+                    .column(expense::Column::BudgetId)
+                    .column(expense::Column::Id)
+                    .filter(expense::Column::RecurringExpenseId.eq(template.id))
+                    .into_tuple()
+                    .all(txn)
+                    .await?;
+                expense::Entity::update_many()
+                    .col_expr(
+                        expense::Column::RecurringExpenseId,
+                        Expr::value(None::<Uuid>),
+                    )
+                    .filter(expense::Column::RecurringExpenseId.eq(template.id))
+                    .exec(txn)
+                    .await?;
+                recurring_expense::Entity::delete_by_id(template.id)
+                    .exec(txn)
+                    .await?;
+                Ok(affected)
+            })
+        })
+        .await?;
+
+    // Cached copies would still point at the template.
+    for (budget_id, expense_id) in affected {
+        cache::invalidate(
+            state.cache.as_ref(),
+            &CacheKey::expense(&state.settings.cache, budget.user_id, budget_id, expense_id),
+        )
+        .await;
+    }
+
+    Ok(HttpResponse::Ok().finish())
+}
@@ -0,0 +1,210 @@
+use actix_web::{web, HttpResponse};
+use chrono::NaiveDate;
+use entities::{budget, expense, income, money::Money, sea_orm_active_enums::BudgetPeriod};
+use sea_orm::{
+    entity::*, JoinType, QueryFilter, QueryOrder, QuerySelect, QueryTrait, RelationTrait,
+};
+use serde::Serialize;
+use uuid::Uuid;
+
+use crate::utility::{
+    currency::{self, Rates},
+    db_structs::CashFlowQuery,
+    error::AppError,
+    ownership, pagination,
+    period::{self, Period},
+    spending,
+    state::AppState,
+};
+
+const DEFAULT_PERIODS: usize = 12;
+const MAX_PERIODS: usize = 120;
+
+/// Money in and out during one calendar period.
+#[derive(Serialize)]
+struct CashFlow {
+    #[serde(flatten)]
+    period: Period,
+    income: Money,
+    expenses: Money,
+    /// `income` minus `expenses`; negative when more went out than came in.
+    net: Money,
+}
+
+#[derive(Serialize)]
+struct CashFlowReport {
+    /// Currency of every amount in the report.
+    currency: String,
+    period: BudgetPeriod,
+    /// Oldest first.
+    periods: Vec<CashFlow>,
+    income: Money,
+    expenses: Money,
+    net: Money,
+}
+
+/// The calendar periods covering `from..=to`, oldest first. Without `from`
+/// the report covers the twelve periods up to and including `to`.
+fn periods(
+    kind: BudgetPeriod,
+    from: Option<NaiveDate>,
+    to: NaiveDate,
+) -> Result<Vec<Period>, AppError> {
+    let out_of_range = || AppError::field("to", "is out of range");
+    let last = period::calendar(kind, to)
+        .ok_or_else(|| AppError::field("period", "must be weekly, monthly, quarterly or yearly"))?;
+
+    let mut first = last;
+    match from {
+        Some(from) => first = period::calendar(kind, from).ok_or_else(out_of_range)?,
+        None => {
+            for _ in 1..DEFAULT_PERIODS {
+                let Some(previous) = first
+                    .start
+                    .pred_opt()
+                    .and_then(|day| period::calendar(kind, day))
+                else {
+                    break;
+                };
+                first = previous;
+            }
+        }
+    }
+
+    let mut periods = vec![first];
+    while periods.last().is_some_and(|p| p.end < last.end) {
+        if periods.len() == MAX_PERIODS {
+            return Err(AppError::field(
+                "from",
+                "must be at most 120 periods before to",
+            ));
+        }
+        let next = periods[periods.len() - 1]
+            .end
+            .succ_opt()
+            .and_then(|day| period::calendar(kind, day))
+            .ok_or_else(out_of_range)?;
+        periods.push(next);
+    }
+    Ok(periods)
+}
+
+/// Income minus expenses per calendar period, across all of the user's
+/// budgets or just one. Amounts in other currencies are converted at the
+/// rate in effect on their date.
+pub(super) async fn get_cash_flow(
+    user_id: web::ReqData<Uuid>,
+    query: web::Query<CashFlowQuery>,
+    state: web::Data<AppState>,
+) -> Result<HttpResponse, AppError> {
+    let user_id = user_id.into_inner();
+    let kind = query.period.unwrap_or(BudgetPeriod::Monthly);
+    let report_currency = query
+        .currency
+        .clone()
+        .unwrap_or_else(|| currency::DEFAULT_CURRENCY.to_string());
+    if !currency::is_currency(&report_currency) {
+        return Err(AppError::field(
+            "currency",
+            "must be an ISO 4217 currency code such as \"EUR\"",
+        ));
+    }
+    ownership::check_budget_field(&state.db, user_id, "budget_id", query.budget_id).await?;
+    let to = query.to.unwrap_or_else(|| state.clock.now().date_naive());
+    pagination::check_range(query.from, Some(to), "to", "must not be before from")?;
+
+    let periods = periods(kind, query.from, to)?;
+    let start = periods[0].start;
+    let end = periods[periods.len() - 1].end;
+
+    // Only a budget's share of split expenses counts towards it.
+    let spending: Vec<(NaiveDate, String, Money)> = match query.budget_id {
+        Some(budget_id) => spending::budget_spending(&state.db, budget_id, Some(start), Some(end))
+            .await?
+            .into_iter()
+            .map(|(_, currency, date, amount)| (date, currency, amount))
+            .collect(),
+        None => {
+            expense::Entity::find()
+                .select_only()
+                .column(expense::Column::Date)
+                .column(expense::Column::Currency)
+                .column_as(expense::Column::Amount.sum(), "spent")
+                .join(JoinType::InnerJoin, expense::Relation::Budget.def())
+                .filter(budget::Column::UserId.eq(user_id))
+                .filter(expense::Column::Date.gte(start))
+                .filter(expense::Column::Date.lte(end))
+                .group_by(expense::Column::Date)
+                .group_by(expense::Column::Currency)
+                .into_tuple()
+                .all(&state.db)
+                .await?
+        }
+    };
+
+    let earnings: Vec<(NaiveDate, String, Money)> = income::Entity::find()
+        .select_only()
+        .column(income::Column::Date)
+        .column(income::Column::Currency)
+        .column_as(income::Column::Amount.sum(), "earned")
+        .filter(income::Column::UserId.eq(user_id))
+        .apply_if(query.budget_id, |q, budget_id| {
+            q.filter(income::Column::BudgetId.eq(budget_id))
+        })
+        .filter(income::Column::Date.gte(start))
+        .filter(income::Column::Date.lte(end))
+        .group_by(income::Column::Date)
+        .group_by(income::Column::Currency)
+        .order_by_asc(income::Column::Date)
+        .into_tuple()
+        .all(&state.db)
+        .await?;
+
+    let rates = Rates::load(
+        &state.db,
+        &report_currency,
+        spending
+            .iter()
+            .chain(&earnings)
+            .map(|(_, currency, _)| currency),
+    )
+    .await?;
+
+    let mut flows: Vec<CashFlow> = periods
+        .into_iter()
+        .map(|period| CashFlow {
+            period,
+            income: Money::ZERO,
+            expenses: Money::ZERO,
+            net: Money::ZERO,
+        })
+        .collect();
+    let index =
+        |flows: &[CashFlow], date: NaiveDate| flows.partition_point(|f| f.period.end < date);
+    for (date, currency, amount) in spending {
+        let i = index(&flows, date);
+        flows[i].expenses += rates.convert(amount, &currency, date)?;
+    }
+    for (date, currency, amount) in earnings {
+        let i = index(&flows, date);
+        flows[i].income += rates.convert(amount, &currency, date)?;
+    }
+
+    let mut report = CashFlowReport {
+        currency: report_currency,
+        period: kind,
+        periods: Vec::with_capacity(flows.len()),
+        income: Money::ZERO,
+        expenses: Money::ZERO,
+        net: Money::ZERO,
+    };
+    for mut flow in flows {
+        flow.net = flow.income - flow.expenses;
+        report.income += flow.income;
+        report.expenses += flow.expenses;
+        report.periods.push(flow);
+    }
+    report.net = report.income - report.expenses;
+
+    Ok(HttpResponse::Ok().json(report))
+}
@@ -0,0 +1,89 @@
+use actix_web::{web, HttpResponse};
+use entities::refresh_token;
+use sea_orm::{entity::*, sea_query::Expr, QueryFilter};
+use uuid::Uuid;
+
+use crate::{
+    middleware::auth::AccessTokenId,
+    utility::{
+        db_structs::RefreshRequest,
+        error::AppError,
+        session::{self, issue_tokens},
+        state::AppState,
+        token::hash_token,
+        validation::ValidatedJson,
+    },
+};
+
+/// Exchanges a refresh token for a new access token and refresh token. Each
+/// refresh token works once; presenting it again means it leaked, so every
+/// token descended from the same login is revoked.
+pub(super) async fn refresh(
+    state: web::Data<AppState>,
+    form: ValidatedJson<RefreshRequest>,
+) -> Result<HttpResponse, AppError> {
+    let now = state.clock.now();
+    let invalid = || AppError::Unauthorized("invalid or expired refresh token".to_string());
+
+    let token = refresh_token::Entity::find()
+        .filter(refresh_token::Column::TokenHash.eq(hash_token(&form.refresh_token)))
+        .one(&state.db)
+        .await?
+        .ok_or_else(invalid)?;
+    if token.revoked_at.is_some() || token.expires_at <= now {
+        return Err(invalid());
+    }
+
+    // Only one of two concurrent requests with the same token can mark it
+    // used; the other is treated as reuse.
+    let marked = refresh_token::Entity::update_many()
+        .col_expr(refresh_token::Column::UsedAt, Expr::value(now))
+        .filter(refresh_token::Column::Id.eq(token.id))
+        .filter(refresh_token::Column::UsedAt.is_null())
+        .exec(&state.db)
+        .await?;
+    if marked.rows_affected == 0 {
+        session::revoke_family(&state.db, token.family_id, now).await?;
+        return Err(AppError::Unauthorized(
+            "refresh token was already used; log in again".to_string(),
+        ));
+    }
+
+    let tokens = issue_tokens(
+        &state.db,
+        &state.settings.jwt,
+        now,
+        token.user_id,
+        token.family_id,
+    )
+    .await?;
+    Ok(HttpResponse::Ok().json(tokens))
+}
+
+/// Revokes the access token of the request and every token from the same
+/// login.
+pub(super) async fn logout(
+    user_id: web::ReqData<Uuid>,
+    token_id: AccessTokenId,
+    state: web::Data<AppState>,
+) -> Result<HttpResponse, AppError> {
+    let token = refresh_token::Entity::find_by_id(token_id.0)
+        .filter(refresh_token::Column::UserId.eq(user_id.into_inner()))
+        .one(&state.db)
+        .await?
+        .ok_or(AppError::NotFound("session"))?;
+    session::revoke_family(&state.db, token.family_id, state.clock.now()).await?;
+
+    Ok(HttpResponse::Ok().finish())
+}
+
+/// Revokes every token of the user, on every device.
+pub(super) async fn logout_everywhere(
+    _session: AccessTokenId,
+    user_id: web::ReqData<Uuid>,
+    state: web::Data<AppState>,
+) -> Result<HttpResponse, AppError> {
+    session::revoke_user(&state.db, user_id.into_inner(), state.clock.now()).await?;
+
+    Ok(HttpResponse::Ok().finish())
+}
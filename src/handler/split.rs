@@ -0,0 +1,92 @@
+use std::collections::HashMap;
+
+use entities::{expense_split, money::Money};
+use sea_orm::{entity::*, ConnectionTrait, DbErr, QueryFilter, QueryOrder};
+use uuid::Uuid;
+
+use super::category;
+use crate::utility::{db_structs::SplitLine, error::AppError, ownership};
+
+/// Split lines of each expense in `expense_ids`, in the order they were given.
+pub(crate) async fn split_lines<C: ConnectionTrait>(
+    db: &C,
+    expense_ids: &[Uuid],
+) -> Result<HashMap<Uuid, Vec<expense_split::Model>>, DbErr> {
+    let rows = expense_split::Entity::find()
+        .filter(expense_split::Column::ExpenseId.is_in(expense_ids.iter().copied()))
+        .order_by_asc(expense_split::Column::Position)
+        .all(db)
+        .await?;
+
+    let mut lines: HashMap<Uuid, Vec<expense_split::Model>> = HashMap::new();
+    for row in rows {
+        lines.entry(row.expense_id).or_default().push(row);
+    }
+    Ok(lines)
+}
+
+/// Checks that `splits` add up to `amount` and only name the user's own
+/// budgets and categories. No splits at all is fine.
+pub(crate) async fn check_splits<C: ConnectionTrait>(
+    db: &C,
+    user_id: Uuid,
+    amount: Money,
+    splits: &[SplitLine],
+) -> Result<(), AppError> {
+    if splits.is_empty() {
+        return Ok(());
+    }
+    if splits.iter().map(|split| split.amount).sum::<Money>() != amount {
+        return Err(AppError::field(
+            "splits",
+            "must add up to the expense's amount",
+        ));
+    }
+    for (i, split) in splits.iter().enumerate() {
+        ownership::check_budget_field(
+            db,
+            user_id,
+            &format!("splits[{}].budget_id", i),
+            split.budget_id,
+        )
+        .await?;
+        category::check_category_field(
+            db,
+            user_id,
+            &format!("splits[{}].category_id", i),
+            split.category_id,
+        )
+        .await?;
+    }
+    Ok(())
+}
+
+/// Replaces the split lines of `expense_id` with `splits`. Lines without a
+/// budget go to `budget_id`, the expense's own.
+pub(crate) async fn assign_splits<C: ConnectionTrait>(
+    db: &C,
+    expense_id: Uuid,
+    budget_id: Uuid,
+    splits: &[SplitLine],
+) -> Result<Vec<expense_split::Model>, DbErr> {
+    expense_split::Entity::delete_many()
+        .filter(expense_split::Column::ExpenseId.eq(expense_id))
+        .exec(db)
+        .await?;
+
+    let mut lines = Vec::with_capacity(splits.len());
+    for (position, split) in (0..).zip(splits) {
+        let line = expense_split::ActiveModel {
+            id: Set(Uuid::new_v4()),
+            expense_id: Set(expense_id),
+            budget_id: Set(split.budget_id.unwrap_or(budget_id)),
+            category_id: Set(split.category_id),
+            amount: Set(split.amount),
+            position: Set(position),
+        }
+        .insert(db)
+        .await?;
+        lines.push(line);
+    }
+    Ok(lines)
+}
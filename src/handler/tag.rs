@@ -0,0 +1,240 @@
+use std::collections::{BTreeSet, HashMap};
+
+use actix_web::{web, HttpResponse};
+use chrono::{DateTime, Utc};
+use entities::{expense, expense_tag, tag};
+use sea_orm::{
+    entity::*,
+    sea_query::{Expr, Query, SelectStatement},
+    ConnectionTrait, DbErr, JoinType, QueryFilter, QueryOrder, QuerySelect, RelationTrait,
+    TransactionTrait,
+};
+use uuid::Uuid;
+
+use crate::utility::{
+    cache::{self, CacheKey},
+    db_structs::TagName,
+    error::AppError,
+    state::AppState,
+    validation::ValidatedJson,
+};
+
+/// Tag names of each expense in `expense_ids`, sorted by name.
+pub(crate) async fn tag_names<C: ConnectionTrait>(
+    db: &C,
+    expense_ids: &[Uuid],
+) -> Result<HashMap<Uuid, Vec<String>>, DbErr> {
+    let rows: Vec<(Uuid, String)> = expense_tag::Entity::find()
+        .select_only()
+        .column(expense_tag::Column::ExpenseId)
+        .column(tag::Column::Name)
+        .join(JoinType::InnerJoin, expense_tag::Relation::Tag.def())
+        .filter(expense_tag::Column::ExpenseId.is_in(expense_ids.iter().copied()))
+        .order_by_asc(tag::Column::Name)
+        .into_tuple()
+        .all(db)
+        .await?;
+
+    let mut names: HashMap<Uuid, Vec<String>> = HashMap::new();
+    for (expense_id, name) in rows {
+        names.entry(expense_id).or_default().push(name);
+    }
+    Ok(names)
+}
+
+/// Replaces the tags of `expense_id` with `names`, creating any of the
+/// user's tags that do not exist yet. Returns the sorted, de-duplicated names.
+pub(crate) async fn assign_tags<C: ConnectionTrait>(
+    db: &C,
+    user_id: Uuid,
+    expense_id: Uuid,
+    names: &[String],
+    now: DateTime<Utc>,
+) -> Result<Vec<String>, DbErr> {
+    let names: BTreeSet<&String> = names.iter().collect();
+
+    let mut tags: Vec<tag::Model> = tag::Entity::find()
+        .filter(tag::Column::UserId.eq(user_id))
+        .filter(tag::Column::Name.is_in(names.iter().map(|n| n.as_str())))
+        .all(db)
+        .await?;
+    for name in &names {
+        if !tags.iter().any(|t| &&t.name == name) {
+            let tag = tag::ActiveModel {
+                id: Set(Uuid::new_v4()),
+                user_id: Set(user_id),
+                name: Set(name.to_string()),
+                created_at: Set(now),
+            }
+            .insert(db)
+            .await?;
+            tags.push(tag);
+        }
+    }
+
+    expense_tag::Entity::delete_many()
+        .filter(expense_tag::Column::ExpenseId.eq(expense_id))
+        .exec(db)
+        .await?;
+    if !tags.is_empty() {
+        expense_tag::Entity::insert_many(tags.iter().map(|t| expense_tag::ActiveModel {
+            expense_id: Set(expense_id),
+            tag_id: Set(t.id),
+        }))
+        .exec(db)
+        .await?;
+    }
+
+    Ok(names.into_iter().cloned().collect())
+}
+
+/// Subquery selecting the ids of expenses tagged `name` by `user_id`.
+pub(crate) fn tagged(user_id: Uuid, name: &str) -> SelectStatement {
+    Query::select()
+        .column((expense_tag::Entity, expense_tag::Column::ExpenseId))
+        .from(expense_tag::Entity)
+        .inner_join(
+            tag::Entity,
+            Expr::col((tag::Entity, tag::Column::Id))
+                .equals((expense_tag::Entity, expense_tag::Column::TagId)),
+        )
+        .and_where(tag::Column::UserId.eq(user_id))
+        .and_where(tag::Column::Name.eq(name))
+        .to_owned()
+}
+
+async fn find_owned(state: &AppState, user_id: Uuid, tag_id: Uuid) -> Result<tag::Model, AppError> {
+    tag::Entity::find_by_id(tag_id)
+        .filter(tag::Column::UserId.eq(user_id))
+        .one(&state.db)
+        .await?
+        .ok_or(AppError::NotFound("tag"))
+}
+
+async fn check_unique_name(state: &AppState, user_id: Uuid, name: &str) -> Result<(), AppError> {
+    let taken = tag::Entity::find()
+        .filter(tag::Column::UserId.eq(user_id))
+        .filter(tag::Column::Name.eq(name))
+        .one(&state.db)
+        .await?;
+    if taken.is_some() {
+        return Err(AppError::Conflict(format!(
+            "tag \"{}\" already exists",
+            name
+        )));
+    }
+    Ok(())
+}
+
+/// Cached expenses embed their tag names, so they go stale when a tag is
+/// renamed or deleted.
+async fn invalidate_tagged_expenses(
+    state: &AppState,
+    user_id: Uuid,
+    tag_id: Uuid,
+) -> Result<(), AppError> {
+    let tagged: Vec<(Uuid, Uuid)> = expense::Entity::find()
+        .select_only()
+        .column(expense::Column::BudgetId)
+        .column(expense::Column::Id)
+        .join(JoinType::InnerJoin, expense::Relation::ExpenseTag.def())
+        .filter(expense_tag::Column::TagId.eq(tag_id))
+        .into_tuple()
+        .all(&state.db)
+        .await?;
+    for (budget_id, expense_id) in tagged {
+        cache::invalidate(
+            state.cache.as_ref(),
+            &CacheKey::expense(&state.settings.cache, user_id, budget_id, expense_id),
+        )
+        .await;
+    }
+    Ok(())
+}
+
+pub(super) async fn get_tags(
+    user_id: web::ReqData<Uuid>,
+    state: web::Data<AppState>,
+) -> Result<HttpResponse, AppError> {
+    let tags = tag::Entity::find()
+        .filter(tag::Column::UserId.eq(user_id.into_inner()))
+        .order_by_asc(tag::Column::Name)
+        .all(&state.db)
+        .await?;
+
+    Ok(HttpResponse::Ok().json(tags))
+}
+
+pub(super) async fn get_tag(
+    user_id: web::ReqData<Uuid>,
+    tag_id: web::Path<Uuid>,
+    state: web::Data<AppState>,
+) -> Result<HttpResponse, AppError> {
+    let tag = find_owned(&state, *user_id, *tag_id).await?;
+
+    Ok(HttpResponse::Ok().json(tag))
+}
+
+pub(super) async fn post_tag(
+    user_id: web::ReqData<Uuid>,
+    state: web::Data<AppState>,
+    form: ValidatedJson<TagName>,
+) -> Result<HttpResponse, AppError> {
+    check_unique_name(&state, *user_id, &form.name).await?;
+
+    let tag = tag::ActiveModel {
+        id: Set(Uuid::new_v4()),
+        user_id: Set(user_id.into_inner()),
+        name: Set(form.name.clone()),
+        created_at: Set(state.clock.now()),
+    }
+    .insert(&state.db)
+    .await?;
+
+    Ok(HttpResponse::Ok().json(tag))
+}
+
+pub(super) async fn update_tag(
+    user_id: web::ReqData<Uuid>,
+    tag_id: web::Path<Uuid>,
+    state: web::Data<AppState>,
+    form: ValidatedJson<TagName>,
+) -> Result<HttpResponse, AppError> {
+    let tag = find_owned(&state, *user_id, *tag_id).await?;
+    if tag.name == form.name {
+        return Ok(HttpResponse::Ok().json(tag));
+    }
+    check_unique_name(&state, *user_id, &form.name).await?;
+    invalidate_tagged_expenses(&state, *user_id, tag.id).await?;
+
+    let mut tag: tag::ActiveModel = tag.into();
+    tag.name = Set(form.name.clone());
+    let tag = tag.update(&state.db).await?;
+
+    Ok(HttpResponse::Ok().json(tag))
+}
+
+/// Deletes a tag and removes it from every expense it was attached to.
+pub(super) async fn delete_tag(
+    user_id: web::ReqData<Uuid>,
+    tag_id: web::Path<Uuid>,
+    state: web::Data<AppState>,
+) -> Result<HttpResponse, AppError> {
+    let tag = find_owned(&state, *user_id, *tag_id).await?;
+    invalidate_tagged_expenses(&state, *user_id, tag.id).await?;
+
+    state
+        .db
+        .transaction::<_, _, DbErr>(|txn| {
+            Box::pin(async move {
+                expense_tag::Entity::delete_many()
+                    .filter(expense_tag::Column::TagId.eq(tag.id))
+                    .exec(txn)
+                    .await?;
+                tag::Entity::delete_by_id(tag.id).exec(txn).await
+            })
+        })
+        .await?;
+
+    Ok(HttpResponse::Ok().finish())
+}
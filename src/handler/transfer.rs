@@ -0,0 +1,189 @@
+use actix_web::{web, HttpRequest, HttpResponse};
+use entities::{account, money::Money, transfer};
+use sea_orm::{entity::*, Condition, QueryFilter, QueryOrder, QueryTrait};
+use uuid::Uuid;
+
+use super::account::account_field;
+use crate::utility::{
+    db_structs::{NewTransfer, TransferFilter, UpdateTransfer},
+    error::AppError,
+    pagination::{self, PageRequest, Paginated},
+    state::AppState,
+    validation::ValidatedJson,
+};
+
+async fn find_owned(
+    state: &AppState,
+    user_id: Uuid,
+    transfer_id: Uuid,
+) -> Result<transfer::Model, AppError> {
+    transfer::Entity::find_by_id(transfer_id)
+        .filter(transfer::Column::UserId.eq(user_id))
+        .one(&state.db)
+        .await?
+        .ok_or(AppError::NotFound("transfer"))
+}
+
+/// The amount paid into `to`. Between accounts in the same currency it is
+/// `amount` itself; otherwise the client has to say what arrived.
+fn received_amount(
+    from: &account::Model,
+    to: &account::Model,
+    amount: Money,
+    to_amount: Option<Money>,
+) -> Result<Money, AppError> {
+    match to_amount {
+        None if from.currency == to.currency => Ok(amount),
+        None => Err(AppError::field(
+            "to_amount",
+            "is required between accounts in different currencies",
+        )),
+        Some(to_amount) if from.currency == to.currency && to_amount != amount => {
+            Err(AppError::field(
+                "to_amount",
+                "must equal amount between accounts in the same currency",
+            ))
+        }
+        Some(to_amount) => Ok(to_amount),
+    }
+}
+
+pub(super) async fn get_transfers(
+    req: HttpRequest,
+    user_id: web::ReqData<Uuid>,
+    filter: web::Query<TransferFilter>,
+    state: web::Data<AppState>,
+) -> Result<HttpResponse, AppError> {
+    let page = PageRequest::new(filter.page, filter.limit)?;
+    pagination::check_range(filter.from, filter.to, "to", "must not be before from")?;
+
+    let query = transfer::Entity::find()
+        .filter(transfer::Column::UserId.eq(user_id.into_inner()))
+        .apply_if(filter.account_id, |q, account_id| {
+            q.filter(
+                Condition::any()
+                    .add(transfer::Column::FromAccountId.eq(account_id))
+                    .add(transfer::Column::ToAccountId.eq(account_id)),
+            )
+        })
+        .apply_if(filter.from, |q, from| {
+            q.filter(transfer::Column::Date.gte(from))
+        })
+        .apply_if(filter.to, |q, to| q.filter(transfer::Column::Date.lte(to)))
+        .order_by_desc(transfer::Column::Date)
+        .order_by_desc(transfer::Column::Id);
+    let (transfers, meta) = pagination::fetch(&state.db, query, page).await?;
+
+    Ok(Paginated {
+        data: transfers,
+        pagination: meta,
+    }
+    .respond(&req))
+}
+
+pub(super) async fn get_transfer(
+    user_id: web::ReqData<Uuid>,
+    transfer_id: web::Path<Uuid>,
+    state: web::Data<AppState>,
+) -> Result<HttpResponse, AppError> {
+    let transfer = find_owned(&state, *user_id, *transfer_id).await?;
+
+    Ok(HttpResponse::Ok().json(transfer))
+}
+
+pub(super) async fn post_transfer(
+    user_id: web::ReqData<Uuid>,
+    state: web::Data<AppState>,
+    form: ValidatedJson<NewTransfer>,
+) -> Result<HttpResponse, AppError> {
+    let user_id = user_id.into_inner();
+    let form = form.into_inner();
+    if form.from_account_id == form.to_account_id {
+        return Err(AppError::field(
+            "to_account_id",
+            "must differ from from_account_id",
+        ));
+    }
+    let from = account_field(&state.db, user_id, "from_account_id", form.from_account_id).await?;
+    let to = account_field(&state.db, user_id, "to_account_id", form.to_account_id).await?;
+    let to_amount = received_amount(&from, &to, form.amount, form.to_amount)?;
+
+    let now = state.clock.now();
+    let transfer = transfer::ActiveModel {
+        id: Set(Uuid::new_v4()),
+        user_id: Set(user_id),
+        from_account_id: Set(from.id),
+        to_account_id: Set(to.id),
+        amount: Set(form.amount),
+        to_amount: Set(to_amount),
+        description: Set(form.description),
+        date: Set(form.date.unwrap_or_else(|| now.date_naive())),
+        created_at: Set(now),
+        updated_at: Set(now),
+    }
+    .insert(&state.db)
+    .await?;
+
+    Ok(HttpResponse::Ok().json(transfer))
+}
+
+pub(super) async fn update_transfer(
+    user_id: web::ReqData<Uuid>,
+    transfer_id: web::Path<Uuid>,
+    state: web::Data<AppState>,
+    form: ValidatedJson<UpdateTransfer>,
+) -> Result<HttpResponse, AppError> {
+    let transfer = find_owned(&state, *user_id, *transfer_id).await?;
+    let form = form.into_inner();
+
+    let from = account_field(
+        &state.db,
+        *user_id,
+        "from_account_id",
+        transfer.from_account_id,
+    )
+    .await?;
+    let to = account_field(&state.db, *user_id, "to_account_id", transfer.to_account_id).await?;
+    let amount = form.amount.unwrap_or(transfer.amount);
+    // A new amount between different currencies keeps the old received
+    // amount unless that is changed too.
+    let to_amount = match form.to_amount {
+        None if from.currency != to.currency => Some(transfer.to_amount),
+        to_amount => to_amount,
+    };
+    let to_amount = received_amount(&from, &to, amount, to_amount)?;
+
+    let mut transfer: transfer::ActiveModel = transfer.into();
+    transfer.amount = Set(amount);
+    transfer.to_amount = Set(to_amount);
+
+    if let Some(description) = form.description {
+        transfer.description = Set(description);
+    }
+
+    if let Some(date) = form.date {
+        transfer.date = Set(date);
+    }
+
+    transfer.updated_at = Set(state.clock.now());
+    let transfer = transfer.update(&state.db).await?;
+
+    Ok(HttpResponse::Ok().json(transfer))
+}
+
+pub(super) async fn delete_transfer(
+    user_id: web::ReqData<Uuid>,
+    transfer_id: web::Path<Uuid>,
+    state: web::Data<AppState>,
+) -> Result<HttpResponse, AppError> {
+    let res = transfer::Entity::delete_many()
+        .filter(transfer::Column::UserId.eq(user_id.into_inner()))
+        .filter(transfer::Column::Id.eq(transfer_id.into_inner()))
+        .exec(&state.db)
+        .await?;
+
+    if res.rows_affected == 0 {
+        return Err(AppError::NotFound("transfer"));
+    }
+    Ok(HttpResponse::Ok().finish())
+}
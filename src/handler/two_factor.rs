@@ -0,0 +1,349 @@
+use actix_web::{web, HttpResponse};
+use chrono::{DateTime, Duration, Utc};
+use entities::{login_challenge, recovery_code, totp, users};
+use sea_orm::{
+    entity::*, sea_query::Expr, ConnectionTrait, DbErr, PaginatorTrait, QueryFilter,
+    TransactionTrait,
+};
+use serde::Serialize;
+use uuid::Uuid;
+
+use crate::{
+    middleware::auth::AccessTokenId,
+    utility::{
+        db_structs::{TwoFactorCode, TwoFactorLogin},
+        error::AppError,
+        session::issue_tokens,
+        state::AppState,
+        token::{hash_token, random_token},
+        totp as otp,
+        validation::ValidatedJson,
+    },
+};
+
+const RECOVERY_CODES: usize = 10;
+/// How long the password step of a login stays good for.
+const CHALLENGE_TTL_SECS: i64 = 300;
+/// Wrong codes after which a challenge is dropped and the password has to
+/// be sent again.
+const MAX_ATTEMPTS: i32 = 5;
+
+#[derive(Serialize)]
+struct TwoFactorStatus {
+    enabled: bool,
+    recovery_codes_remaining: u64,
+}
+
+/// A secret for the user to add to their authenticator app, as text and as
+/// a URI for a QR code.
+#[derive(Serialize)]
+struct TotpEnrollment {
+    secret: String,
+    otpauth_uri: String,
+}
+
+#[derive(Serialize)]
+struct RecoveryCodes {
+    recovery_codes: Vec<String>,
+}
+
+/// What login answers instead of tokens when a code is needed.
+#[derive(Serialize)]
+pub(crate) struct LoginChallenge {
+    two_factor_required: bool,
+    challenge_token: String,
+    challenge_expires_at: DateTime<Utc>,
+}
+
+/// The confirmed TOTP secret of `user_id`, if two-factor authentication is
+/// on.
+pub(crate) async fn confirmed_totp<C: ConnectionTrait>(
+    db: &C,
+    user_id: Uuid,
+) -> Result<Option<totp::Model>, DbErr> {
+    Ok(totp::Entity::find_by_id(user_id)
+        .one(db)
+        .await?
+        .filter(|totp| totp.confirmed_at.is_some()))
+}
+
+/// Starts a two-step login for `user_id`, whose password was right.
+pub(crate) async fn challenge(state: &AppState, user_id: Uuid) -> Result<LoginChallenge, AppError> {
+    let now = state.clock.now();
+    login_challenge::Entity::delete_many()
+        .filter(login_challenge::Column::UserId.eq(user_id))
+        .filter(login_challenge::Column::ExpiresAt.lte(now))
+        .exec(&state.db)
+        .await?;
+
+    let challenge_token = random_token();
+    let challenge_expires_at = now + Duration::seconds(CHALLENGE_TTL_SECS);
+    login_challenge::ActiveModel {
+        id: Set(Uuid::new_v4()),
+        user_id: Set(user_id),
+        token_hash: Set(hash_token(&challenge_token)),
+        attempts: Set(0),
+        expires_at: Set(challenge_expires_at),
+        created_at: Set(now),
+    }
+    .insert(&state.db)
+    .await?;
+
+    Ok(LoginChallenge {
+        two_factor_required: true,
+        challenge_token,
+        challenge_expires_at,
+    })
+}
+
+/// Whether `code` is a TOTP code that has not been used yet or an unused
+/// recovery code of the owner of `totp`. Either is used up by this.
+async fn check_code<C: ConnectionTrait>(
+    db: &C,
+    totp: &totp::Model,
+    code: &str,
+    now: DateTime<Utc>,
+) -> Result<bool, DbErr> {
+    let code = code.trim();
+    if code.bytes().all(|b| b.is_ascii_digit()) {
+        let Some(step) = otp::verify(&totp.secret, code, now, totp.last_used_step) else {
+            return Ok(false);
+        };
+        // Only moves on from the step that was read, so that of two requests
+        // racing with the same code just one gets it.
+        let used = totp::Entity::update_many()
+            .col_expr(totp::Column::LastUsedStep, Expr::value(step))
+            .filter(totp::Column::UserId.eq(totp.user_id))
+            .filter(match totp.last_used_step {
+                Some(last) => totp::Column::LastUsedStep.eq(last),
+                None => totp::Column::LastUsedStep.is_null(),
+            })
+            .exec(db)
+            .await?;
+        return Ok(used.rows_affected > 0);
+    }
+
+    let used = recovery_code::Entity::update_many()
+        .col_expr(recovery_code::Column::UsedAt, Expr::value(now))
+        .filter(recovery_code::Column::UserId.eq(totp.user_id))
+        .filter(recovery_code::Column::CodeHash.eq(hash_token(&otp::normalize_recovery_code(code))))
+        .filter(recovery_code::Column::UsedAt.is_null())
+        .exec(db)
+        .await?;
+    Ok(used.rows_affected > 0)
+}
+
+pub(super) async fn get_two_factor(
+    user_id: web::ReqData<Uuid>,
+    state: web::Data<AppState>,
+) -> Result<HttpResponse, AppError> {
+    let enabled = confirmed_totp(&state.db, *user_id).await?.is_some();
+    let recovery_codes_remaining = recovery_code::Entity::find()
+        .filter(recovery_code::Column::UserId.eq(*user_id))
+        .filter(recovery_code::Column::UsedAt.is_null())
+        .count(&state.db)
+        .await?;
+
+    Ok(HttpResponse::Ok().json(TwoFactorStatus {
+        enabled,
+        recovery_codes_remaining,
+    }))
+}
+
+/// Generates a new secret for the user to add to their authenticator app.
+/// Nothing changes at login until it is confirmed.
+pub(super) async fn post_totp(
+    _session: AccessTokenId,
+    user_id: web::ReqData<Uuid>,
+    state: web::Data<AppState>,
+) -> Result<HttpResponse, AppError> {
+    if confirmed_totp(&state.db, *user_id).await?.is_some() {
+        return Err(AppError::Conflict(
+            "two-factor authentication is already enabled".to_string(),
+        ));
+    }
+    let user = users::Entity::find_by_id(*user_id)
+        .one(&state.db)
+        .await?
+        .ok_or(AppError::NotFound("user"))?;
+
+    let secret = otp::new_secret();
+    totp::Entity::delete_by_id(user.id).exec(&state.db).await?;
+    totp::ActiveModel {
+        user_id: Set(user.id),
+        secret: Set(secret.clone()),
+        confirmed_at: Set(None),
+        last_used_step: Set(None),
+        created_at: Set(state.clock.now()),
+    }
+    .insert(&state.db)
+    .await?;
+
+    Ok(HttpResponse::Ok().json(TotpEnrollment {
+        otpauth_uri: otp::otpauth_uri(&secret, &user.username),
+        secret,
+    }))
+}
+
+/// Turns two-factor authentication on once the user sends a code from their
+/// app, and hands out recovery codes. They are shown only this once.
+pub(super) async fn confirm_totp(
+    _session: AccessTokenId,
+    user_id: web::ReqData<Uuid>,
+    state: web::Data<AppState>,
+    form: ValidatedJson<TwoFactorCode>,
+) -> Result<HttpResponse, AppError> {
+    let user_id = user_id.into_inner();
+    let totp = totp::Entity::find_by_id(user_id)
+        .one(&state.db)
+        .await?
+        .ok_or(AppError::NotFound("TOTP enrollment"))?;
+    if totp.confirmed_at.is_some() {
+        return Err(AppError::Conflict(
+            "two-factor authentication is already enabled".to_string(),
+        ));
+    }
+    let now = state.clock.now();
+    let step = otp::verify(&totp.secret, form.code.trim(), now, None)
+        .ok_or_else(|| AppError::field("code", "is not a valid code"))?;
+
+    let codes: Vec<String> = (0..RECOVERY_CODES)
+        .map(|_| otp::new_recovery_code())
+        .collect();
+    let hashes: Vec<String> = codes
+        .iter()
+        .map(|code| hash_token(&otp::normalize_recovery_code(code)))
+        .collect();
+    state
+        .db
+        .transaction::<_, _, DbErr>(|txn| {
+            Box::pin(async move {
+                let mut totp: totp::ActiveModel = totp.into();
+                totp.confirmed_at = Set(Some(now));
+                totp.last_used_step = Set(Some(step));
+                totp.update(txn).await?;
+
+                recovery_code::Entity::delete_many()
+                    .filter(recovery_code::Column::UserId.eq(user_id))
+                    .exec(txn)
+                    .await?;
+                for code_hash in hashes {
+                    recovery_code::ActiveModel {
+                        id: Set(Uuid::new_v4()),
+                        user_id: Set(user_id),
+                        code_hash: Set(code_hash),
+                        used_at: Set(None),
+                        created_at: Set(now),
+                    }
+                    .insert(txn)
+                    .await?;
+                }
+                Ok(())
+            })
+        })
+        .await?;
+
+    Ok(HttpResponse::Ok().json(RecoveryCodes {
+        recovery_codes: codes,
+    }))
+}
+
+/// Turns two-factor authentication off; needs a current code.
+pub(super) async fn delete_totp(
+    _session: AccessTokenId,
+    user_id: web::ReqData<Uuid>,
+    state: web::Data<AppState>,
+    form: ValidatedJson<TwoFactorCode>,
+) -> Result<HttpResponse, AppError> {
+    let user_id = user_id.into_inner();
+    let totp = confirmed_totp(&state.db, user_id)
+        .await?
+        .ok_or(AppError::NotFound("TOTP enrollment"))?;
+    if !check_code(&state.db, &totp, &form.code, state.clock.now()).await? {
+        return Err(AppError::field("code", "is not a valid code"));
+    }
+
+    state
+        .db
+        .transaction::<_, _, DbErr>(|txn| {
+            Box::pin(async move {
+                login_challenge::Entity::delete_many()
+                    .filter(login_challenge::Column::UserId.eq(user_id))
+                    .exec(txn)
+                    .await?;
+                recovery_code::Entity::delete_many()
+                    .filter(recovery_code::Column::UserId.eq(user_id))
+                    .exec(txn)
+                    .await?;
+                totp::Entity::delete_by_id(user_id).exec(txn).await?;
+                Ok(())
+            })
+        })
+        .await?;
+
+    Ok(HttpResponse::Ok().finish())
+}
+
+/// The second login step: exchanges a challenge token and a code for tokens.
+pub(super) async fn login_two_factor(
+    state: web::Data<AppState>,
+    form: ValidatedJson<TwoFactorLogin>,
+) -> Result<HttpResponse, AppError> {
+    let now = state.clock.now();
+    let invalid = || AppError::Unauthorized("invalid or expired challenge token".to_string());
+
+    let challenge = login_challenge::Entity::find()
+        .filter(login_challenge::Column::TokenHash.eq(hash_token(&form.challenge_token)))
+        .filter(login_challenge::Column::ExpiresAt.gt(now))
+        .filter(login_challenge::Column::Attempts.lt(MAX_ATTEMPTS))
+        .one(&state.db)
+        .await?
+        .ok_or_else(invalid)?;
+    let totp = confirmed_totp(&state.db, challenge.user_id)
+        .await?
+        .ok_or_else(invalid)?;
+
+    // The attempt is counted before the code is checked, so that requests
+    // sent in parallel cannot get more guesses between them.
+    let claimed = login_challenge::Entity::update_many()
+        .col_expr(
+            login_challenge::Column::Attempts,
+            Expr::col(login_challenge::Column::Attempts).add(1),
+        )
+        .filter(login_challenge::Column::Id.eq(challenge.id))
+        .filter(login_challenge::Column::Attempts.lt(MAX_ATTEMPTS))
+        .exec(&state.db)
+        .await?;
+    if claimed.rows_affected == 0 {
+        return Err(invalid());
+    }
+
+    if !check_code(&state.db, &totp, &form.code, now).await? {
+        login_challenge::Entity::delete_many()
+            .filter(login_challenge::Column::Id.eq(challenge.id))
+            .filter(login_challenge::Column::Attempts.gte(MAX_ATTEMPTS))
+            .exec(&state.db)
+            .await?;
+        return Err(AppError::Unauthorized(
+            "invalid two-factor code".to_string(),
+        ));
+    }
+
+    // A challenge logs in once, even if two requests race with it.
+    let consumed = login_challenge::Entity::delete_by_id(challenge.id)
+        .exec(&state.db)
+        .await?;
+    if consumed.rows_affected == 0 {
+        return Err(invalid());
+    }
+
+    let tokens = issue_tokens(
+        &state.db,
+        &state.settings.jwt,
+        now,
+        challenge.user_id,
+        Uuid::new_v4(),
+    )
+    .await?;
+    Ok(HttpResponse::Ok().json(tokens))
+}
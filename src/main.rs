@@ -1,26 +1,53 @@
-mod handler;
-mod middleware;
-mod utility;
-
-use actix_web::{App, HttpServer};
+use actix_web::{web, App, HttpServer};
 use dotenv::dotenv;
 use migration::{Migrator, MigratorTrait};
+use pbudget::{
+    handler,
+    utility::{config::Settings, currency, scheduler, state::AppState},
+};
 use sea_orm::{Database, DatabaseConnection};
 
 // Lambda Function
 #[actix_web::main]
 async fn main() -> std::io::Result<()> {
     dotenv().ok();
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("info")).init();
+
+    let settings = match Settings::load() {
+        Ok(settings) => settings,
+        Err(e) => {
+            eprintln!("pbudget: invalid configuration: {}", e);
+            std::process::exit(2);
+        }
+    };
 
-    let db: DatabaseConnection = Database::connect("sqlite://store.sqlite?mode=rwc")
+    let db: DatabaseConnection = Database::connect(&settings.database.url)
         .await
         .expect("Failed to connect Database");
     Migrator::up(&db, None)
         .await
         .expect("Failed to migrate database schema");
 
-    HttpServer::new(|| App::new().configure(handler::init))
-        .bind(("127.0.0.1", 8080))?
-        .run()
-        .await
+    if let Some(path) = &settings.exchange_rates.file {
+        match currency::import_file(&db, path).await {
+            Ok(count) => log::info!("loaded {} exchange rates from {}", count, path.display()),
+            Err(e) => {
+                eprintln!("pbudget: {}: {}", path.display(), e);
+                std::process::exit(2);
+            }
+        }
+    }
+
+    let bind = (settings.server.host.clone(), settings.server.port);
+    let workers = settings.server.workers;
+    let state = web::Data::new(AppState::new(db, settings).expect("Invalid Redis URL"));
+    scheduler::spawn(state.clone());
+
+    let mut server =
+        HttpServer::new(move || App::new().app_data(state.clone()).configure(handler::init));
+    if let Some(workers) = workers {
+        server = server.workers(workers);
+    }
+
+    server.bind(bind)?.run().await
 }
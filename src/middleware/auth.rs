@@ -3,13 +3,46 @@ use std::rc::Rc;
 use actix_web::Error;
 use actix_web::{
     body::EitherBody,
-    dev::{forward_ready, Service, ServiceRequest, ServiceResponse, Transform},
-    http::header::HeaderValue,
-    HttpMessage, HttpResponse,
+    dev::{forward_ready, Payload, Service, ServiceRequest, ServiceResponse, Transform},
+    http::{header::HeaderValue, Method},
+    web, FromRequest, HttpMessage, HttpRequest, ResponseError,
 };
-use futures::future::{ok, LocalBoxFuture, Ready};
+use entities::{api_key, sea_orm_active_enums::ApiKeyScope};
+use futures::future::{ok, ready, LocalBoxFuture, Ready};
+use sea_orm::{entity::*, sea_query::Expr, QueryFilter};
+use uuid::Uuid;
 
-use crate::utility::token::decode_jwt;
+use crate::utility::{
+    error::AppError,
+    session,
+    state::AppState,
+    token::{decode_jwt, hash_token},
+};
+
+/// Header carrying an API key, as an alternative to a bearer token.
+pub const API_KEY_HEADER: &str = "X-API-Key";
+
+/// The `jti` of the access token a request was made with. Only requests
+/// authenticated by logging in have one, so handlers that must not be
+/// reachable with an API key extract it.
+#[derive(Clone, Copy, Debug)]
+pub struct AccessTokenId(pub Uuid);
+
+impl FromRequest for AccessTokenId {
+    type Error = AppError;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        ready(
+            req.extensions()
+                .get::<AccessTokenId>()
+                .copied()
+                .ok_or_else(|| {
+                    AppError::Forbidden("API keys cannot be used here; log in instead".to_string())
+                }),
+        )
+    }
+}
 
 pub struct Auth;
 
@@ -36,6 +69,57 @@ pub struct AuthMiddleware<S> {
     service: Rc<S>,
 }
 
+/// Checks the API key or bearer token of `req` and returns the user it
+/// belongs to, with the access token's id when it was a bearer token.
+async fn authenticate(req: &ServiceRequest) -> Result<(Uuid, Option<AccessTokenId>), AppError> {
+    let state = req
+        .app_data::<web::Data<AppState>>()
+        .expect("AppState must be registered as app data");
+
+    if let Some(key) = req.headers().get(API_KEY_HEADER) {
+        let key = key.to_str().unwrap_or_default();
+        let user_id = authenticate_key(state, key, req.method()).await?;
+        return Ok((user_id, None));
+    }
+
+    let token = req
+        .headers()
+        .get(actix_web::http::header::AUTHORIZATION)
+        .and_then(|hv: &HeaderValue| hv.to_str().ok())
+        .map(|s: &str| s.trim_start_matches("Bearer "))
+        .ok_or_else(|| AppError::Unauthorized("missing bearer token".to_string()))?;
+
+    let claims = decode_jwt(token, state.clock.now(), &state.settings.jwt)
+        .map_err(|_| AppError::Unauthorized("invalid or expired token".to_string()))?
+        .claims;
+    if !session::is_active(&state.db, claims.sub, claims.jti).await? {
+        return Err(AppError::Unauthorized("token has been revoked".to_string()));
+    }
+    Ok((claims.sub, Some(AccessTokenId(claims.jti))))
+}
+
+/// Checks that `key` exists, has not expired and allows `method`, and
+/// records that it was used.
+async fn authenticate_key(state: &AppState, key: &str, method: &Method) -> Result<Uuid, AppError> {
+    let now = state.clock.now();
+    let key = api_key::Entity::find()
+        .filter(api_key::Column::KeyHash.eq(hash_token(key)))
+        .one(&state.db)
+        .await?
+        .filter(|key| key.expires_at.is_none_or(|expires_at| now < expires_at))
+        .ok_or_else(|| AppError::Unauthorized("invalid or expired API key".to_string()))?;
+    if key.scope == ApiKeyScope::Read && !matches!(*method, Method::GET | Method::HEAD) {
+        return Err(AppError::Forbidden("this API key is read-only".to_string()));
+    }
+
+    api_key::Entity::update_many()
+        .col_expr(api_key::Column::LastUsedAt, Expr::value(now))
+        .filter(api_key::Column::Id.eq(key.id))
+        .exec(&state.db)
+        .await?;
+    Ok(key.user_id)
+}
+
 impl<S, B> Service<ServiceRequest> for AuthMiddleware<S>
 where
     S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
@@ -49,35 +133,24 @@ where
     forward_ready!(service);
 
     fn call(&self, req: ServiceRequest) -> Self::Future {
-        let auth_header = req
-            .headers()
-            .get(actix_web::http::header::AUTHORIZATION)
-            .and_then(|hv: &HeaderValue| hv.to_str().ok())
-            .map(|s: &str| s.trim_start_matches("Bearer "))
-            .map(String::from);
-
-        if let Some(token) = auth_header {
-            match decode_jwt(token) {
-                Ok(token_data) => {
-                    req.extensions_mut().insert(token_data.claims.sub);
-                    let fut = self.service.call(req);
-                    Box::pin(async move {
-                        let res = fut.await?;
-                        Ok(res.map_into_left_body())
-                    })
+        let service = Rc::clone(&self.service);
+
+        Box::pin(async move {
+            match authenticate(&req).await {
+                Ok((user_id, token_id)) => {
+                    req.extensions_mut().insert(user_id);
+                    if let Some(token_id) = token_id {
+                        req.extensions_mut().insert(token_id);
+                    }
+                    let res = service.call(req).await?;
+                    Ok(res.map_into_left_body())
                 }
-                Err(_) => Box::pin(async {
+                Err(e) => {
                     let (req, _pl) = req.into_parts();
-                    let res = HttpResponse::Unauthorized().finish().map_into_right_body();
+                    let res = e.error_response().map_into_right_body();
                     Ok(ServiceResponse::new(req, res))
-                }),
+                }
             }
-        } else {
-            Box::pin(async {
-                let (req, _pl) = req.into_parts();
-                let res = HttpResponse::Unauthorized().finish().map_into_right_body();
-                Ok(ServiceResponse::new(req, res))
-            })
-        }
+        })
     }
 }
@@ -0,0 +1,152 @@
+use std::{
+    future::Future,
+    num::NonZeroUsize,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use async_trait::async_trait;
+use lru::LruCache;
+use serde::{de::DeserializeOwned, Serialize};
+use uuid::Uuid;
+
+use crate::utility::config::CacheSettings;
+
+/// A string key/value store with per-entry expiry. Failures are swallowed by
+/// the implementations: a cache that cannot answer behaves like a miss.
+#[async_trait]
+pub trait Cache: Send + Sync {
+    async fn get(&self, key: &str) -> Option<String>;
+
+    async fn set(&self, key: &str, value: String, ttl_secs: u64);
+
+    async fn delete(&self, key: &str);
+}
+
+/// In-process cache evicting the least recently used entry once `capacity`
+/// is reached.
+pub struct MemoryCache {
+    entries: Mutex<LruCache<String, (String, Instant)>>,
+}
+
+impl MemoryCache {
+    pub fn new(capacity: NonZeroUsize) -> Self {
+        MemoryCache {
+            entries: Mutex::new(LruCache::new(capacity)),
+        }
+    }
+}
+
+#[async_trait]
+impl Cache for MemoryCache {
+    async fn get(&self, key: &str) -> Option<String> {
+        let mut entries = self.entries.lock().unwrap();
+        match entries.get(key) {
+            Some((value, expires_at)) if Instant::now() < *expires_at => Some(value.clone()),
+            Some(_) => {
+                entries.pop(key);
+                None
+            }
+            None => None,
+        }
+    }
+
+    async fn set(&self, key: &str, value: String, ttl_secs: u64) {
+        let expires_at = Instant::now() + Duration::from_secs(ttl_secs);
+        self.entries
+            .lock()
+            .unwrap()
+            .put(key.to_string(), (value, expires_at));
+    }
+
+    async fn delete(&self, key: &str) {
+        self.entries.lock().unwrap().pop(key);
+    }
+}
+
+/// Never stores anything, so every lookup goes to the database.
+pub struct NoopCache;
+
+#[async_trait]
+impl Cache for NoopCache {
+    async fn get(&self, _key: &str) -> Option<String> {
+        None
+    }
+
+    async fn set(&self, _key: &str, _value: String, _ttl_secs: u64) {}
+
+    async fn delete(&self, _key: &str) {}
+}
+
+/// Where an entity is cached and for how long.
+pub struct CacheKey {
+    pub key: String,
+    pub ttl_secs: u64,
+}
+
+impl CacheKey {
+    pub fn profile(settings: &CacheSettings, user_id: Uuid) -> Self {
+        CacheKey {
+            key: format!("profile:{}", user_id),
+            ttl_secs: settings.profile_ttl_secs,
+        }
+    }
+
+    pub fn budget(settings: &CacheSettings, user_id: Uuid, budget_id: Uuid) -> Self {
+        CacheKey {
+            key: format!("budget:{}:{}", user_id, budget_id),
+            ttl_secs: settings.budget_ttl_secs,
+        }
+    }
+
+    pub fn expense(
+        settings: &CacheSettings,
+        user_id: Uuid,
+        budget_id: Uuid,
+        expense_id: Uuid,
+    ) -> Self {
+        CacheKey {
+            key: format!("expense:{}:{}:{}", user_id, budget_id, expense_id),
+            ttl_secs: settings.expense_ttl_secs,
+        }
+    }
+}
+
+/// Returns the cached value for `key`, or runs `load` and caches what it
+/// finds. Entries that no longer deserialize into `T` are treated as misses
+/// and overwritten.
+pub async fn get_or_load<T, E, F, Fut>(
+    cache: &dyn Cache,
+    key: &CacheKey,
+    load: F,
+) -> Result<Option<T>, E>
+where
+    T: Serialize + DeserializeOwned,
+    F: FnOnce() -> Fut,
+    Fut: Future<Output = Result<Option<T>, E>>,
+{
+    if let Some(value) = get(cache, key).await {
+        return Ok(Some(value));
+    }
+
+    let value = load().await?;
+    if let Some(value) = &value {
+        put(cache, key, value).await;
+    }
+    Ok(value)
+}
+
+pub async fn get<T: DeserializeOwned>(cache: &dyn Cache, key: &CacheKey) -> Option<T> {
+    let cached = cache.get(&key.key).await?;
+    serde_json::from_str(&cached).ok()
+}
+
+pub async fn put<T: Serialize>(cache: &dyn Cache, key: &CacheKey, value: &T) {
+    if let Ok(json) = serde_json::to_string(value) {
+        cache.set(&key.key, json, key.ttl_secs).await;
+    }
+}
+
+pub async fn invalidate(cache: &dyn Cache, key: &CacheKey) {
+    cache.delete(&key.key).await;
+}
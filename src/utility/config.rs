@@ -0,0 +1,544 @@
+use std::{fmt, fs, path::PathBuf};
+
+use clap::{Parser, ValueEnum};
+use serde::Deserialize;
+
+const DEFAULT_CONFIG_FILE: &str = "pbudget.toml";
+
+/// Command line flags. Every flag can also be provided through the listed
+/// environment variable; flags win over the environment, which wins over the
+/// config file.
+#[derive(Parser, Debug)]
+#[command(name = "pbudget", version, about = "Personal Budgeting API")]
+pub struct Cli {
+    /// Path to a TOML config file (defaults to ./pbudget.toml when present)
+    #[arg(long, env = "PBUDGET_CONFIG")]
+    pub config: Option<PathBuf>,
+
+    #[arg(long, env = "DATABASE_URL")]
+    pub database_url: Option<String>,
+
+    #[arg(long, env = "PBUDGET_HOST")]
+    pub host: Option<String>,
+
+    #[arg(long, env = "PBUDGET_PORT")]
+    pub port: Option<u16>,
+
+    #[arg(long, env = "PBUDGET_WORKERS")]
+    pub workers: Option<usize>,
+
+    #[arg(long, env = "REDIS_URL")]
+    pub redis_url: Option<String>,
+
+    #[arg(long, env = "PBUDGET_REDIS_CONNECT_TIMEOUT_MS")]
+    pub redis_connect_timeout_ms: Option<u64>,
+
+    #[arg(long, env = "PBUDGET_REDIS_RESPONSE_TIMEOUT_MS")]
+    pub redis_response_timeout_ms: Option<u64>,
+
+    #[arg(long, env = "PBUDGET_REDIS_RETRY_INTERVAL_SECS")]
+    pub redis_retry_interval_secs: Option<u64>,
+
+    #[arg(long, env = "JWT_SECRET", hide_env_values = true)]
+    pub jwt_secret: Option<String>,
+
+    #[arg(long, env = "PBUDGET_JWT_EXPIRY_SECS")]
+    pub jwt_expiry_secs: Option<i64>,
+
+    #[arg(long, env = "PBUDGET_JWT_REFRESH_EXPIRY_SECS")]
+    pub jwt_refresh_expiry_secs: Option<i64>,
+
+    #[arg(long, env = "PBUDGET_CACHE_BACKEND", value_enum)]
+    pub cache_backend: Option<CacheBackend>,
+
+    #[arg(long, env = "PBUDGET_CACHE_MEMORY_CAPACITY")]
+    pub cache_memory_capacity: Option<usize>,
+
+    #[arg(long, env = "PBUDGET_CACHE_PROFILE_TTL_SECS")]
+    pub cache_profile_ttl_secs: Option<u64>,
+
+    #[arg(long, env = "PBUDGET_CACHE_BUDGET_TTL_SECS")]
+    pub cache_budget_ttl_secs: Option<u64>,
+
+    #[arg(long, env = "PBUDGET_CACHE_EXPENSE_TTL_SECS")]
+    pub cache_expense_ttl_secs: Option<u64>,
+
+    #[arg(long, env = "PBUDGET_SCHEDULER_INTERVAL_SECS")]
+    pub scheduler_interval_secs: Option<u64>,
+
+    /// CSV file of exchange rates to load at startup
+    #[arg(long, env = "PBUDGET_EXCHANGE_RATES_FILE")]
+    pub exchange_rates_file: Option<PathBuf>,
+
+    #[arg(long, env = "PBUDGET_MAIL_BACKEND", value_enum)]
+    pub mail_backend: Option<MailBackend>,
+
+    #[arg(long, env = "PBUDGET_MAIL_FROM")]
+    pub mail_from: Option<String>,
+
+    #[arg(long, env = "PBUDGET_MAIL_SMTP_HOST")]
+    pub mail_smtp_host: Option<String>,
+
+    #[arg(long, env = "PBUDGET_MAIL_SMTP_PORT")]
+    pub mail_smtp_port: Option<u16>,
+
+    /// File the `file` mail backend appends messages to
+    #[arg(long, env = "PBUDGET_MAIL_FILE")]
+    pub mail_file: Option<PathBuf>,
+
+    #[arg(long, env = "PBUDGET_MAIL_VERIFICATION_TTL_SECS")]
+    pub mail_verification_ttl_secs: Option<i64>,
+
+    #[arg(long, env = "PBUDGET_MAIL_RESET_TTL_SECS")]
+    pub mail_reset_ttl_secs: Option<i64>,
+}
+
+#[derive(Clone, Debug)]
+pub struct Settings {
+    pub database: DatabaseSettings,
+    pub server: ServerSettings,
+    pub redis: RedisSettings,
+    pub jwt: JwtSettings,
+    pub cache: CacheSettings,
+    pub scheduler: SchedulerSettings,
+    pub exchange_rates: ExchangeRateSettings,
+    pub mail: MailSettings,
+}
+
+#[derive(Clone, Debug)]
+pub struct DatabaseSettings {
+    pub url: String,
+}
+
+#[derive(Clone, Debug)]
+pub struct ServerSettings {
+    pub host: String,
+    pub port: u16,
+    /// `None` keeps actix's default of one worker per physical core.
+    pub workers: Option<usize>,
+}
+
+#[derive(Clone, Debug)]
+pub struct RedisSettings {
+    pub url: String,
+    pub connect_timeout_ms: u64,
+    /// Commands that take longer than this are treated as cache misses.
+    pub response_timeout_ms: u64,
+    /// How long to wait before reconnecting after Redis was unreachable.
+    pub retry_interval_secs: u64,
+}
+
+#[derive(Clone)]
+pub struct JwtSettings {
+    pub secret: String,
+    /// Lifetime of an access token.
+    pub expiry_secs: i64,
+    /// Lifetime of a refresh token; every refresh issues a new one.
+    pub refresh_expiry_secs: i64,
+}
+
+#[derive(Clone, Debug)]
+pub struct CacheSettings {
+    pub backend: CacheBackend,
+    /// Maximum number of entries kept by the in-memory backend.
+    pub memory_capacity: usize,
+    pub profile_ttl_secs: u64,
+    pub budget_ttl_secs: u64,
+    pub expense_ttl_secs: u64,
+}
+
+#[derive(Clone, Debug)]
+pub struct SchedulerSettings {
+    /// How often due recurring expenses are created.
+    pub interval_secs: u64,
+}
+
+#[derive(Clone, Debug)]
+pub struct ExchangeRateSettings {
+    /// Rows of `base_currency,quote_currency,effective_on,rate` that are
+    /// added to (or replace) the stored rates on every start.
+    pub file: Option<PathBuf>,
+}
+
+#[derive(Clone, Debug)]
+pub struct MailSettings {
+    pub backend: MailBackend,
+    /// Sender address of every message.
+    pub from: String,
+    pub smtp_host: String,
+    pub smtp_port: u16,
+    /// Where the `file` backend appends messages.
+    pub file: Option<PathBuf>,
+    /// How long an email verification link stays valid.
+    pub verification_ttl_secs: i64,
+    /// How long a password reset link stays valid.
+    pub reset_ttl_secs: i64,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum MailBackend {
+    Smtp,
+    File,
+    Log,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum CacheBackend {
+    Redis,
+    Memory,
+    None,
+}
+
+impl fmt::Debug for JwtSettings {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("JwtSettings")
+            .field("secret", &"<redacted>")
+            .field("expiry_secs", &self.expiry_secs)
+            .field("refresh_expiry_secs", &self.refresh_expiry_secs)
+            .finish()
+    }
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default, deny_unknown_fields)]
+struct FileSettings {
+    database: FileDatabase,
+    server: FileServer,
+    redis: FileRedis,
+    jwt: FileJwt,
+    cache: FileCache,
+    scheduler: FileScheduler,
+    exchange_rates: FileExchangeRates,
+    mail: FileMail,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default, deny_unknown_fields)]
+struct FileDatabase {
+    url: Option<String>,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default, deny_unknown_fields)]
+struct FileServer {
+    host: Option<String>,
+    port: Option<u16>,
+    workers: Option<usize>,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default, deny_unknown_fields)]
+struct FileRedis {
+    url: Option<String>,
+    connect_timeout_ms: Option<u64>,
+    response_timeout_ms: Option<u64>,
+    retry_interval_secs: Option<u64>,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default, deny_unknown_fields)]
+struct FileJwt {
+    secret: Option<String>,
+    expiry_secs: Option<i64>,
+    refresh_expiry_secs: Option<i64>,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default, deny_unknown_fields)]
+struct FileCache {
+    backend: Option<CacheBackend>,
+    memory_capacity: Option<usize>,
+    profile_ttl_secs: Option<u64>,
+    budget_ttl_secs: Option<u64>,
+    expense_ttl_secs: Option<u64>,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default, deny_unknown_fields)]
+struct FileScheduler {
+    interval_secs: Option<u64>,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default, deny_unknown_fields)]
+struct FileExchangeRates {
+    file: Option<PathBuf>,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default, deny_unknown_fields)]
+struct FileMail {
+    backend: Option<MailBackend>,
+    from: Option<String>,
+    smtp_host: Option<String>,
+    smtp_port: Option<u16>,
+    file: Option<PathBuf>,
+    verification_ttl_secs: Option<i64>,
+    reset_ttl_secs: Option<i64>,
+}
+
+#[derive(Debug)]
+pub enum ConfigError {
+    ReadFile(PathBuf, std::io::Error),
+    ParseFile(PathBuf, toml::de::Error),
+    Missing(&'static str),
+    Invalid(&'static str, String),
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::ReadFile(path, e) => {
+                write!(f, "cannot read config file {}: {}", path.display(), e)
+            }
+            ConfigError::ParseFile(path, e) => {
+                write!(f, "cannot parse config file {}: {}", path.display(), e)
+            }
+            ConfigError::Missing(key) => write!(
+                f,
+                "`{}` is not set (use the config file, environment or command line)",
+                key
+            ),
+            ConfigError::Invalid(key, reason) => write!(f, "`{}` is invalid: {}", key, reason),
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+impl Settings {
+    /// Loads settings from the config file, the environment and the command
+    /// line of the running process.
+    pub fn load() -> Result<Self, ConfigError> {
+        Self::from_cli(Cli::parse())
+    }
+
+    pub fn from_cli(cli: Cli) -> Result<Self, ConfigError> {
+        let file = match &cli.config {
+            Some(path) => read_file(path)?,
+            None => {
+                let path = PathBuf::from(DEFAULT_CONFIG_FILE);
+                if path.exists() {
+                    read_file(&path)?
+                } else {
+                    FileSettings::default()
+                }
+            }
+        };
+
+        let settings = Settings {
+            database: DatabaseSettings {
+                url: cli
+                    .database_url
+                    .or(file.database.url)
+                    .unwrap_or_else(|| "sqlite://store.sqlite?mode=rwc".to_string()),
+            },
+            server: ServerSettings {
+                host: cli
+                    .host
+                    .or(file.server.host)
+                    .unwrap_or_else(|| "127.0.0.1".to_string()),
+                port: cli.port.or(file.server.port).unwrap_or(8080),
+                workers: cli.workers.or(file.server.workers),
+            },
+            redis: RedisSettings {
+                url: cli
+                    .redis_url
+                    .or(file.redis.url)
+                    .unwrap_or_else(|| "redis://127.0.0.1".to_string()),
+                connect_timeout_ms: cli
+                    .redis_connect_timeout_ms
+                    .or(file.redis.connect_timeout_ms)
+                    .unwrap_or(250),
+                response_timeout_ms: cli
+                    .redis_response_timeout_ms
+                    .or(file.redis.response_timeout_ms)
+                    .unwrap_or(250),
+                retry_interval_secs: cli
+                    .redis_retry_interval_secs
+                    .or(file.redis.retry_interval_secs)
+                    .unwrap_or(5),
+            },
+            jwt: JwtSettings {
+                secret: cli
+                    .jwt_secret
+                    .or(file.jwt.secret)
+                    .ok_or(ConfigError::Missing("jwt.secret"))?,
+                expiry_secs: cli.jwt_expiry_secs.or(file.jwt.expiry_secs).unwrap_or(900),
+                refresh_expiry_secs: cli
+                    .jwt_refresh_expiry_secs
+                    .or(file.jwt.refresh_expiry_secs)
+                    .unwrap_or(30 * 86400),
+            },
+            cache: CacheSettings {
+                backend: cli
+                    .cache_backend
+                    .or(file.cache.backend)
+                    .unwrap_or(CacheBackend::Redis),
+                memory_capacity: cli
+                    .cache_memory_capacity
+                    .or(file.cache.memory_capacity)
+                    .unwrap_or(10_000),
+                profile_ttl_secs: cli
+                    .cache_profile_ttl_secs
+                    .or(file.cache.profile_ttl_secs)
+                    .unwrap_or(86400),
+                budget_ttl_secs: cli
+                    .cache_budget_ttl_secs
+                    .or(file.cache.budget_ttl_secs)
+                    .unwrap_or(86400),
+                expense_ttl_secs: cli
+                    .cache_expense_ttl_secs
+                    .or(file.cache.expense_ttl_secs)
+                    .unwrap_or(86400),
+            },
+            scheduler: SchedulerSettings {
+                interval_secs: cli
+                    .scheduler_interval_secs
+                    .or(file.scheduler.interval_secs)
+                    .unwrap_or(3600),
+            },
+            exchange_rates: ExchangeRateSettings {
+                file: cli.exchange_rates_file.or(file.exchange_rates.file),
+            },
+            mail: MailSettings {
+                backend: cli
+                    .mail_backend
+                    .or(file.mail.backend)
+                    .unwrap_or(MailBackend::Log),
+                from: cli
+                    .mail_from
+                    .or(file.mail.from)
+                    .unwrap_or_else(|| "pbudget@localhost".to_string()),
+                smtp_host: cli
+                    .mail_smtp_host
+                    .or(file.mail.smtp_host)
+                    .unwrap_or_else(|| "127.0.0.1".to_string()),
+                smtp_port: cli.mail_smtp_port.or(file.mail.smtp_port).unwrap_or(25),
+                file: cli.mail_file.or(file.mail.file),
+                verification_ttl_secs: cli
+                    .mail_verification_ttl_secs
+                    .or(file.mail.verification_ttl_secs)
+                    .unwrap_or(86400),
+                reset_ttl_secs: cli
+                    .mail_reset_ttl_secs
+                    .or(file.mail.reset_ttl_secs)
+                    .unwrap_or(3600),
+            },
+        };
+
+        settings.validate()?;
+        Ok(settings)
+    }
+
+    fn validate(&self) -> Result<(), ConfigError> {
+        if !self.database.url.starts_with("sqlite:") {
+            return Err(ConfigError::Invalid(
+                "database.url",
+                "only sqlite:// URLs are supported".to_string(),
+            ));
+        }
+        if self.server.host.trim().is_empty() {
+            return Err(ConfigError::Invalid(
+                "server.host",
+                "must not be empty".to_string(),
+            ));
+        }
+        if self.server.port == 0 {
+            return Err(ConfigError::Invalid(
+                "server.port",
+                "must be between 1 and 65535".to_string(),
+            ));
+        }
+        if self.server.workers == Some(0) {
+            return Err(ConfigError::Invalid(
+                "server.workers",
+                "must be at least 1".to_string(),
+            ));
+        }
+        if !["redis://", "rediss://", "redis+unix://", "unix://"]
+            .iter()
+            .any(|scheme| self.redis.url.starts_with(scheme))
+        {
+            return Err(ConfigError::Invalid(
+                "redis.url",
+                "expected a redis://, rediss:// or unix:// URL".to_string(),
+            ));
+        }
+        for (key, timeout) in [
+            ("redis.connect_timeout_ms", self.redis.connect_timeout_ms),
+            ("redis.response_timeout_ms", self.redis.response_timeout_ms),
+        ] {
+            if timeout == 0 {
+                return Err(ConfigError::Invalid(
+                    key,
+                    "must be greater than zero".to_string(),
+                ));
+            }
+        }
+        if self.jwt.secret.trim().is_empty() {
+            return Err(ConfigError::Invalid(
+                "jwt.secret",
+                "must not be empty".to_string(),
+            ));
+        }
+        for (key, expiry) in [
+            ("jwt.expiry_secs", self.jwt.expiry_secs),
+            ("jwt.refresh_expiry_secs", self.jwt.refresh_expiry_secs),
+            (
+                "mail.verification_ttl_secs",
+                self.mail.verification_ttl_secs,
+            ),
+            ("mail.reset_ttl_secs", self.mail.reset_ttl_secs),
+        ] {
+            if expiry <= 0 {
+                return Err(ConfigError::Invalid(
+                    key,
+                    "must be greater than zero".to_string(),
+                ));
+            }
+        }
+        if self.cache.backend == CacheBackend::Memory && self.cache.memory_capacity == 0 {
+            return Err(ConfigError::Invalid(
+                "cache.memory_capacity",
+                "must be at least 1".to_string(),
+            ));
+        }
+        for (key, ttl) in [
+            ("cache.profile_ttl_secs", self.cache.profile_ttl_secs),
+            ("cache.budget_ttl_secs", self.cache.budget_ttl_secs),
+            ("cache.expense_ttl_secs", self.cache.expense_ttl_secs),
+            ("scheduler.interval_secs", self.scheduler.interval_secs),
+        ] {
+            if ttl == 0 {
+                return Err(ConfigError::Invalid(
+                    key,
+                    "must be greater than zero".to_string(),
+                ));
+            }
+        }
+        if !self.mail.from.contains('@') {
+            return Err(ConfigError::Invalid(
+                "mail.from",
+                "must be an email address".to_string(),
+            ));
+        }
+        if self.mail.backend == MailBackend::Smtp && self.mail.smtp_port == 0 {
+            return Err(ConfigError::Invalid(
+                "mail.smtp_port",
+                "must be between 1 and 65535".to_string(),
+            ));
+        }
+        if self.mail.backend == MailBackend::File && self.mail.file.is_none() {
+            return Err(ConfigError::Missing("mail.file"));
+        }
+        Ok(())
+    }
+}
+
+fn read_file(path: &PathBuf) -> Result<FileSettings, ConfigError> {
+    let contents = fs::read_to_string(path).map_err(|e| ConfigError::ReadFile(path.clone(), e))?;
+    toml::from_str(&contents).map_err(|e| ConfigError::ParseFile(path.clone(), e))
+}
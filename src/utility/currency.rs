@@ -0,0 +1,227 @@
+use std::{collections::HashMap, fmt, fs, path::Path};
+
+use chrono::NaiveDate;
+use entities::{exchange_rate, money::Money, rate::Rate};
+use sea_orm::{
+    sea_query::{Condition, OnConflict},
+    ColumnTrait, ConnectionTrait, DbErr, EntityTrait, QueryFilter, QueryOrder, Set,
+};
+
+use crate::utility::error::AppError;
+
+/// Currency of budgets created without one, and of all amounts recorded
+/// before currencies were introduced.
+pub const DEFAULT_CURRENCY: &str = "USD";
+
+/// Active ISO 4217 currency codes, sorted.
+const ISO_4217: &[&str] = &[
+    "AED", "AFN", "ALL", "AMD", "ANG", "AOA", "ARS", "AUD", "AWG", "AZN", "BAM", "BBD", "BDT",
+    "BGN", "BHD", "BIF", "BMD", "BND", "BOB", "BRL", "BSD", "BTN", "BWP", "BYN", "BZD", "CAD",
+    "CDF", "CHF", "CLP", "CNY", "COP", "CRC", "CUP", "CVE", "CZK", "DJF", "DKK", "DOP", "DZD",
+    "EGP", "ERN", "ETB", "EUR", "FJD", "FKP", "GBP", "GEL", "GHS", "GIP", "GMD", "GNF", "GTQ",
+    "GYD", "HKD", "HNL", "HTG", "HUF", "IDR", "ILS", "INR", "IQD", "IRR", "ISK", "JMD", "JOD",
+    "JPY", "KES", "KGS", "KHR", "KMF", "KPW", "KRW", "KWD", "KYD", "KZT", "LAK", "LBP", "LKR",
+    "LRD", "LSL", "LYD", "MAD", "MDL", "MGA", "MKD", "MMK", "MNT", "MOP", "MRU", "MUR", "MVR",
+    "MWK", "MXN", "MYR", "MZN", "NAD", "NGN", "NIO", "NOK", "NPR", "NZD", "OMR", "PAB", "PEN",
+    "PGK", "PHP", "PKR", "PLN", "PYG", "QAR", "RON", "RSD", "RUB", "RWF", "SAR", "SBD", "SCR",
+    "SDG", "SEK", "SGD", "SHP", "SLE", "SLL", "SOS", "SRD", "SSP", "STN", "SVC", "SYP", "SZL",
+    "THB", "TJS", "TMT", "TND", "TOP", "TRY", "TTD", "TWD", "TZS", "UAH", "UGX", "USD", "UYU",
+    "UZS", "VES", "VND", "VUV", "WST", "XAF", "XCD", "XCG", "XOF", "XPF", "YER", "ZAR", "ZMW",
+    "ZWG", "ZWL",
+];
+
+pub fn is_currency(code: &str) -> bool {
+    ISO_4217.binary_search(&code).is_ok()
+}
+
+/// Exchange rates into one currency, loaded for converting expenses in a
+/// known set of other currencies.
+pub struct Rates {
+    to: String,
+    /// Per foreign currency, sorted by effective date. `true` marks rates
+    /// quoted from the foreign currency into `to`, `false` the other way
+    /// round; on the same day the former wins.
+    by_currency: HashMap<String, Vec<(NaiveDate, bool, Rate)>>,
+}
+
+impl Rates {
+    /// Loads every rate between `to` and one of `from`, in either direction.
+    pub async fn load<C, I, S>(db: &C, to: &str, from: I) -> Result<Self, DbErr>
+    where
+        C: ConnectionTrait,
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        let mut foreign: Vec<String> = from
+            .into_iter()
+            .map(|c| c.as_ref().to_string())
+            .filter(|c| c != to)
+            .collect();
+        foreign.sort();
+        foreign.dedup();
+
+        let mut by_currency: HashMap<String, Vec<(NaiveDate, bool, Rate)>> = HashMap::new();
+        if !foreign.is_empty() {
+            let rates = exchange_rate::Entity::find()
+                .filter(
+                    Condition::any()
+                        .add(
+                            exchange_rate::Column::QuoteCurrency
+                                .eq(to)
+                                .and(exchange_rate::Column::BaseCurrency.is_in(foreign.clone())),
+                        )
+                        .add(
+                            exchange_rate::Column::BaseCurrency
+                                .eq(to)
+                                .and(exchange_rate::Column::QuoteCurrency.is_in(foreign)),
+                        ),
+                )
+                .order_by_asc(exchange_rate::Column::EffectiveOn)
+                .all(db)
+                .await?;
+            for rate in rates {
+                let (currency, direct) = if rate.quote_currency == to {
+                    (rate.base_currency, true)
+                } else {
+                    (rate.quote_currency, false)
+                };
+                by_currency.entry(currency).or_default().push((
+                    rate.effective_on,
+                    direct,
+                    rate.rate,
+                ));
+            }
+            for rates in by_currency.values_mut() {
+                rates.sort();
+            }
+        }
+
+        Ok(Rates {
+            to: to.to_string(),
+            by_currency,
+        })
+    }
+
+    /// Converts `amount` from `currency` using the latest rate in effect on
+    /// `on`.
+    pub fn convert(&self, amount: Money, currency: &str, on: NaiveDate) -> Result<Money, AppError> {
+        if currency == self.to {
+            return Ok(amount);
+        }
+        let missing = || {
+            AppError::Conflict(format!(
+                "no exchange rate from {} to {} on or before {}",
+                currency, self.to, on
+            ))
+        };
+        let rates = self.by_currency.get(currency).ok_or_else(missing)?;
+        let effective = rates.partition_point(|(date, _, _)| *date <= on);
+        let (_, direct, rate) = effective
+            .checked_sub(1)
+            .map(|i| rates[i])
+            .ok_or_else(missing)?;
+        let converted = if direct {
+            rate.convert(amount)
+        } else {
+            rate.convert_inverse(amount)
+        };
+        converted.ok_or_else(|| AppError::Internal("currency conversion overflowed".to_string()))
+    }
+}
+
+#[derive(Debug)]
+pub enum ImportError {
+    Read(std::io::Error),
+    Line(usize, String),
+    Db(DbErr),
+}
+
+impl fmt::Display for ImportError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ImportError::Read(e) => write!(f, "cannot read exchange rates: {}", e),
+            ImportError::Line(line, reason) => {
+                write!(f, "invalid exchange rate on line {}: {}", line, reason)
+            }
+            ImportError::Db(e) => write!(f, "cannot store exchange rates: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for ImportError {}
+
+/// Parses exchange rates from CSV with the columns
+/// `base_currency,quote_currency,effective_on,rate`, e.g.
+/// `EUR,USD,2024-08-01,1.0876`. A header row, blank lines and lines starting
+/// with `#` are skipped.
+pub fn parse_csv(csv: &str) -> Result<Vec<exchange_rate::Model>, ImportError> {
+    let mut rates = Vec::new();
+    for (index, line) in csv.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') || line.starts_with("base_currency") {
+            continue;
+        }
+        let invalid = |reason: &str| ImportError::Line(index + 1, reason.to_string());
+        let fields: Vec<&str> = line.split(',').map(str::trim).collect();
+        let [base, quote, effective_on, rate] = fields[..] else {
+            return Err(invalid("expected 4 comma separated fields"));
+        };
+        if !is_currency(base) || !is_currency(quote) {
+            return Err(invalid("currencies must be ISO 4217 codes"));
+        }
+        if base == quote {
+            return Err(invalid("base and quote currency must differ"));
+        }
+        let effective_on = effective_on
+            .parse::<NaiveDate>()
+            .map_err(|_| invalid("effective date must be YYYY-MM-DD"))?;
+        let rate = rate.parse::<Rate>().map_err(|e| invalid(&e.to_string()))?;
+        rates.push(exchange_rate::Model {
+            base_currency: base.to_string(),
+            quote_currency: quote.to_string(),
+            effective_on,
+            rate,
+        });
+    }
+    Ok(rates)
+}
+
+/// Stores `rates`, replacing any earlier rate for the same pair and day.
+pub async fn store_rates<C: ConnectionTrait>(
+    db: &C,
+    rates: Vec<exchange_rate::Model>,
+) -> Result<usize, DbErr> {
+    let count = rates.len();
+    // Keeps each statement well below SQLite's limit on bound parameters.
+    for chunk in rates.chunks(200) {
+        let models = chunk
+            .iter()
+            .cloned()
+            .map(|rate| exchange_rate::ActiveModel {
+                base_currency: Set(rate.base_currency),
+                quote_currency: Set(rate.quote_currency),
+                effective_on: Set(rate.effective_on),
+                rate: Set(rate.rate),
+            });
+        exchange_rate::Entity::insert_many(models)
+            .on_conflict(
+                OnConflict::columns([
+                    exchange_rate::Column::BaseCurrency,
+                    exchange_rate::Column::QuoteCurrency,
+                    exchange_rate::Column::EffectiveOn,
+                ])
+                .update_column(exchange_rate::Column::Rate)
+                .to_owned(),
+            )
+            .exec(db)
+            .await?;
+    }
+    Ok(count)
+}
+
+/// Loads the exchange rate CSV file at `path` into the database.
+pub async fn import_file<C: ConnectionTrait>(db: &C, path: &Path) -> Result<usize, ImportError> {
+    let csv = fs::read_to_string(path).map_err(ImportError::Read)?;
+    let rates = parse_csv(&csv)?;
+    store_rates(db, rates).await.map_err(ImportError::Db)
+}
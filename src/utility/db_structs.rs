@@ -1,45 +1,534 @@
-use serde::{Deserialize, Serialize};
+use chrono::{DateTime, NaiveDate, Utc};
+use entities::{
+    money::Money,
+    sea_orm_active_enums::{AccountKind, ApiKeyScope, BudgetPeriod, Frequency},
+};
+use serde::{Deserialize, Deserializer, Serialize};
+use uuid::Uuid;
+use validator::Validate;
 
-#[derive(Serialize, Deserialize)]
+use crate::utility::validation::{
+    validate_amount, validate_balance, validate_currency, validate_not_blank, validate_password,
+    validate_splits, validate_tag, validate_tags, validate_username,
+};
+
+#[derive(Serialize, Deserialize, Validate)]
 pub struct NewUser {
+    #[validate(length(min = 3, max = 32, message = "must be 3 to 32 characters long"))]
+    #[validate(custom(function = "validate_username"))]
     pub username: String,
+    #[validate(custom(function = "validate_password"))]
     pub password: String,
+    #[validate(email(message = "must be a valid email address"))]
+    #[validate(length(max = 254, message = "must be at most 254 characters long"))]
     pub email: String,
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, Validate)]
 pub struct LoginInfo {
+    #[validate(length(min = 1, max = 32, message = "must be 1 to 32 characters long"))]
     pub username: String,
+    #[validate(length(min = 1, max = 128, message = "must be 1 to 128 characters long"))]
     pub password: String,
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, Validate)]
+pub struct EmailTokenForm {
+    #[validate(length(min = 1, max = 128, message = "must be 1 to 128 characters long"))]
+    pub token: String,
+}
+
+#[derive(Serialize, Deserialize, Validate)]
+pub struct ForgotPassword {
+    #[validate(email(message = "must be a valid email address"))]
+    #[validate(length(max = 254, message = "must be at most 254 characters long"))]
+    pub email: String,
+}
+
+#[derive(Serialize, Deserialize, Validate)]
+pub struct ResetPassword {
+    #[validate(length(min = 1, max = 128, message = "must be 1 to 128 characters long"))]
+    pub token: String,
+    #[validate(custom(function = "validate_password"))]
+    pub password: String,
+}
+
+#[derive(Serialize, Deserialize, Validate)]
+pub struct RefreshRequest {
+    #[validate(length(min = 1, max = 128, message = "must be 1 to 128 characters long"))]
+    pub refresh_token: String,
+}
+
+#[derive(Serialize, Deserialize, Validate)]
+pub struct TwoFactorCode {
+    /// A TOTP code or a recovery code.
+    #[validate(length(min = 1, max = 32, message = "must be 1 to 32 characters long"))]
+    pub code: String,
+}
+
+#[derive(Serialize, Deserialize, Validate)]
+pub struct TwoFactorLogin {
+    #[validate(length(min = 1, max = 128, message = "must be 1 to 128 characters long"))]
+    pub challenge_token: String,
+    /// A TOTP code or a recovery code.
+    #[validate(length(min = 1, max = 32, message = "must be 1 to 32 characters long"))]
+    pub code: String,
+}
+
+#[derive(Serialize, Deserialize, Validate)]
 pub struct UpdateUser {
+    #[validate(length(min = 3, max = 32, message = "must be 3 to 32 characters long"))]
+    #[validate(custom(function = "validate_username"))]
     pub username: Option<String>,
+    #[validate(custom(function = "validate_password"))]
     pub password: Option<String>,
+    #[validate(email(message = "must be a valid email address"))]
+    #[validate(length(max = 254, message = "must be at most 254 characters long"))]
     pub email: Option<String>,
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, Validate)]
+pub struct NewApiKey {
+    #[validate(length(max = 100, message = "must be at most 100 characters long"))]
+    #[validate(custom(function = "validate_not_blank"))]
+    pub name: String,
+    /// Defaults to `read`.
+    pub scope: Option<ApiKeyScope>,
+    /// `None` for a key that never expires.
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Serialize, Deserialize, Validate)]
+pub struct UpdateApiKey {
+    #[validate(length(max = 100, message = "must be at most 100 characters long"))]
+    #[validate(custom(function = "validate_not_blank"))]
+    pub name: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Validate)]
 pub struct NewBudget {
+    #[validate(length(max = 100, message = "must be at most 100 characters long"))]
+    #[validate(custom(function = "validate_not_blank"))]
     pub name: String,
-    pub total_amount: f64,
+    #[validate(custom(function = "validate_amount"))]
+    pub total_amount: Money,
+    /// ISO 4217 code; defaults to `USD`.
+    #[validate(custom(function = "validate_currency"))]
+    pub currency: Option<String>,
+    /// Defaults to `monthly`.
+    pub period: Option<BudgetPeriod>,
+    /// Defaults to today (UTC).
+    pub starts_on: Option<NaiveDate>,
+    /// Required for, and only allowed with, `custom` periods.
+    pub ends_on: Option<NaiveDate>,
+    #[serde(default)]
+    pub rollover: bool,
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, Validate)]
 pub struct UpdateBudget {
+    #[validate(length(max = 100, message = "must be at most 100 characters long"))]
+    #[validate(custom(function = "validate_not_blank"))]
     pub name: Option<String>,
-    pub total_amount: Option<f64>,
+    #[validate(custom(function = "validate_amount"))]
+    pub total_amount: Option<Money>,
+    #[validate(custom(function = "validate_currency"))]
+    pub currency: Option<String>,
+    pub period: Option<BudgetPeriod>,
+    pub starts_on: Option<NaiveDate>,
+    #[serde(default, deserialize_with = "nullable")]
+    pub ends_on: Option<Option<NaiveDate>>,
+    pub rollover: Option<bool>,
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, Validate)]
 pub struct NewExpense {
-    pub amount: f64,
+    #[validate(custom(function = "validate_amount"))]
+    pub amount: Money,
+    /// ISO 4217 code; defaults to the budget's currency.
+    #[validate(custom(function = "validate_currency"))]
+    pub currency: Option<String>,
+    #[validate(length(max = 500, message = "must be at most 500 characters long"))]
     pub description: String,
+    /// Day the money was spent; defaults to today (UTC).
+    #[serde(default, deserialize_with = "expense_date")]
+    pub date: Option<NaiveDate>,
+    pub category_id: Option<Uuid>,
+    /// The account the expense was paid from.
+    pub account_id: Option<Uuid>,
+    /// Tag names; tags that do not exist yet are created.
+    #[serde(default)]
+    #[validate(custom(function = "validate_tags"))]
+    pub tags: Vec<String>,
+    /// Lines charging parts of the expense to other budgets or categories;
+    /// they must add up to `amount`.
+    #[serde(default)]
+    #[validate(custom(function = "validate_splits"))]
+    pub splits: Vec<SplitLine>,
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, Validate)]
 pub struct UpdateExpense {
-    pub amount: Option<f64>,
+    #[validate(custom(function = "validate_amount"))]
+    pub amount: Option<Money>,
+    #[validate(custom(function = "validate_currency"))]
+    pub currency: Option<String>,
+    #[validate(length(max = 500, message = "must be at most 500 characters long"))]
+    pub description: Option<String>,
+    #[serde(default, deserialize_with = "expense_date")]
+    pub date: Option<NaiveDate>,
+    /// `null` removes the expense from its category.
+    #[serde(default, deserialize_with = "nullable")]
+    pub category_id: Option<Option<Uuid>>,
+    /// `null` unlinks the expense from its account.
+    #[serde(default, deserialize_with = "nullable")]
+    pub account_id: Option<Option<Uuid>>,
+    /// Replaces all of the expense's tags.
+    #[validate(custom(function = "validate_tags"))]
+    pub tags: Option<Vec<String>>,
+    /// Replaces all of the expense's split lines; `[]` stops splitting it.
+    #[validate(custom(function = "validate_splits"))]
+    pub splits: Option<Vec<SplitLine>>,
+}
+
+/// One line of a split expense, in the expense's currency.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct SplitLine {
+    pub amount: Money,
+    /// Defaults to the expense's own budget.
+    pub budget_id: Option<Uuid>,
+    pub category_id: Option<Uuid>,
+}
+
+#[derive(Serialize, Deserialize, Validate)]
+pub struct NewRecurringExpense {
+    #[validate(custom(function = "validate_amount"))]
+    pub amount: Money,
+    /// ISO 4217 code; defaults to the budget's currency.
+    #[validate(custom(function = "validate_currency"))]
+    pub currency: Option<String>,
+    #[validate(length(max = 500, message = "must be at most 500 characters long"))]
+    pub description: String,
+    pub category_id: Option<Uuid>,
+    pub frequency: Frequency,
+    /// Defaults to 1.
+    #[validate(range(min = 1, max = 1000, message = "must be between 1 and 1000"))]
+    pub interval: Option<i32>,
+    /// Date of the first occurrence; defaults to today (UTC).
+    pub starts_on: Option<NaiveDate>,
+    pub ends_on: Option<NaiveDate>,
+    #[validate(range(min = 1, max = 10000, message = "must be between 1 and 10000"))]
+    pub count: Option<i32>,
+}
+
+/// The schedule itself cannot be changed, only where it stops.
+#[derive(Serialize, Deserialize, Validate)]
+pub struct UpdateRecurringExpense {
+    #[validate(custom(function = "validate_amount"))]
+    pub amount: Option<Money>,
+    #[validate(custom(function = "validate_currency"))]
+    pub currency: Option<String>,
+    #[validate(length(max = 500, message = "must be at most 500 characters long"))]
+    pub description: Option<String>,
+    #[serde(default, deserialize_with = "nullable")]
+    pub category_id: Option<Option<Uuid>>,
+    #[serde(default, deserialize_with = "nullable")]
+    pub ends_on: Option<Option<NaiveDate>>,
+    #[serde(default, deserialize_with = "nullable")]
+    pub count: Option<Option<i32>>,
+}
+
+#[derive(Serialize, Deserialize, Validate)]
+pub struct NewIncome {
+    #[validate(length(max = 100, message = "must be at most 100 characters long"))]
+    #[validate(custom(function = "validate_not_blank"))]
+    pub source: String,
+    #[validate(custom(function = "validate_amount"))]
+    pub amount: Money,
+    /// ISO 4217 code; defaults to the linked budget's currency, or `USD`.
+    #[validate(custom(function = "validate_currency"))]
+    pub currency: Option<String>,
+    /// Day the money came in; defaults to today (UTC).
+    #[serde(default, deserialize_with = "expense_date")]
+    pub date: Option<NaiveDate>,
+    pub budget_id: Option<Uuid>,
+    /// The account the income was paid into.
+    pub account_id: Option<Uuid>,
+}
+
+#[derive(Serialize, Deserialize, Validate)]
+pub struct UpdateIncome {
+    #[validate(length(max = 100, message = "must be at most 100 characters long"))]
+    #[validate(custom(function = "validate_not_blank"))]
+    pub source: Option<String>,
+    #[validate(custom(function = "validate_amount"))]
+    pub amount: Option<Money>,
+    #[validate(custom(function = "validate_currency"))]
+    pub currency: Option<String>,
+    #[serde(default, deserialize_with = "expense_date")]
+    pub date: Option<NaiveDate>,
+    /// `null` unlinks the income from its budget.
+    #[serde(default, deserialize_with = "nullable")]
+    pub budget_id: Option<Option<Uuid>>,
+    /// `null` unlinks the income from its account.
+    #[serde(default, deserialize_with = "nullable")]
+    pub account_id: Option<Option<Uuid>>,
+}
+
+#[derive(Serialize, Deserialize, Validate)]
+pub struct NewAccount {
+    #[validate(length(max = 100, message = "must be at most 100 characters long"))]
+    #[validate(custom(function = "validate_not_blank"))]
+    pub name: String,
+    pub kind: AccountKind,
+    /// ISO 4217 code; defaults to `USD`.
+    #[validate(custom(function = "validate_currency"))]
+    pub currency: Option<String>,
+    /// Defaults to zero.
+    #[serde(default)]
+    #[validate(custom(function = "validate_balance"))]
+    pub opening_balance: Money,
+    /// Defaults to today (UTC).
+    pub opened_on: Option<NaiveDate>,
+}
+
+/// An account's currency cannot be changed, since every amount already
+/// booked against it is in that currency.
+#[derive(Serialize, Deserialize, Validate)]
+pub struct UpdateAccount {
+    #[validate(length(max = 100, message = "must be at most 100 characters long"))]
+    #[validate(custom(function = "validate_not_blank"))]
+    pub name: Option<String>,
+    pub kind: Option<AccountKind>,
+    #[validate(custom(function = "validate_balance"))]
+    pub opening_balance: Option<Money>,
+    pub opened_on: Option<NaiveDate>,
+}
+
+#[derive(Serialize, Deserialize, Validate)]
+pub struct NewTransfer {
+    pub from_account_id: Uuid,
+    pub to_account_id: Uuid,
+    /// In the currency of `from_account_id`.
+    #[validate(custom(function = "validate_amount"))]
+    pub amount: Money,
+    /// In the currency of `to_account_id`; required when it differs from
+    /// that of `from_account_id`, and otherwise equal to `amount`.
+    #[validate(custom(function = "validate_amount"))]
+    pub to_amount: Option<Money>,
+    #[serde(default)]
+    #[validate(length(max = 500, message = "must be at most 500 characters long"))]
+    pub description: String,
+    /// Defaults to today (UTC).
+    #[serde(default, deserialize_with = "expense_date")]
+    pub date: Option<NaiveDate>,
+}
+
+/// The accounts of a transfer cannot be changed.
+#[derive(Serialize, Deserialize, Validate)]
+pub struct UpdateTransfer {
+    #[validate(custom(function = "validate_amount"))]
+    pub amount: Option<Money>,
+    #[validate(custom(function = "validate_amount"))]
+    pub to_amount: Option<Money>,
+    #[validate(length(max = 500, message = "must be at most 500 characters long"))]
     pub description: Option<String>,
+    #[serde(default, deserialize_with = "expense_date")]
+    pub date: Option<NaiveDate>,
+}
+
+/// Query string of `GET /budget/{id}/periods`.
+#[derive(Deserialize)]
+pub struct PeriodHistoryQuery {
+    /// Number of periods to list, newest first; defaults to 12.
+    pub limit: Option<u32>,
+}
+
+/// What the budget and expense listings can be sorted by. `date` is a
+/// budget's `starts_on` and `amount` its `total_amount`.
+#[derive(Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SortField {
+    #[default]
+    Date,
+    Amount,
+    CreatedAt,
+}
+
+#[derive(Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SortOrder {
+    #[default]
+    Asc,
+    Desc,
+}
+
+/// Query string of `GET /budget`.
+#[derive(Deserialize)]
+pub struct BudgetFilter {
+    pub page: Option<u64>,
+    pub limit: Option<u64>,
+    #[serde(default)]
+    pub sort: SortField,
+    #[serde(default)]
+    pub order: SortOrder,
+    /// Only list budgets starting on or after this date.
+    pub from: Option<NaiveDate>,
+    /// Only list budgets starting on or before this date.
+    pub to: Option<NaiveDate>,
+    pub min_amount: Option<Money>,
+    pub max_amount: Option<Money>,
+    /// Only list budgets whose name contains this, ignoring ASCII case.
+    pub q: Option<String>,
+}
+
+/// Query string of `GET /budget/{id}/expenses`.
+#[derive(Deserialize)]
+pub struct ExpenseFilter {
+    pub page: Option<u64>,
+    pub limit: Option<u64>,
+    #[serde(default)]
+    pub sort: SortField,
+    #[serde(default)]
+    pub order: SortOrder,
+    /// Only list expenses dated on or after this day.
+    pub from: Option<NaiveDate>,
+    /// Only list expenses dated on or before this day.
+    pub to: Option<NaiveDate>,
+    pub min_amount: Option<Money>,
+    pub max_amount: Option<Money>,
+    /// Only list expenses whose description contains this, ignoring ASCII
+    /// case.
+    pub q: Option<String>,
+    /// Only list expenses carrying this tag.
+    pub tag: Option<String>,
+}
+
+/// Query string of `GET /income`.
+#[derive(Deserialize)]
+pub struct IncomeFilter {
+    pub page: Option<u64>,
+    pub limit: Option<u64>,
+    #[serde(default)]
+    pub sort: SortField,
+    #[serde(default)]
+    pub order: SortOrder,
+    /// Only list income dated on or after this day.
+    pub from: Option<NaiveDate>,
+    /// Only list income dated on or before this day.
+    pub to: Option<NaiveDate>,
+    pub min_amount: Option<Money>,
+    pub max_amount: Option<Money>,
+    /// Only list income whose source contains this, ignoring ASCII case.
+    pub q: Option<String>,
+    pub budget_id: Option<Uuid>,
+}
+
+/// Query string of `GET /transfers`.
+#[derive(Deserialize)]
+pub struct TransferFilter {
+    pub page: Option<u64>,
+    pub limit: Option<u64>,
+    /// Only list transfers out of or into this account.
+    pub account_id: Option<Uuid>,
+    pub from: Option<NaiveDate>,
+    pub to: Option<NaiveDate>,
+}
+
+/// Query string of `GET /accounts/{id}/balance`.
+#[derive(Deserialize)]
+pub struct BalanceQuery {
+    /// Balance at the end of this day; defaults to today (UTC).
+    pub on: Option<NaiveDate>,
+}
+
+/// Query string of `GET /accounts/{id}/transactions`.
+#[derive(Deserialize)]
+pub struct LedgerQuery {
+    /// Defaults to the day the account was opened.
+    pub from: Option<NaiveDate>,
+    /// Defaults to today (UTC).
+    pub to: Option<NaiveDate>,
+}
+
+/// Query string of `GET /reports/cash-flow`.
+#[derive(Deserialize)]
+pub struct CashFlowQuery {
+    /// Defaults to `monthly`; `custom` is not accepted.
+    pub period: Option<BudgetPeriod>,
+    /// Defaults to the start of the eleventh period before `to`.
+    pub from: Option<NaiveDate>,
+    /// Defaults to today (UTC).
+    pub to: Option<NaiveDate>,
+    /// Currency to report in; defaults to `USD`.
+    pub currency: Option<String>,
+    /// Only count this budget's expenses and the income linked to it.
+    pub budget_id: Option<Uuid>,
+}
+
+/// Query string of `GET /exchange-rates`.
+#[derive(Deserialize)]
+pub struct ExchangeRateFilter {
+    pub page: Option<u64>,
+    pub limit: Option<u64>,
+    pub base: Option<String>,
+    pub quote: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Validate)]
+pub struct NewCategory {
+    #[validate(length(max = 100, message = "must be at most 100 characters long"))]
+    #[validate(custom(function = "validate_not_blank"))]
+    pub name: String,
+    pub parent_id: Option<Uuid>,
+}
+
+#[derive(Serialize, Deserialize, Validate)]
+pub struct UpdateCategory {
+    #[validate(length(max = 100, message = "must be at most 100 characters long"))]
+    #[validate(custom(function = "validate_not_blank"))]
+    pub name: Option<String>,
+    /// `null` turns the category into a top-level one.
+    #[serde(default, deserialize_with = "nullable")]
+    pub parent_id: Option<Option<Uuid>>,
+}
+
+#[derive(Serialize, Deserialize, Validate)]
+pub struct CategoryAllocation {
+    #[validate(custom(function = "validate_amount"))]
+    pub amount: Money,
+}
+
+#[derive(Serialize, Deserialize, Validate)]
+pub struct TagName {
+    #[validate(custom(function = "validate_tag"))]
+    pub name: String,
+}
+
+/// Tells an explicit `null` (`Some(None)`) apart from a missing field
+/// (`None`, via `#[serde(default)]`).
+fn nullable<'de, D, T>(deserializer: D) -> Result<Option<Option<T>>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    Option::<T>::deserialize(deserializer).map(Some)
+}
+
+/// Accepts a calendar date (`2024-08-15`) or an RFC 3339 timestamp. For
+/// timestamps the date is taken in the offset the client sent, so an expense
+/// made late in the evening in New York is not booked on the next day.
+fn expense_date<'de, D>(deserializer: D) -> Result<Option<NaiveDate>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let Some(value) = Option::<String>::deserialize(deserializer)? else {
+        return Ok(None);
+    };
+    if let Ok(date) = NaiveDate::parse_from_str(&value, "%Y-%m-%d") {
+        return Ok(Some(date));
+    }
+    DateTime::parse_from_rfc3339(&value)
+        .map(|timestamp| Some(timestamp.date_naive()))
+        .map_err(|_| serde::de::Error::custom("date must be YYYY-MM-DD or an RFC 3339 timestamp"))
 }
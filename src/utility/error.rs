@@ -0,0 +1,169 @@
+use std::fmt;
+
+use actix_web::{
+    error::{JsonPayloadError, PathError, QueryPayloadError},
+    http::StatusCode,
+    HttpRequest, HttpResponse, ResponseError,
+};
+use log::error;
+use sea_orm::{DbErr, SqlErr, TransactionError};
+use serde::Serialize;
+
+/// Error returned by every handler. Serialized as
+/// `{"error": {"code": ..., "message": ..., "details": [...]}}` where `code`
+/// is stable and meant for programs, `message` is meant for people.
+#[derive(Debug)]
+pub enum AppError {
+    Validation(Vec<FieldError>),
+    BadRequest(String),
+    PayloadTooLarge(String),
+    Unauthorized(String),
+    Forbidden(String),
+    NotFound(&'static str),
+    Conflict(String),
+    Unavailable(String),
+    Internal(String),
+}
+
+#[derive(Debug, Serialize)]
+pub struct FieldError {
+    pub field: String,
+    pub message: String,
+}
+
+#[derive(Serialize)]
+struct ErrorBody<'a> {
+    error: ErrorDetail<'a>,
+}
+
+#[derive(Serialize)]
+struct ErrorDetail<'a> {
+    code: &'static str,
+    message: String,
+    #[serde(skip_serializing_if = "<[FieldError]>::is_empty")]
+    details: &'a [FieldError],
+}
+
+impl AppError {
+    pub fn code(&self) -> &'static str {
+        match self {
+            AppError::Validation(_) => "validation_failed",
+            AppError::BadRequest(_) => "bad_request",
+            AppError::PayloadTooLarge(_) => "payload_too_large",
+            AppError::Unauthorized(_) => "unauthorized",
+            AppError::Forbidden(_) => "forbidden",
+            AppError::NotFound(_) => "not_found",
+            AppError::Conflict(_) => "conflict",
+            AppError::Unavailable(_) => "service_unavailable",
+            AppError::Internal(_) => "internal_error",
+        }
+    }
+
+    pub fn field(field: &str, message: &str) -> Self {
+        AppError::Validation(vec![FieldError {
+            field: field.to_string(),
+            message: message.to_string(),
+        }])
+    }
+}
+
+impl fmt::Display for AppError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AppError::Validation(_) => write!(f, "request validation failed"),
+            AppError::NotFound(resource) => write!(f, "{} not found", resource),
+            AppError::Unavailable(_) => write!(f, "a backend service is unavailable"),
+            AppError::Internal(_) => write!(f, "internal server error"),
+            AppError::BadRequest(message)
+            | AppError::PayloadTooLarge(message)
+            | AppError::Unauthorized(message)
+            | AppError::Forbidden(message)
+            | AppError::Conflict(message) => write!(f, "{}", message),
+        }
+    }
+}
+
+impl ResponseError for AppError {
+    fn status_code(&self) -> StatusCode {
+        match self {
+            AppError::Validation(_) => StatusCode::UNPROCESSABLE_ENTITY,
+            AppError::BadRequest(_) => StatusCode::BAD_REQUEST,
+            AppError::PayloadTooLarge(_) => StatusCode::PAYLOAD_TOO_LARGE,
+            AppError::Unauthorized(_) => StatusCode::UNAUTHORIZED,
+            AppError::Forbidden(_) => StatusCode::FORBIDDEN,
+            AppError::NotFound(_) => StatusCode::NOT_FOUND,
+            AppError::Conflict(_) => StatusCode::CONFLICT,
+            AppError::Unavailable(_) => StatusCode::SERVICE_UNAVAILABLE,
+            AppError::Internal(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    fn error_response(&self) -> HttpResponse {
+        // Internal causes are logged, never sent to the client.
+        if let AppError::Unavailable(cause) | AppError::Internal(cause) = self {
+            error!("{}: {}", self.code(), cause);
+        }
+
+        let details = match self {
+            AppError::Validation(details) => details.as_slice(),
+            _ => &[],
+        };
+
+        HttpResponse::build(self.status_code()).json(ErrorBody {
+            error: ErrorDetail {
+                code: self.code(),
+                message: self.to_string(),
+                details,
+            },
+        })
+    }
+}
+
+impl From<DbErr> for AppError {
+    fn from(e: DbErr) -> Self {
+        if let Some(SqlErr::UniqueConstraintViolation(_)) = e.sql_err() {
+            return AppError::Conflict("resource already exists".to_string());
+        }
+        match e {
+            DbErr::Conn(_) | DbErr::ConnectionAcquire(_) => AppError::Unavailable(e.to_string()),
+            _ => AppError::Internal(e.to_string()),
+        }
+    }
+}
+
+impl From<TransactionError<DbErr>> for AppError {
+    fn from(e: TransactionError<DbErr>) -> Self {
+        match e {
+            TransactionError::Connection(e) | TransactionError::Transaction(e) => e.into(),
+        }
+    }
+}
+
+impl From<bcrypt::BcryptError> for AppError {
+    fn from(e: bcrypt::BcryptError) -> Self {
+        AppError::Internal(format!("bcrypt: {}", e))
+    }
+}
+
+impl From<jsonwebtoken::errors::Error> for AppError {
+    fn from(e: jsonwebtoken::errors::Error) -> Self {
+        AppError::Internal(format!("jwt: {}", e))
+    }
+}
+
+pub fn json_error_handler(err: JsonPayloadError, _req: &HttpRequest) -> actix_web::Error {
+    match err {
+        JsonPayloadError::Overflow { .. } | JsonPayloadError::OverflowKnownLength { .. } => {
+            AppError::PayloadTooLarge(err.to_string()).into()
+        }
+        _ => AppError::BadRequest(format!("invalid JSON body: {}", err)).into(),
+    }
+}
+
+pub fn path_error_handler(err: PathError, _req: &HttpRequest) -> actix_web::Error {
+    AppError::BadRequest(format!("invalid path: {}", err)).into()
+}
+
+pub fn query_error_handler(err: QueryPayloadError, _req: &HttpRequest) -> actix_web::Error {
+    AppError::BadRequest(format!("invalid query string: {}", err)).into()
+}
@@ -0,0 +1,188 @@
+use std::{
+    io::{self, ErrorKind},
+    path::PathBuf,
+    sync::Arc,
+    time::Duration,
+};
+
+use async_trait::async_trait;
+use log::info;
+use tokio::{
+    fs::OpenOptions,
+    io::{AsyncBufReadExt, AsyncWrite, AsyncWriteExt, BufReader},
+    net::TcpStream,
+    time::timeout,
+};
+
+use crate::utility::config::{MailBackend, MailSettings};
+
+/// How long a whole SMTP conversation may take.
+const SMTP_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// A plain text message to one recipient.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Email {
+    pub to: String,
+    pub subject: String,
+    pub body: String,
+}
+
+/// Sends mail. Callers decide whether a failure matters; most log it and
+/// carry on, so an unreachable mail server does not break registration.
+#[async_trait]
+pub trait Mailer: Send + Sync {
+    async fn send(&self, email: &Email) -> io::Result<()>;
+}
+
+/// The mailer chosen by `settings.backend`.
+pub fn from_settings(settings: &MailSettings) -> Arc<dyn Mailer> {
+    match (settings.backend, &settings.file) {
+        (MailBackend::Smtp, _) => Arc::new(SmtpMailer {
+            host: settings.smtp_host.clone(),
+            port: settings.smtp_port,
+            from: settings.from.clone(),
+        }),
+        (MailBackend::File, Some(path)) => Arc::new(FileMailer {
+            path: path.clone(),
+            from: settings.from.clone(),
+        }),
+        (MailBackend::File, None) | (MailBackend::Log, _) => Arc::new(LogMailer),
+    }
+}
+
+/// `email` as an RFC 5322 message with CRLF line endings.
+fn render(from: &str, email: &Email) -> String {
+    let mut message = format!(
+        "From: {}\r\nTo: {}\r\nSubject: {}\r\nMIME-Version: 1.0\r\n\
+         Content-Type: text/plain; charset=utf-8\r\nContent-Transfer-Encoding: 8bit\r\n\r\n",
+        from, email.to, email.subject
+    );
+    for line in email.body.lines() {
+        message.push_str(line);
+        message.push_str("\r\n");
+    }
+    message
+}
+
+/// Delivers to an SMTP server without TLS or authentication, such as a
+/// relay on the same host that forwards mail on.
+pub struct SmtpMailer {
+    pub host: String,
+    pub port: u16,
+    pub from: String,
+}
+
+impl SmtpMailer {
+    async fn deliver(&self, email: &Email) -> io::Result<()> {
+        let stream = TcpStream::connect((self.host.as_str(), self.port)).await?;
+        let (reader, mut writer) = stream.into_split();
+        let mut reader = BufReader::new(reader);
+
+        expect(&mut reader, 220).await?;
+        command(&mut writer, &mut reader, "EHLO localhost", 250).await?;
+        command(
+            &mut writer,
+            &mut reader,
+            &format!("MAIL FROM:<{}>", self.from),
+            250,
+        )
+        .await?;
+        command(
+            &mut writer,
+            &mut reader,
+            &format!("RCPT TO:<{}>", email.to),
+            250,
+        )
+        .await?;
+        command(&mut writer, &mut reader, "DATA", 354).await?;
+
+        // Lines starting with a dot are escaped so they do not end the data.
+        let mut data = String::new();
+        for line in render(&self.from, email).split_terminator("\r\n") {
+            if line.starts_with('.') {
+                data.push('.');
+            }
+            data.push_str(line);
+            data.push_str("\r\n");
+        }
+        data.push_str(".\r\n");
+        writer.write_all(data.as_bytes()).await?;
+        expect(&mut reader, 250).await?;
+
+        command(&mut writer, &mut reader, "QUIT", 221).await
+    }
+}
+
+#[async_trait]
+impl Mailer for SmtpMailer {
+    async fn send(&self, email: &Email) -> io::Result<()> {
+        timeout(SMTP_TIMEOUT, self.deliver(email))
+            .await
+            .map_err(|_| io::Error::new(ErrorKind::TimedOut, "SMTP server timed out"))?
+    }
+}
+
+async fn command<W, R>(writer: &mut W, reader: &mut R, line: &str, code: u16) -> io::Result<()>
+where
+    W: AsyncWrite + Unpin,
+    R: AsyncBufReadExt + Unpin,
+{
+    writer.write_all(format!("{}\r\n", line).as_bytes()).await?;
+    expect(reader, code).await
+}
+
+/// Reads a possibly multi-line reply and checks its code.
+async fn expect<R: AsyncBufReadExt + Unpin>(reader: &mut R, code: u16) -> io::Result<()> {
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line).await? == 0 {
+            return Err(io::Error::new(
+                ErrorKind::UnexpectedEof,
+                "SMTP server closed the connection",
+            ));
+        }
+        let line = line.trim_end();
+        if line.len() > 3 && line.as_bytes()[3] == b'-' {
+            continue;
+        }
+        if line.get(..3).and_then(|reply| reply.parse::<u16>().ok()) != Some(code) {
+            return Err(io::Error::other(format!(
+                "SMTP server answered {:?}, expected {}",
+                line, code
+            )));
+        }
+        return Ok(());
+    }
+}
+
+/// Appends every message to a file, for development and tests.
+pub struct FileMailer {
+    pub path: PathBuf,
+    pub from: String,
+}
+
+#[async_trait]
+impl Mailer for FileMailer {
+    async fn send(&self, email: &Email) -> io::Result<()> {
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .await?;
+        let message = render(&self.from, email).replace("\r\n", "\n");
+        file.write_all(format!("{}\n", message).as_bytes()).await
+    }
+}
+
+/// Logs that a message would have been sent instead of sending it. The body
+/// is left out, since it carries tokens that would let anyone reading the log
+/// into the account.
+pub struct LogMailer;
+
+#[async_trait]
+impl Mailer for LogMailer {
+    async fn send(&self, email: &Email) -> io::Result<()> {
+        info!("mail to {} not sent: {}", email.to, email.subject);
+        Ok(())
+    }
+}
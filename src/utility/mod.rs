@@ -1,3 +1,19 @@
+pub mod cache;
+pub mod clock;
+pub mod config;
+pub mod currency;
 pub mod db_structs;
+pub mod error;
+pub mod mailer;
+pub mod ownership;
+pub mod pagination;
+pub mod period;
+pub mod recurring;
+pub mod scheduler;
+pub mod session;
+pub mod spending;
 pub mod token;
-pub mod redis;
\ No newline at end of file
+pub mod totp;
+pub mod redis;
+pub mod state;
+pub mod validation;
\ No newline at end of file
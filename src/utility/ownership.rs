@@ -0,0 +1,91 @@
+use actix_web::{dev::Payload, web, FromRequest, HttpMessage, HttpRequest};
+use entities::budget;
+use futures::future::LocalBoxFuture;
+use sea_orm::{ColumnTrait, ConnectionTrait, EntityTrait, QueryFilter};
+use serde::Deserialize;
+use uuid::Uuid;
+
+use crate::utility::{
+    cache::{self, CacheKey},
+    error::AppError,
+    state::AppState,
+};
+
+/// The budget named by the `{id}` path segment, loaded on behalf of the
+/// authenticated user. Budgets owned by someone else are indistinguishable
+/// from missing ones: both are rejected with 404 before the handler runs.
+///
+/// Every `/budget/{id}/...` handler takes this instead of the raw path id, so
+/// nested resources only ever need to be filtered by `budget.id`.
+pub struct OwnedBudget(pub budget::Model);
+
+impl OwnedBudget {
+    pub fn into_inner(self) -> budget::Model {
+        self.0
+    }
+}
+
+impl std::ops::Deref for OwnedBudget {
+    type Target = budget::Model;
+
+    fn deref(&self) -> &budget::Model {
+        &self.0
+    }
+}
+
+/// Checks that `budget_id`, if given in the request body or query string
+/// under `field`, names one of the user's budgets.
+pub async fn check_budget_field<C: ConnectionTrait>(
+    db: &C,
+    user_id: Uuid,
+    field: &str,
+    budget_id: Option<Uuid>,
+) -> Result<(), AppError> {
+    let Some(budget_id) = budget_id else {
+        return Ok(());
+    };
+    let budget = budget::Entity::find_by_id(budget_id)
+        .filter(budget::Column::UserId.eq(user_id))
+        .one(db)
+        .await?;
+    if budget.is_none() {
+        return Err(AppError::field(field, "does not name one of your budgets"));
+    }
+    Ok(())
+}
+
+#[derive(Deserialize)]
+struct BudgetPath {
+    id: Uuid,
+}
+
+impl FromRequest for OwnedBudget {
+    type Error = actix_web::Error;
+    type Future = LocalBoxFuture<'static, Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        let state = req.app_data::<web::Data<AppState>>().cloned();
+        let user_id = req.extensions().get::<Uuid>().copied();
+        let path = web::Path::<BudgetPath>::extract(req);
+
+        Box::pin(async move {
+            let state = state.expect("AppState is registered as app data");
+            let user_id = user_id
+                .ok_or_else(|| AppError::Unauthorized("missing bearer token".to_string()))?;
+            let budget_id = path.await?.id;
+
+            let key = CacheKey::budget(&state.settings.cache, user_id, budget_id);
+            let budget = cache::get_or_load(state.cache.as_ref(), &key, || {
+                budget::Entity::find()
+                    .filter(budget::Column::Id.eq(budget_id))
+                    .filter(budget::Column::UserId.eq(user_id))
+                    .one(&state.db)
+            })
+            .await
+            .map_err(AppError::from)?
+            .ok_or(AppError::NotFound("budget"))?;
+
+            Ok(OwnedBudget(budget))
+        })
+    }
+}
@@ -0,0 +1,150 @@
+use actix_web::{http::header, HttpRequest, HttpResponse};
+use sea_orm::{
+    sea_query::LikeExpr, ConnectionTrait, DbErr, EntityTrait, Order, PaginatorTrait, Select,
+};
+use serde::Serialize;
+
+use crate::utility::{db_structs::SortOrder, error::AppError};
+
+pub const DEFAULT_LIMIT: u64 = 50;
+pub const MAX_LIMIT: u64 = 100;
+
+/// A validated `page`/`limit` pair. Pages are numbered from one.
+#[derive(Clone, Copy)]
+pub struct PageRequest {
+    pub page: u64,
+    pub limit: u64,
+}
+
+impl PageRequest {
+    pub fn new(page: Option<u64>, limit: Option<u64>) -> Result<Self, AppError> {
+        let page = page.unwrap_or(1);
+        if page == 0 {
+            return Err(AppError::field("page", "must be at least 1"));
+        }
+        let limit = limit.unwrap_or(DEFAULT_LIMIT);
+        if !(1..=MAX_LIMIT).contains(&limit) {
+            return Err(AppError::field("limit", "must be between 1 and 100"));
+        }
+        // The database offset is a signed 64-bit integer.
+        let offset = (page - 1).checked_mul(limit);
+        if offset.is_none_or(|offset| offset > i64::MAX as u64) {
+            return Err(AppError::field("page", "is too large"));
+        }
+        Ok(PageRequest { page, limit })
+    }
+}
+
+#[derive(Clone, Copy, Debug, Serialize)]
+pub struct PageMeta {
+    pub page: u64,
+    pub limit: u64,
+    pub total_items: u64,
+    pub total_pages: u64,
+}
+
+/// One page of a listing, as returned by the list endpoints.
+#[derive(Serialize)]
+pub struct Paginated<T> {
+    pub data: Vec<T>,
+    pub pagination: PageMeta,
+}
+
+impl<T: Serialize> Paginated<T> {
+    /// Responds with the page as JSON and an RFC 8288 `Link` header pointing
+    /// at the first, previous, next and last pages of the same query.
+    pub fn respond(self, req: &HttpRequest) -> HttpResponse {
+        let mut response = HttpResponse::Ok();
+        response.insert_header((header::LINK, links(req, &self.pagination)));
+        response.json(self)
+    }
+}
+
+/// Runs `query` for the requested page. Pages past the end are empty.
+pub async fn fetch<C, E>(
+    db: &C,
+    query: Select<E>,
+    page: PageRequest,
+) -> Result<(Vec<E::Model>, PageMeta), DbErr>
+where
+    C: ConnectionTrait,
+    E: EntityTrait,
+    E::Model: Sync,
+{
+    let paginator = query.paginate(db, page.limit);
+    let totals = paginator.num_items_and_pages().await?;
+    let items = paginator.fetch_page(page.page - 1).await?;
+    Ok((
+        items,
+        PageMeta {
+            page: page.page,
+            limit: page.limit,
+            total_items: totals.number_of_items,
+            total_pages: totals.number_of_pages,
+        },
+    ))
+}
+
+impl From<SortOrder> for Order {
+    fn from(order: SortOrder) -> Self {
+        match order {
+            SortOrder::Asc => Order::Asc,
+            SortOrder::Desc => Order::Desc,
+        }
+    }
+}
+
+/// Checks that an optional lower bound does not exceed its upper bound.
+pub fn check_range<T: PartialOrd>(
+    min: Option<T>,
+    max: Option<T>,
+    max_field: &'static str,
+    message: &'static str,
+) -> Result<(), AppError> {
+    match (min, max) {
+        (Some(min), Some(max)) if min > max => Err(AppError::field(max_field, message)),
+        _ => Ok(()),
+    }
+}
+
+/// A `LIKE` pattern matching values that contain `needle`, with the
+/// wildcards in `needle` itself escaped.
+pub fn contains(needle: &str) -> LikeExpr {
+    let mut pattern = String::with_capacity(needle.len() + 2);
+    pattern.push('%');
+    for c in needle.chars() {
+        if matches!(c, '%' | '_' | '\\') {
+            pattern.push('\\');
+        }
+        pattern.push(c);
+    }
+    pattern.push('%');
+    LikeExpr::new(pattern).escape('\\')
+}
+
+fn links(req: &HttpRequest, meta: &PageMeta) -> String {
+    // The rest of the query string is kept verbatim, so only `page` changes.
+    let params: Vec<&str> = req
+        .query_string()
+        .split('&')
+        .filter(|p| !p.is_empty() && *p != "page" && !p.starts_with("page="))
+        .collect();
+    let link = |page: u64, rel: &str| {
+        let mut query = params.join("&");
+        if !query.is_empty() {
+            query.push('&');
+        }
+        format!("<{}?{}page={}>; rel=\"{}\"", req.path(), query, page, rel)
+    };
+
+    let last = meta.total_pages.max(1);
+    let mut links = vec![link(1, "first")];
+    if meta.page > 1 {
+        links.push(link((meta.page - 1).min(last), "prev"));
+    }
+    if meta.page < last {
+        links.push(link(meta.page + 1, "next"));
+    }
+    links.push(link(last, "last"));
+    links.join(", ")
+}
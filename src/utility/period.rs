@@ -0,0 +1,139 @@
+use chrono::{Datelike, Days, Months, NaiveDate};
+use entities::{budget, money::Money, sea_orm_active_enums::BudgetPeriod};
+use serde::Serialize;
+
+/// One window of a budget, both ends inclusive.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+pub struct Period {
+    pub start: NaiveDate,
+    pub end: NaiveDate,
+}
+
+/// How a budget fared in one period.
+#[derive(Clone, Debug, Serialize)]
+pub struct PeriodSummary {
+    #[serde(flatten)]
+    pub period: Period,
+    /// The budget's `total_amount`.
+    pub allowance: Money,
+    /// Left over (or, when negative, overspent) from the previous period.
+    /// Always zero for budgets without rollover.
+    pub carried_over: Money,
+    pub available: Money,
+    pub spent: Money,
+    pub remaining: Money,
+}
+
+/// The `n`th period of `budget`, counting from zero. Custom budgets only
+/// have period zero.
+pub fn nth(budget: &budget::Model, n: u32) -> Option<Period> {
+    let start = start_of(budget, n)?;
+    let end = match budget.period {
+        BudgetPeriod::Custom => budget.ends_on.unwrap_or(start),
+        _ => start_of(budget, n + 1)?.pred_opt()?,
+    };
+    Some(Period { start, end })
+}
+
+fn start_of(budget: &budget::Model, n: u32) -> Option<NaiveDate> {
+    let starts_on = budget.starts_on;
+    // Months are always added to `starts_on` itself so that a budget starting
+    // on the 31st keeps returning to the 31st after shorter months.
+    match budget.period {
+        BudgetPeriod::Weekly => starts_on.checked_add_days(Days::new(7 * u64::from(n))),
+        BudgetPeriod::Monthly => starts_on.checked_add_months(Months::new(n)),
+        BudgetPeriod::Quarterly => starts_on.checked_add_months(Months::new(n.checked_mul(3)?)),
+        BudgetPeriod::Yearly => starts_on.checked_add_months(Months::new(n.checked_mul(12)?)),
+        BudgetPeriod::Custom => (n == 0).then_some(starts_on),
+    }
+}
+
+/// The calendar period of `kind` containing `date`: a week from Monday to
+/// Sunday, a month, a quarter or a year. Custom periods have no calendar
+/// counterpart.
+pub fn calendar(kind: BudgetPeriod, date: NaiveDate) -> Option<Period> {
+    let (start, months) = match kind {
+        BudgetPeriod::Weekly => {
+            let start =
+                date.checked_sub_days(Days::new(date.weekday().num_days_from_monday().into()))?;
+            let end = start.checked_add_days(Days::new(6))?;
+            return Some(Period { start, end });
+        }
+        BudgetPeriod::Monthly => (date.with_day(1)?, 1),
+        BudgetPeriod::Quarterly => (
+            NaiveDate::from_ymd_opt(date.year(), date.month0() / 3 * 3 + 1, 1)?,
+            3,
+        ),
+        BudgetPeriod::Yearly => (date.with_ordinal(1)?, 12),
+        BudgetPeriod::Custom => return None,
+    };
+    let end = start.checked_add_months(Months::new(months))?.pred_opt()?;
+    Some(Period { start, end })
+}
+
+/// Index of the period containing `date`. Dates before the first period map
+/// to it, as do all dates for custom budgets.
+pub fn index_of(budget: &budget::Model, date: NaiveDate) -> u32 {
+    let starts_on = budget.starts_on;
+    if date <= starts_on {
+        return 0;
+    }
+    let months =
+        (date.year() - starts_on.year()) * 12 + date.month() as i32 - starts_on.month() as i32;
+    let estimate = match budget.period {
+        BudgetPeriod::Weekly => (date - starts_on).num_days() / 7,
+        BudgetPeriod::Monthly => i64::from(months),
+        BudgetPeriod::Quarterly => i64::from(months / 3),
+        BudgetPeriod::Yearly => i64::from(months / 12),
+        BudgetPeriod::Custom => return 0,
+    };
+    let mut n = u32::try_from(estimate.max(0)).unwrap_or(u32::MAX);
+    while n > 0 && start_of(budget, n).is_none_or(|start| start > date) {
+        n -= 1;
+    }
+    while start_of(budget, n + 1).is_some_and(|start| start <= date) {
+        n += 1;
+    }
+    n
+}
+
+/// Summaries of periods `0..=last`, oldest first. `spending` holds the total
+/// spent per expense date, sorted by date.
+pub fn history(
+    budget: &budget::Model,
+    spending: &[(NaiveDate, Money)],
+    last: u32,
+) -> Vec<PeriodSummary> {
+    let mut summaries = Vec::new();
+    let mut carried_over = Money::ZERO;
+    let mut spending = spending.iter().peekable();
+
+    for n in 0..=last {
+        let Some(period) = nth(budget, n) else {
+            break;
+        };
+        // Expenses dated before the budget started do not count.
+        while spending.next_if(|(date, _)| *date < period.start).is_some() {}
+        let mut spent = Money::ZERO;
+        while let Some((_, amount)) = spending.next_if(|(date, _)| *date <= period.end) {
+            spent += *amount;
+        }
+
+        let available = budget.total_amount + carried_over;
+        let remaining = available - spent;
+        summaries.push(PeriodSummary {
+            period,
+            allowance: budget.total_amount,
+            carried_over,
+            available,
+            spent,
+            remaining,
+        });
+        carried_over = if budget.rollover {
+            remaining
+        } else {
+            Money::ZERO
+        };
+    }
+    summaries
+}
@@ -0,0 +1,125 @@
+use chrono::{DateTime, Days, Months, NaiveDate, Utc};
+use entities::{expense, recurring_expense, sea_orm_active_enums::Frequency};
+use sea_orm::{
+    entity::*, sea_query::Expr, ConnectionTrait, DbErr, QueryFilter, QueryOrder, TransactionTrait,
+};
+use uuid::Uuid;
+
+/// Most occurrences of one template created in a single pass, so that a
+/// daily template left alone for years cannot stall the scheduler. The rest
+/// are picked up by the following passes.
+const MAX_CATCH_UP: u32 = 400;
+
+/// Date of occurrence `n` of `template`, counting from zero, or `None` if
+/// the template ends before it.
+pub fn nth(template: &recurring_expense::Model, n: u32) -> Option<NaiveDate> {
+    if template
+        .count
+        .is_some_and(|count| i64::from(n) >= i64::from(count))
+    {
+        return None;
+    }
+    let steps = n.checked_mul(u32::try_from(template.interval).ok()?)?;
+    let starts_on = template.starts_on;
+    // Always counted from `starts_on` so that a template on the 31st comes
+    // back to the 31st after shorter months.
+    let date = match template.frequency {
+        Frequency::Daily => starts_on.checked_add_days(Days::new(u64::from(steps))),
+        Frequency::Weekly => starts_on.checked_add_days(Days::new(7 * u64::from(steps))),
+        Frequency::Monthly => starts_on.checked_add_months(Months::new(steps)),
+        Frequency::Yearly => starts_on.checked_add_months(Months::new(steps.checked_mul(12)?)),
+    }?;
+    template
+        .ends_on
+        .is_none_or(|ends_on| date <= ends_on)
+        .then_some(date)
+}
+
+/// Creates an expense for every occurrence of `template` due on or before
+/// `today` that has not been created yet, and returns how many were created.
+///
+/// Safe to run concurrently and repeatedly: the template's occurrence counter
+/// is advanced with a compare-and-set in the same transaction as the inserts,
+/// so each occurrence is created exactly once.
+pub async fn materialize<C: TransactionTrait>(
+    db: &C,
+    template: &recurring_expense::Model,
+    today: NaiveDate,
+    now: DateTime<Utc>,
+) -> Result<u32, DbErr> {
+    let done = u32::try_from(template.occurrences).unwrap_or(0);
+    let mut due = Vec::new();
+    let mut next = nth(template, done);
+    while let Some(date) = next.filter(|date| *date <= today) {
+        if due.len() as u32 == MAX_CATCH_UP {
+            break;
+        }
+        due.push(date);
+        next = nth(template, done + due.len() as u32);
+    }
+    if due.is_empty() {
+        return Ok(0);
+    }
+
+    let template = template.clone();
+    db.transaction::<_, u32, DbErr>(|txn| {
+        Box::pin(async move {
+            let created = due.len() as u32;
+            let claimed = recurring_expense::Entity::update_many()
+                .col_expr(
+                    recurring_expense::Column::Occurrences,
+                    Expr::value(template.occurrences + created as i32),
+                )
+                .col_expr(recurring_expense::Column::NextOccurrence, Expr::value(next))
+                .col_expr(recurring_expense::Column::UpdatedAt, Expr::value(now))
+                .filter(recurring_expense::Column::Id.eq(template.id))
+                .filter(recurring_expense::Column::Occurrences.eq(template.occurrences))
+                .exec(txn)
+                .await?;
+            if claimed.rows_affected == 0 {
+                // Someone else got there first.
+                return Ok(0);
+            }
+
+            let expenses = due.into_iter().map(|date| expense::ActiveModel {
+                id: Set(Uuid::new_v4()),
+                budget_id: Set(template.budget_id),
+                amount: Set(template.amount),
+                currency: Set(template.currency.clone()),
+                description: Set(template.description.clone()),
+                date: Set(date),
+                category_id: Set(template.category_id),
+                recurring_expense_id: Set(Some(template.id)),
+                account_id: Set(None),
+                created_at: Set(now),
+                updated_at: Set(now),
+            });
+            expense::Entity::insert_many(expenses).exec(txn).await?;
+            Ok(created)
+        })
+    })
+    .await
+    .map_err(|e| match e {
+        sea_orm::TransactionError::Connection(e) | sea_orm::TransactionError::Transaction(e) => e,
+    })
+}
+
+/// Materializes every template with an occurrence due on or before `today`,
+/// catching up on anything missed while the server was down. Returns the
+/// number of expenses created.
+pub async fn materialize_due<C>(db: &C, today: NaiveDate, now: DateTime<Utc>) -> Result<u32, DbErr>
+where
+    C: ConnectionTrait + TransactionTrait,
+{
+    let templates = recurring_expense::Entity::find()
+        .filter(recurring_expense::Column::NextOccurrence.lte(today))
+        .order_by_asc(recurring_expense::Column::NextOccurrence)
+        .all(db)
+        .await?;
+
+    let mut created = 0;
+    for template in &templates {
+        created += materialize(db, template, today, now).await?;
+    }
+    Ok(created)
+}
@@ -1,6 +1,100 @@
-use redis::{Client, Connection};
+use std::time::{Duration, Instant};
 
-pub fn get_redis_connection() -> Connection {
-    let client = Client::open("redis://127.0.0.1").expect("Invalid Redis URL");
-    client.get_connection().expect("Failed to connect Redis")
-}
\ No newline at end of file
+use async_trait::async_trait;
+use log::warn;
+use redis::{
+    aio::{ConnectionManager, ConnectionManagerConfig},
+    AsyncCommands, Client, RedisResult,
+};
+use tokio::sync::Mutex;
+
+use crate::utility::{cache::Cache, config::RedisSettings};
+
+/// A lazily connected, multiplexed Redis connection shared by every worker.
+///
+/// Redis is only used as a cache, so when it cannot be reached the pool hands
+/// out `None` and callers fall through to the database. After a failed attempt
+/// the pool waits `retry_interval_secs` before dialling again, so an outage
+/// costs one connect timeout per interval rather than one per request.
+pub struct RedisPool {
+    client: Client,
+    connect_timeout: Duration,
+    response_timeout: Duration,
+    retry_interval: Duration,
+    state: Mutex<PoolState>,
+}
+
+#[derive(Default)]
+struct PoolState {
+    manager: Option<ConnectionManager>,
+    retry_at: Option<Instant>,
+}
+
+impl RedisPool {
+    pub fn new(settings: &RedisSettings) -> RedisResult<Self> {
+        Ok(RedisPool {
+            client: Client::open(settings.url.as_str())?,
+            connect_timeout: Duration::from_millis(settings.connect_timeout_ms),
+            response_timeout: Duration::from_millis(settings.response_timeout_ms),
+            retry_interval: Duration::from_secs(settings.retry_interval_secs),
+            state: Mutex::new(PoolState::default()),
+        })
+    }
+
+    /// Returns a handle to the shared connection, or `None` while the cache
+    /// is unavailable.
+    pub async fn connection(&self) -> Option<ConnectionManager> {
+        let mut state = self.state.lock().await;
+
+        if let Some(manager) = &state.manager {
+            return Some(manager.clone());
+        }
+        if matches!(state.retry_at, Some(retry_at) if Instant::now() < retry_at) {
+            return None;
+        }
+
+        let config = ConnectionManagerConfig::new()
+            .set_number_of_retries(1)
+            .set_connection_timeout(self.connect_timeout)
+            .set_response_timeout(self.response_timeout);
+        let connect = ConnectionManager::new_with_config(self.client.clone(), config);
+
+        match tokio::time::timeout(self.connect_timeout, connect).await {
+            Ok(Ok(manager)) => {
+                state.manager = Some(manager.clone());
+                state.retry_at = None;
+                Some(manager)
+            }
+            Ok(Err(e)) => {
+                warn!("cache unavailable, falling through to the database: {}", e);
+                state.retry_at = Some(Instant::now() + self.retry_interval);
+                None
+            }
+            Err(_) => {
+                warn!("cache unavailable, falling through to the database: connect timed out");
+                state.retry_at = Some(Instant::now() + self.retry_interval);
+                None
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl Cache for RedisPool {
+    async fn get(&self, key: &str) -> Option<String> {
+        let mut conn = self.connection().await?;
+        conn.get(key).await.ok()?
+    }
+
+    async fn set(&self, key: &str, value: String, ttl_secs: u64) {
+        if let Some(mut conn) = self.connection().await {
+            let _: RedisResult<()> = conn.set_ex(key, value, ttl_secs).await;
+        }
+    }
+
+    async fn delete(&self, key: &str) {
+        if let Some(mut conn) = self.connection().await {
+            let _: RedisResult<()> = conn.del(key).await;
+        }
+    }
+}
@@ -0,0 +1,30 @@
+use std::time::Duration;
+
+use actix_web::{rt, web};
+use log::{error, info};
+
+use crate::utility::{recurring, state::AppState};
+
+/// Starts the background jobs on the current actix runtime. The first pass
+/// runs right away, so anything that came due while the server was down is
+/// caught up on startup.
+pub fn spawn(state: web::Data<AppState>) -> rt::task::JoinHandle<()> {
+    let every = Duration::from_secs(state.settings.scheduler.interval_secs);
+    rt::spawn(async move {
+        let mut ticks = rt::time::interval(every);
+        loop {
+            ticks.tick().await;
+            run_once(&state).await;
+        }
+    })
+}
+
+/// One pass of every job. Failures are logged and retried on the next pass.
+pub async fn run_once(state: &AppState) {
+    let now = state.clock.now();
+    match recurring::materialize_due(&state.db, now.date_naive(), now).await {
+        Ok(0) => {}
+        Ok(created) => info!("created {} expenses from recurring templates", created),
+        Err(e) => error!("cannot create recurring expenses: {}", e),
+    }
+}
@@ -0,0 +1,106 @@
+use chrono::{DateTime, Duration, Utc};
+use entities::refresh_token;
+use sea_orm::{entity::*, sea_query::Expr, ConnectionTrait, DbErr, QueryFilter};
+use serde::Serialize;
+use uuid::Uuid;
+
+use crate::utility::{
+    config::JwtSettings,
+    error::AppError,
+    token::{hash_token, random_token, sign_jwt},
+};
+
+/// What login and refresh hand back to the client.
+#[derive(Serialize)]
+pub struct TokenPair {
+    token_type: &'static str,
+    access_token: String,
+    access_token_expires_at: DateTime<Utc>,
+    /// Seconds until the access token expires.
+    expires_in: i64,
+    refresh_token: String,
+    refresh_token_expires_at: DateTime<Utc>,
+}
+
+/// Signs an access token for `user_id` and stores a new refresh token in
+/// `family_id`. The access token's `jti` is the id of the refresh token, so
+/// revoking one revokes the other.
+pub async fn issue_tokens<C: ConnectionTrait>(
+    db: &C,
+    settings: &JwtSettings,
+    now: DateTime<Utc>,
+    user_id: Uuid,
+    family_id: Uuid,
+) -> Result<TokenPair, AppError> {
+    let id = Uuid::new_v4();
+    let access_token_expires_at = now + Duration::seconds(settings.expiry_secs);
+    let access_token = sign_jwt(user_id, id, access_token_expires_at, settings)?;
+
+    let refresh_token = random_token();
+    let refresh_token_expires_at = now + Duration::seconds(settings.refresh_expiry_secs);
+    refresh_token::ActiveModel {
+        id: Set(id),
+        user_id: Set(user_id),
+        family_id: Set(family_id),
+        token_hash: Set(hash_token(&refresh_token)),
+        expires_at: Set(refresh_token_expires_at),
+        used_at: Set(None),
+        revoked_at: Set(None),
+        created_at: Set(now),
+    }
+    .insert(db)
+    .await?;
+
+    Ok(TokenPair {
+        token_type: "Bearer",
+        access_token,
+        access_token_expires_at,
+        expires_in: settings.expiry_secs,
+        refresh_token,
+        refresh_token_expires_at,
+    })
+}
+
+/// Whether the access token `jti` of `user_id` has not been revoked.
+pub async fn is_active<C: ConnectionTrait>(
+    db: &C,
+    user_id: Uuid,
+    jti: Uuid,
+) -> Result<bool, DbErr> {
+    let token = refresh_token::Entity::find_by_id(jti)
+        .filter(refresh_token::Column::UserId.eq(user_id))
+        .one(db)
+        .await?;
+    Ok(token.is_some_and(|token| token.revoked_at.is_none()))
+}
+
+/// Revokes every token in `family_id`, that is everything issued from one
+/// login.
+pub async fn revoke_family<C: ConnectionTrait>(
+    db: &C,
+    family_id: Uuid,
+    now: DateTime<Utc>,
+) -> Result<(), DbErr> {
+    refresh_token::Entity::update_many()
+        .col_expr(refresh_token::Column::RevokedAt, Expr::value(now))
+        .filter(refresh_token::Column::FamilyId.eq(family_id))
+        .filter(refresh_token::Column::RevokedAt.is_null())
+        .exec(db)
+        .await?;
+    Ok(())
+}
+
+/// Revokes every token of `user_id`, logging them out everywhere.
+pub async fn revoke_user<C: ConnectionTrait>(
+    db: &C,
+    user_id: Uuid,
+    now: DateTime<Utc>,
+) -> Result<(), DbErr> {
+    refresh_token::Entity::update_many()
+        .col_expr(refresh_token::Column::RevokedAt, Expr::value(now))
+        .filter(refresh_token::Column::UserId.eq(user_id))
+        .filter(refresh_token::Column::RevokedAt.is_null())
+        .exec(db)
+        .await?;
+    Ok(())
+}
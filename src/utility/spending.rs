@@ -0,0 +1,65 @@
+use chrono::NaiveDate;
+use entities::{expense, expense_split, money::Money};
+use sea_orm::{
+    sea_query::Query, ColumnTrait, ConnectionTrait, DbErr, EntityTrait, JoinType, QueryFilter,
+    QuerySelect, QueryTrait, RelationTrait,
+};
+use uuid::Uuid;
+
+/// Spending charged to a budget, summed per category, currency and day.
+pub type Spending = Vec<(Option<Uuid>, String, NaiveDate, Money)>;
+
+/// What was spent against `budget_id` between `from` and `to`, both
+/// inclusive. A split expense counts through its lines, each towards its own
+/// budget and category, rather than in full towards the budget it was
+/// recorded in.
+pub async fn budget_spending<C: ConnectionTrait>(
+    db: &C,
+    budget_id: Uuid,
+    from: Option<NaiveDate>,
+    to: Option<NaiveDate>,
+) -> Result<Spending, DbErr> {
+    let mut whole: Spending = expense::Entity::find()
+        .select_only()
+        .column(expense::Column::CategoryId)
+        .column(expense::Column::Currency)
+        .column(expense::Column::Date)
+        .column_as(expense::Column::Amount.sum(), "spent")
+        .filter(expense::Column::BudgetId.eq(budget_id))
+        .filter(
+            expense::Column::Id.not_in_subquery(
+                Query::select()
+                    .column(expense_split::Column::ExpenseId)
+                    .from(expense_split::Entity)
+                    .to_owned(),
+            ),
+        )
+        .apply_if(from, |q, from| q.filter(expense::Column::Date.gte(from)))
+        .apply_if(to, |q, to| q.filter(expense::Column::Date.lte(to)))
+        .group_by(expense::Column::CategoryId)
+        .group_by(expense::Column::Currency)
+        .group_by(expense::Column::Date)
+        .into_tuple()
+        .all(db)
+        .await?;
+
+    let split: Spending = expense_split::Entity::find()
+        .select_only()
+        .column(expense_split::Column::CategoryId)
+        .column(expense::Column::Currency)
+        .column(expense::Column::Date)
+        .column_as(expense_split::Column::Amount.sum(), "spent")
+        .join(JoinType::InnerJoin, expense_split::Relation::Expense.def())
+        .filter(expense_split::Column::BudgetId.eq(budget_id))
+        .apply_if(from, |q, from| q.filter(expense::Column::Date.gte(from)))
+        .apply_if(to, |q, to| q.filter(expense::Column::Date.lte(to)))
+        .group_by(expense_split::Column::CategoryId)
+        .group_by(expense::Column::Currency)
+        .group_by(expense::Column::Date)
+        .into_tuple()
+        .all(db)
+        .await?;
+
+    whole.extend(split);
+    Ok(whole)
+}
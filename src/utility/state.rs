@@ -0,0 +1,52 @@
+use std::{num::NonZeroUsize, sync::Arc};
+
+use redis::RedisResult;
+use sea_orm::DatabaseConnection;
+
+use crate::utility::{
+    cache::{Cache, MemoryCache, NoopCache},
+    clock::{Clock, SystemClock},
+    config::{CacheBackend, Settings},
+    mailer::{self, Mailer},
+    redis::RedisPool,
+};
+
+/// Everything a handler needs, built once in `main` and shared by every
+/// worker through `web::Data<AppState>`.
+pub struct AppState {
+    pub db: DatabaseConnection,
+    pub cache: Arc<dyn Cache>,
+    pub settings: Settings,
+    pub clock: Arc<dyn Clock>,
+    pub mailer: Arc<dyn Mailer>,
+}
+
+impl AppState {
+    pub fn new(db: DatabaseConnection, settings: Settings) -> RedisResult<Self> {
+        Self::with_clock(db, settings, Arc::new(SystemClock))
+    }
+
+    pub fn with_clock(
+        db: DatabaseConnection,
+        settings: Settings,
+        clock: Arc<dyn Clock>,
+    ) -> RedisResult<Self> {
+        let cache: Arc<dyn Cache> = match settings.cache.backend {
+            CacheBackend::Redis => Arc::new(RedisPool::new(&settings.redis)?),
+            CacheBackend::Memory => Arc::new(MemoryCache::new(
+                NonZeroUsize::new(settings.cache.memory_capacity).unwrap_or(NonZeroUsize::MIN),
+            )),
+            CacheBackend::None => Arc::new(NoopCache),
+        };
+
+        let mailer = mailer::from_settings(&settings.mail);
+
+        Ok(AppState {
+            db,
+            cache,
+            settings,
+            clock,
+            mailer,
+        })
+    }
+}
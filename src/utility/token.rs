@@ -1,45 +1,80 @@
-use std::env;
-
-use chrono;
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use chrono::{DateTime, Utc};
 use jsonwebtoken::{
-    decode, encode, errors::Result as JwtResult, DecodingKey, EncodingKey, Header, TokenData,
-    Validation,
+    decode, encode,
+    errors::{ErrorKind, Result as JwtResult},
+    DecodingKey, EncodingKey, Header, TokenData, Validation,
 };
+use rand::RngCore;
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
 use uuid::Uuid;
 
+use crate::utility::config::JwtSettings;
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Claims {
     pub sub: Uuid,
+    /// Identifies the token so it can be revoked before it expires.
+    pub jti: Uuid,
     pub exp: usize,
 }
 
-pub fn sign_jwt(id: Uuid) -> JwtResult<String> {
-    let expiration = chrono::Utc::now()
-        .checked_add_signed(chrono::Duration::days(1))
-        .expect("Valid Timestamp")
-        .timestamp() as usize;
-
+/// Signs access token `jti` for user `id` that is valid until `expires_at`.
+pub fn sign_jwt(
+    id: Uuid,
+    jti: Uuid,
+    expires_at: DateTime<Utc>,
+    settings: &JwtSettings,
+) -> JwtResult<String> {
     let claims = Claims {
         sub: id,
-        exp: expiration,
+        jti,
+        exp: expires_at.timestamp() as usize,
     };
 
     encode(
         &Header::default(),
         &claims,
-        &EncodingKey::from_secret(get_secret().as_ref()),
+        &EncodingKey::from_secret(settings.secret.as_ref()),
     )
 }
 
-pub fn decode_jwt(token: String) -> JwtResult<TokenData<Claims>> {
-    decode::<Claims>(
-        &token,
-        &DecodingKey::from_secret(get_secret().as_ref()),
-        &Validation::default(),
-    )
+/// Checks the signature of `token` and that it has not expired by `now`.
+pub fn decode_jwt(
+    token: &str,
+    now: DateTime<Utc>,
+    settings: &JwtSettings,
+) -> JwtResult<TokenData<Claims>> {
+    // Expiry is checked against the app's clock rather than the system's.
+    let mut validation = Validation::default();
+    validation.validate_exp = false;
+
+    let data = decode::<Claims>(
+        token,
+        &DecodingKey::from_secret(settings.secret.as_ref()),
+        &validation,
+    )?;
+    if data.claims.exp as i64 <= now.timestamp() {
+        return Err(ErrorKind::ExpiredSignature.into());
+    }
+    Ok(data)
+}
+
+/// A new random opaque token, such as a refresh token.
+pub fn random_token() -> String {
+    let mut bytes = [0u8; 32];
+    rand::thread_rng().fill_bytes(&mut bytes);
+    URL_SAFE_NO_PAD.encode(bytes)
+}
+
+/// A new API key. The prefix makes keys easy to recognize, e.g. by secret
+/// scanners.
+pub fn new_api_key() -> String {
+    format!("pbk_{}", random_token())
 }
 
-pub fn get_secret() -> String {
-    return env::var("JWT_SECRET").expect("JWT_SECRET must be set");
+/// The hash an opaque token is stored and looked up by.
+pub fn hash_token(token: &str) -> String {
+    format!("{:x}", Sha256::digest(token.as_bytes()))
 }
@@ -0,0 +1,127 @@
+use chrono::{DateTime, Utc};
+use hmac::{Hmac, Mac};
+use rand::RngCore;
+use sha1::Sha1;
+
+const DIGITS: u32 = 6;
+const STEP_SECS: i64 = 30;
+/// Steps before and after the current one that are still accepted, for
+/// clocks that are slightly off.
+const SKEW: i64 = 1;
+const SECRET_BYTES: usize = 20;
+const ISSUER: &str = "pbudget";
+const BASE32: &[u8; 32] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ234567";
+
+/// A new random secret, base32 encoded like authenticator apps expect.
+pub fn new_secret() -> String {
+    let mut bytes = [0u8; SECRET_BYTES];
+    rand::thread_rng().fill_bytes(&mut bytes);
+    base32_encode(&bytes)
+}
+
+/// A new recovery code such as `k3x7q-p2mzd`.
+pub fn new_recovery_code() -> String {
+    let mut bytes = [0u8; 7];
+    rand::thread_rng().fill_bytes(&mut bytes);
+    let code = base32_encode(&bytes).to_ascii_lowercase();
+    format!("{}-{}", &code[..5], &code[5..10])
+}
+
+/// A recovery code as typed by the user, in the form it is hashed in.
+pub fn normalize_recovery_code(code: &str) -> String {
+    code.chars()
+        .filter(|c| c.is_ascii_alphanumeric())
+        .map(|c| c.to_ascii_lowercase())
+        .collect()
+}
+
+/// The `otpauth://` URI to show as a QR code when enrolling `username`.
+/// Usernames only contain characters that need no escaping.
+pub fn otpauth_uri(secret: &str, username: &str) -> String {
+    format!(
+        "otpauth://totp/{issuer}:{username}?secret={secret}&issuer={issuer}&algorithm=SHA1&digits={DIGITS}&period={STEP_SECS}",
+        issuer = ISSUER,
+    )
+}
+
+/// The code for `secret` at `at`, or `None` if the secret is not base32.
+pub fn code(secret: &str, at: DateTime<Utc>) -> Option<String> {
+    let key = base32_decode(secret)?;
+    Some(format!(
+        "{:0width$}",
+        hotp(&key, step(at)),
+        width = DIGITS as usize
+    ))
+}
+
+/// The time step at which the RFC 6238 `code` is valid for `secret` around
+/// `now`. Steps up to and including `used_step` are refused so a code works
+/// only once.
+pub fn verify(secret: &str, code: &str, now: DateTime<Utc>, used_step: Option<i64>) -> Option<i64> {
+    if code.len() != DIGITS as usize || !code.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    let code: u32 = code.parse().ok()?;
+    let key = base32_decode(secret)?;
+    let current = step(now);
+
+    (current - SKEW..=current + SKEW)
+        .filter(|&step| used_step.is_none_or(|used| step > used))
+        .find(|&step| hotp(&key, step) == code)
+}
+
+fn step(at: DateTime<Utc>) -> i64 {
+    at.timestamp().div_euclid(STEP_SECS)
+}
+
+/// HOTP (RFC 4226) for `counter`.
+fn hotp(key: &[u8], counter: i64) -> u32 {
+    let mut mac = Hmac::<Sha1>::new_from_slice(key).expect("HMAC takes keys of any length");
+    mac.update(&counter.to_be_bytes());
+    let hash = mac.finalize().into_bytes();
+
+    let offset = usize::from(hash[hash.len() - 1] & 0x0f);
+    let truncated = u32::from_be_bytes([
+        hash[offset] & 0x7f,
+        hash[offset + 1],
+        hash[offset + 2],
+        hash[offset + 3],
+    ]);
+    truncated % 10u32.pow(DIGITS)
+}
+
+/// RFC 4648 base32 without padding.
+fn base32_encode(bytes: &[u8]) -> String {
+    let mut out = String::with_capacity(bytes.len().div_ceil(5) * 8);
+    let mut buffer: u32 = 0;
+    let mut bits = 0;
+    for &byte in bytes {
+        buffer = (buffer << 8) | u32::from(byte);
+        bits += 8;
+        while bits >= 5 {
+            bits -= 5;
+            out.push(BASE32[((buffer >> bits) & 0x1f) as usize] as char);
+        }
+    }
+    if bits > 0 {
+        out.push(BASE32[((buffer << (5 - bits)) & 0x1f) as usize] as char);
+    }
+    out
+}
+
+/// Decodes base32, ignoring case, padding and spaces.
+fn base32_decode(s: &str) -> Option<Vec<u8>> {
+    let mut out = Vec::with_capacity(s.len() * 5 / 8);
+    let mut buffer: u32 = 0;
+    let mut bits = 0;
+    for c in s.bytes().filter(|&c| c != b'=' && c != b' ') {
+        let value = BASE32.iter().position(|&b| b == c.to_ascii_uppercase())?;
+        buffer = (buffer << 5) | value as u32;
+        bits += 5;
+        if bits >= 8 {
+            bits -= 8;
+            out.push((buffer >> bits) as u8);
+        }
+    }
+    Some(out)
+}
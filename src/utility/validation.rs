@@ -0,0 +1,179 @@
+use std::borrow::Cow;
+
+use actix_web::{dev::Payload, web, FromRequest, HttpRequest};
+use entities::money::Money;
+use futures::future::LocalBoxFuture;
+use serde::de::DeserializeOwned;
+use validator::{Validate, ValidationError, ValidationErrors};
+
+use crate::utility::{
+    currency,
+    db_structs::SplitLine,
+    error::{AppError, FieldError},
+};
+
+/// Largest amount accepted for budgets and expenses.
+const MAX_AMOUNT: Money = Money::from_minor_units(100_000_000_000_000);
+
+/// `web::Json` that also runs the payload's `Validate` rules, rejecting the
+/// request with 422 and one entry per offending field before the handler runs.
+pub struct ValidatedJson<T>(pub T);
+
+impl<T> ValidatedJson<T> {
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+impl<T> std::ops::Deref for ValidatedJson<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.0
+    }
+}
+
+impl<T> FromRequest for ValidatedJson<T>
+where
+    T: DeserializeOwned + Validate + 'static,
+{
+    type Error = actix_web::Error;
+    type Future = LocalBoxFuture<'static, Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, payload: &mut Payload) -> Self::Future {
+        let json = web::Json::<T>::from_request(req, payload);
+
+        Box::pin(async move {
+            let value = json.await?.into_inner();
+            value
+                .validate()
+                .map_err(|e| AppError::Validation(field_errors(&e)))?;
+            Ok(ValidatedJson(value))
+        })
+    }
+}
+
+fn field_errors(errors: &ValidationErrors) -> Vec<FieldError> {
+    let mut fields: Vec<FieldError> = errors
+        .field_errors()
+        .into_iter()
+        .flat_map(|(field, errors)| {
+            errors.iter().map(move |e| FieldError {
+                field: field.to_string(),
+                message: e
+                    .message
+                    .as_ref()
+                    .map(|m| m.to_string())
+                    .unwrap_or_else(|| format!("is invalid ({})", e.code)),
+            })
+        })
+        .collect();
+    fields.sort_by(|a, b| a.field.cmp(&b.field));
+    fields
+}
+
+fn invalid(code: &'static str, message: &'static str) -> ValidationError {
+    let mut error = ValidationError::new(code);
+    error.message = Some(Cow::Borrowed(message));
+    error
+}
+
+pub fn validate_amount(amount: &Money) -> Result<(), ValidationError> {
+    if !amount.is_positive() {
+        return Err(invalid("amount", "must be a positive number"));
+    }
+    if *amount > MAX_AMOUNT {
+        return Err(invalid("amount", "is too large"));
+    }
+    Ok(())
+}
+
+/// Balances may also be zero or negative, e.g. money owed on a credit card.
+pub fn validate_balance(balance: &Money) -> Result<(), ValidationError> {
+    if *balance > MAX_AMOUNT || *balance < -MAX_AMOUNT {
+        return Err(invalid("balance", "is too large"));
+    }
+    Ok(())
+}
+
+pub fn validate_not_blank(value: &str) -> Result<(), ValidationError> {
+    if value.trim().is_empty() {
+        return Err(invalid("blank", "must not be blank"));
+    }
+    Ok(())
+}
+
+pub fn validate_username(username: &str) -> Result<(), ValidationError> {
+    if !username
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '_' | '-'))
+    {
+        return Err(invalid(
+            "username",
+            "may only contain letters, digits, '.', '_' and '-'",
+        ));
+    }
+    Ok(())
+}
+
+pub fn validate_currency(code: &str) -> Result<(), ValidationError> {
+    if !currency::is_currency(code) {
+        return Err(invalid(
+            "currency",
+            "must be an ISO 4217 currency code such as \"EUR\"",
+        ));
+    }
+    Ok(())
+}
+
+/// Tags are short labels such as `reimbursable` or `trip-berlin`.
+pub fn validate_tag(tag: &str) -> Result<(), ValidationError> {
+    if tag.is_empty() || tag.chars().count() > 50 {
+        return Err(invalid("tag", "must be 1 to 50 characters long"));
+    }
+    if !tag
+        .chars()
+        .all(|c| c.is_alphanumeric() || matches!(c, '.' | '_' | '-' | ':'))
+    {
+        return Err(invalid(
+            "tag",
+            "may only contain letters, digits, '.', '_', '-' and ':'",
+        ));
+    }
+    Ok(())
+}
+
+pub fn validate_tags(tags: &[String]) -> Result<(), ValidationError> {
+    if tags.len() > 20 {
+        return Err(invalid("tags", "must have at most 20 tags"));
+    }
+    tags.iter().try_for_each(|tag| validate_tag(tag))
+}
+
+/// Every line of a split expense needs a valid amount; whether they add up
+/// is checked against the expense itself.
+pub fn validate_splits(splits: &[SplitLine]) -> Result<(), ValidationError> {
+    if splits.len() > 50 {
+        return Err(invalid("splits", "must have at most 50 lines"));
+    }
+    splits
+        .iter()
+        .try_for_each(|split| validate_amount(&split.amount))
+}
+
+/// At least 8 characters (bcrypt ignores anything past 72 bytes), mixing
+/// letters with digits or symbols.
+pub fn validate_password(password: &str) -> Result<(), ValidationError> {
+    if password.chars().count() < 8 || password.len() > 72 {
+        return Err(invalid("password", "must be 8 to 72 characters long"));
+    }
+    let has_letter = password.chars().any(char::is_alphabetic);
+    let has_other = password.chars().any(|c| !c.is_alphabetic());
+    if !has_letter || !has_other {
+        return Err(invalid(
+            "password",
+            "must contain letters and at least one digit, space or symbol",
+        ));
+    }
+    Ok(())
+}
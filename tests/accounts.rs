@@ -0,0 +1,229 @@
+mod common;
+
+use actix_web::{http::StatusCode, test, App};
+use pbudget::{handler, utility::currency};
+use serde_json::{json, Value};
+
+use common::{authed, login, test_state};
+
+#[actix_web::test]
+async fn balances_follow_expenses_income_and_transfers() {
+    let state = test_state().await;
+    let rates = currency::parse_csv("EUR,USD,2024-07-01,1.1\n").unwrap();
+    currency::store_rates(&state.db, rates).await.unwrap();
+    let app = test::init_service(App::new().app_data(state.clone()).configure(handler::init)).await;
+    let token = login(&app, "yara").await;
+
+    let mut accounts = Vec::new();
+    for body in [
+        json!({ "name": "Checking", "kind": "checking", "opening_balance": "1000", "opened_on": "2024-08-01" }),
+        json!({ "name": "Visa", "kind": "credit_card", "opening_balance": "-200", "opened_on": "2024-08-01" }),
+        json!({ "name": "Savings", "kind": "savings", "currency": "EUR" }),
+    ] {
+        let req = authed("POST", "/api/accounts", &token)
+            .set_json(body)
+            .to_request();
+        let account: Value = test::call_and_read_body_json(&app, req).await;
+        accounts.push(account["id"].as_str().unwrap().to_string());
+    }
+    let [checking, visa, savings] = &accounts[..] else {
+        unreachable!()
+    };
+
+    let req = authed("POST", "/api/budget", &token)
+        .set_json(json!({ "name": "Groceries", "total_amount": "400", "starts_on": "2024-08-01" }))
+        .to_request();
+    let budget: Value = test::call_and_read_body_json(&app, req).await;
+    let expenses_uri = format!("/api/budget/{}/expenses", budget["id"].as_str().unwrap());
+    for body in [
+        json!({ "amount": "50", "description": "Market", "date": "2024-08-03", "account_id": checking }),
+        json!({ "amount": "30", "currency": "EUR", "description": "Bakery", "date": "2024-08-10", "account_id": visa }),
+    ] {
+        let req = authed("POST", &expenses_uri, &token)
+            .set_json(body)
+            .to_request();
+        let expense: Value = test::call_and_read_body_json(&app, req).await;
+        assert!(expense["account_id"].is_string());
+    }
+    let req = authed("POST", "/api/income", &token)
+        .set_json(json!({
+            "source": "Employer",
+            "amount": "2000",
+            "date": "2024-08-05",
+            "account_id": checking,
+        }))
+        .to_request();
+    let res = test::call_service(&app, req).await;
+    assert_eq!(res.status(), StatusCode::OK);
+
+    for body in [
+        json!({ "from_account_id": checking, "to_account_id": visa, "amount": "200", "date": "2024-08-12", "description": "Card bill" }),
+        json!({ "from_account_id": checking, "to_account_id": savings, "amount": "550", "to_amount": "500", "date": "2024-08-14" }),
+    ] {
+        let req = authed("POST", "/api/transfers", &token)
+            .set_json(body)
+            .to_request();
+        let res = test::call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::OK);
+    }
+
+    let balance = |account: &str, on: Option<&str>| {
+        let uri = match on {
+            Some(on) => format!("/api/accounts/{}/balance?on={}", account, on),
+            None => format!("/api/accounts/{}/balance", account),
+        };
+        authed("GET", &uri, &token).to_request()
+    };
+    for (account, on, expected) in [
+        (checking, None, "2200.00"),
+        (checking, Some("2024-08-04"), "950.00"),
+        (visa, None, "-33.00"),
+        (savings, None, "500.00"),
+    ] {
+        let body: Value = test::call_and_read_body_json(&app, balance(account, on)).await;
+        assert_eq!(body["balance"], expected, "{} {:?}", account, on);
+    }
+
+    let req = authed(
+        "GET",
+        &format!("/api/accounts/{}/transactions?from=2024-08-05", checking),
+        &token,
+    )
+    .to_request();
+    let ledger: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(ledger["opening_balance"], "950.00");
+    assert_eq!(ledger["closing_balance"], "2200.00");
+    let entries: Vec<_> = ledger["entries"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|e| {
+            (
+                e["kind"].as_str().unwrap().to_string(),
+                e["amount"].as_str().unwrap().to_string(),
+                e["balance"].as_str().unwrap().to_string(),
+            )
+        })
+        .collect();
+    assert_eq!(
+        entries,
+        [
+            ("income".into(), "2000.00".into(), "2950.00".into()),
+            ("transfer_out".into(), "-200.00".into(), "2750.00".into()),
+            ("transfer_out".into(), "-550.00".into(), "2200.00".into()),
+        ]
+    );
+
+    // Transfers are not spending.
+    let req = authed("GET", "/api/reports/cash-flow?from=2024-08-01", &token).to_request();
+    let report: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(report["expenses"], "83.00");
+    assert_eq!(report["income"], "2000.00");
+
+    // Transfers keep an account from being deleted, so the other side's
+    // balance never changes behind its back.
+    let savings_uri = format!("/api/accounts/{}", savings);
+    let res = test::call_service(&app, authed("DELETE", &savings_uri, &token).to_request()).await;
+    assert_eq!(res.status(), StatusCode::CONFLICT);
+    let body: Value = test::call_and_read_body_json(&app, balance(checking, None)).await;
+    assert_eq!(body["balance"], "2200.00");
+
+    let req = authed(
+        "GET",
+        &format!("/api/transfers?account_id={}", savings),
+        &token,
+    )
+    .to_request();
+    let transfers: Value = test::call_and_read_body_json(&app, req).await;
+    let transfer_uri = format!(
+        "/api/transfers/{}",
+        transfers["data"][0]["id"].as_str().unwrap()
+    );
+    let res = test::call_service(&app, authed("DELETE", &transfer_uri, &token).to_request()).await;
+    assert_eq!(res.status(), StatusCode::OK);
+    let res = test::call_service(&app, authed("DELETE", &savings_uri, &token).to_request()).await;
+    assert_eq!(res.status(), StatusCode::OK);
+    let body: Value = test::call_and_read_body_json(&app, balance(checking, None)).await;
+    assert_eq!(body["balance"], "2750.00");
+}
+
+#[actix_web::test]
+async fn transfers_are_checked_against_both_accounts() {
+    let state = test_state().await;
+    let app = test::init_service(App::new().app_data(state.clone()).configure(handler::init)).await;
+    let token = login(&app, "zack").await;
+    let other = login(&app, "amber").await;
+
+    let mut accounts = Vec::new();
+    for (token, currency) in [(&token, "USD"), (&token, "GBP"), (&other, "USD")] {
+        let req = authed("POST", "/api/accounts", token)
+            .set_json(json!({ "name": "Main", "kind": "checking", "currency": currency }))
+            .to_request();
+        let account: Value = test::call_and_read_body_json(&app, req).await;
+        accounts.push(account["id"].as_str().unwrap().to_string());
+    }
+    let [usd, gbp, theirs] = &accounts[..] else {
+        unreachable!()
+    };
+
+    for (body, field) in [
+        (
+            json!({ "from_account_id": usd, "to_account_id": usd, "amount": "5" }),
+            "to_account_id",
+        ),
+        (
+            json!({ "from_account_id": usd, "to_account_id": gbp, "amount": "5" }),
+            "to_amount",
+        ),
+        (
+            json!({ "from_account_id": usd, "to_account_id": theirs, "amount": "5" }),
+            "to_account_id",
+        ),
+        (
+            json!({ "from_account_id": theirs, "to_account_id": usd, "amount": "5" }),
+            "from_account_id",
+        ),
+    ] {
+        let req = authed("POST", "/api/transfers", &token)
+            .set_json(&body)
+            .to_request();
+        let res = test::call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::UNPROCESSABLE_ENTITY, "{}", body);
+        let error: Value = test::read_body_json(res).await;
+        assert_eq!(error["error"]["details"][0]["field"], field, "{}", body);
+    }
+
+    let req = authed("POST", "/api/transfers", &token)
+        .set_json(json!({ "from_account_id": usd, "to_account_id": gbp, "amount": "10", "to_amount": "7.80" }))
+        .to_request();
+    let transfer: Value = test::call_and_read_body_json(&app, req).await;
+    let transfer_uri = format!("/api/transfers/{}", transfer["id"].as_str().unwrap());
+
+    // Changing the amount keeps what arrived on the other side.
+    let req = authed("PUT", &transfer_uri, &token)
+        .set_json(json!({ "amount": "10.50" }))
+        .to_request();
+    let transfer: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(transfer["amount"], "10.50");
+    assert_eq!(transfer["to_amount"], "7.80");
+
+    let req = authed("GET", &format!("/api/transfers?account_id={}", gbp), &token).to_request();
+    let listed: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(listed["pagination"]["total_items"], 1);
+    let req = authed("GET", &transfer_uri, &other).to_request();
+    let res = test::call_service(&app, req).await;
+    assert_eq!(res.status(), StatusCode::NOT_FOUND);
+
+    let req = authed("POST", "/api/income", &token)
+        .set_json(json!({ "source": "Gift", "amount": "5", "account_id": theirs }))
+        .to_request();
+    let res = test::call_service(&app, req).await;
+    assert_eq!(res.status(), StatusCode::UNPROCESSABLE_ENTITY);
+
+    let req = authed("DELETE", &transfer_uri, &token).to_request();
+    let res = test::call_service(&app, req).await;
+    assert_eq!(res.status(), StatusCode::OK);
+    let req = authed("GET", &transfer_uri, &token).to_request();
+    let res = test::call_service(&app, req).await;
+    assert_eq!(res.status(), StatusCode::NOT_FOUND);
+}
@@ -0,0 +1,141 @@
+mod common;
+
+use std::sync::Arc;
+
+use actix_web::{http::StatusCode, test, web, App};
+use chrono::{TimeZone, Utc};
+use pbudget::{
+    handler,
+    utility::{clock::FixedClock, state::AppState},
+};
+use serde_json::{json, Value};
+
+use common::{authed, login, test_settings, test_state};
+
+fn keyed(method: &str, uri: &str, key: &Value) -> test::TestRequest {
+    let req = match method {
+        "GET" => test::TestRequest::get(),
+        "POST" => test::TestRequest::post(),
+        _ => unreachable!("unsupported method {}", method),
+    };
+    req.uri(uri)
+        .insert_header(("X-API-Key", key.as_str().unwrap()))
+}
+
+#[actix_web::test]
+async fn api_keys_authenticate_within_their_scope() {
+    let state = test_state().await;
+    let app = test::init_service(App::new().app_data(state.clone()).configure(handler::init)).await;
+    let token = login(&app, "yusuf").await;
+
+    let req = authed("POST", "/api/api-keys", &token)
+        .set_json(json!({ "name": "Bank import", "scope": "write" }))
+        .to_request();
+    let writer: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(writer["scope"], "write");
+    assert_eq!(writer["last_used_at"], Value::Null);
+    let key = &writer["key"];
+    assert!(key
+        .as_str()
+        .unwrap()
+        .starts_with(writer["prefix"].as_str().unwrap()));
+
+    let req = authed("POST", "/api/api-keys", &token)
+        .set_json(json!({ "name": "Dashboard" }))
+        .to_request();
+    let reader: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(reader["scope"], "read");
+
+    // The key itself is never shown again.
+    let req = authed("GET", "/api/api-keys", &token).to_request();
+    let keys: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(keys.as_array().unwrap().len(), 2);
+    assert!(keys
+        .as_array()
+        .unwrap()
+        .iter()
+        .all(|k| k.get("key").is_none()));
+    assert!(keys
+        .as_array()
+        .unwrap()
+        .iter()
+        .all(|k| k.get("key_hash").is_none()));
+
+    let req = keyed("POST", "/api/budget", key)
+        .set_json(json!({ "name": "Groceries", "total_amount": "400" }))
+        .to_request();
+    let res = test::call_service(&app, req).await;
+    assert_eq!(res.status(), StatusCode::OK);
+
+    let req = keyed("GET", "/api/budget", &reader["key"]).to_request();
+    let budgets: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(budgets["data"][0]["name"], "Groceries");
+
+    let req = keyed("POST", "/api/budget", &reader["key"])
+        .set_json(json!({ "name": "Rent", "total_amount": "900" }))
+        .to_request();
+    let res = test::call_service(&app, req).await;
+    assert_eq!(res.status(), StatusCode::FORBIDDEN);
+
+    // Keys cannot manage keys, so a leaked one cannot mint more.
+    let req = keyed("POST", "/api/api-keys", key)
+        .set_json(json!({ "name": "Sneaky", "scope": "write" }))
+        .to_request();
+    let res = test::call_service(&app, req).await;
+    assert_eq!(res.status(), StatusCode::FORBIDDEN);
+
+    let uri = format!("/api/api-keys/{}", writer["id"].as_str().unwrap());
+    let used: Value =
+        test::call_and_read_body_json(&app, authed("GET", &uri, &token).to_request()).await;
+    assert_eq!(used["last_used_at"], "2024-08-15T12:00:00Z");
+
+    let res = test::call_service(&app, authed("DELETE", &uri, &token).to_request()).await;
+    assert_eq!(res.status(), StatusCode::OK);
+    let res = test::call_service(&app, keyed("GET", "/api/budget", key).to_request()).await;
+    assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+}
+
+#[actix_web::test]
+async fn expired_api_keys_are_rejected() {
+    let state = test_state().await;
+    let app = test::init_service(App::new().app_data(state.clone()).configure(handler::init)).await;
+    let token = login(&app, "zora").await;
+
+    let req = authed("POST", "/api/api-keys", &token)
+        .set_json(json!({ "name": "Old", "expires_at": "2024-08-15T11:00:00Z" }))
+        .to_request();
+    let res = test::call_service(&app, req).await;
+    assert_eq!(res.status(), StatusCode::UNPROCESSABLE_ENTITY);
+    let error: Value = test::read_body_json(res).await;
+    assert_eq!(error["error"]["details"][0]["field"], "expires_at");
+
+    let req = authed("POST", "/api/api-keys", &token)
+        .set_json(json!({ "name": "Short", "expires_at": "2024-08-15T13:00:00Z" }))
+        .to_request();
+    let short: Value = test::call_and_read_body_json(&app, req).await;
+    let res = test::call_service(
+        &app,
+        keyed("GET", "/api/profile", &short["key"]).to_request(),
+    )
+    .await;
+    assert_eq!(res.status(), StatusCode::OK);
+
+    // At the expiry time the key stops working.
+    let later = FixedClock(Utc.with_ymd_and_hms(2024, 8, 15, 13, 0, 0).unwrap());
+    let later = web::Data::new(
+        AppState::with_clock(state.db.clone(), test_settings(), Arc::new(later)).unwrap(),
+    );
+    let app = test::init_service(App::new().app_data(later).configure(handler::init)).await;
+    let res = test::call_service(
+        &app,
+        keyed("GET", "/api/profile", &short["key"]).to_request(),
+    )
+    .await;
+    assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+    let res = test::call_service(
+        &app,
+        keyed("GET", "/api/profile", &json!("pbk_nope")).to_request(),
+    )
+    .await;
+    assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+}
@@ -0,0 +1,80 @@
+mod common;
+
+use std::{num::NonZeroUsize, time::Duration};
+
+use actix_web::{test, App};
+use pbudget::{
+    handler,
+    utility::cache::{Cache, MemoryCache},
+};
+use serde_json::{json, Value};
+
+use common::{authed, login, test_state};
+
+#[actix_web::test]
+async fn memory_cache_evicts_least_recently_used_entries() {
+    let cache = MemoryCache::new(NonZeroUsize::new(2).unwrap());
+
+    cache.set("a", "1".to_string(), 60).await;
+    cache.set("b", "2".to_string(), 60).await;
+    assert_eq!(cache.get("a").await.as_deref(), Some("1"));
+    cache.set("c", "3".to_string(), 60).await;
+
+    assert_eq!(cache.get("a").await.as_deref(), Some("1"));
+    assert_eq!(cache.get("b").await, None);
+    assert_eq!(cache.get("c").await.as_deref(), Some("3"));
+
+    cache.delete("a").await;
+    assert_eq!(cache.get("a").await, None);
+}
+
+#[actix_web::test]
+async fn memory_cache_expires_entries() {
+    let cache = MemoryCache::new(NonZeroUsize::new(2).unwrap());
+
+    cache.set("a", "1".to_string(), 1).await;
+    actix_web::rt::time::sleep(Duration::from_millis(1100)).await;
+
+    assert_eq!(cache.get("a").await, None);
+}
+
+#[actix_web::test]
+async fn cache_hits_match_database_responses() {
+    let state = test_state().await;
+    let app = test::init_service(App::new().app_data(state.clone()).configure(handler::init)).await;
+    let token = login(&app, "carol").await;
+
+    let req = authed("POST", "/api/budget", &token)
+        .set_json(json!({ "name": "Travel", "total_amount": 1200.0 }))
+        .to_request();
+    let budget: Value = test::call_and_read_body_json(&app, req).await;
+    let budget_uri = format!("/api/budget/{}", budget["id"].as_str().unwrap());
+
+    let req = authed("POST", &format!("{}/expenses", budget_uri), &token)
+        .set_json(json!({ "amount": 89.99, "description": "Train" }))
+        .to_request();
+    let expense: Value = test::call_and_read_body_json(&app, req).await;
+    let expense_uri = format!(
+        "{}/expenses/{}",
+        budget_uri,
+        expense["id"].as_str().unwrap()
+    );
+
+    for uri in ["/api/profile", budget_uri.as_str(), expense_uri.as_str()] {
+        let miss: Value =
+            test::call_and_read_body_json(&app, authed("GET", uri, &token).to_request()).await;
+        let hit: Value =
+            test::call_and_read_body_json(&app, authed("GET", uri, &token).to_request()).await;
+        assert_eq!(miss, hit, "{}", uri);
+    }
+
+    let req = authed("PUT", &budget_uri, &token)
+        .set_json(json!({ "name": "Holidays" }))
+        .to_request();
+    let res = test::call_service(&app, req).await;
+    assert!(res.status().is_success());
+
+    let budget: Value =
+        test::call_and_read_body_json(&app, authed("GET", &budget_uri, &token).to_request()).await;
+    assert_eq!(budget["name"], "Holidays");
+}
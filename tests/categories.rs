@@ -0,0 +1,286 @@
+mod common;
+
+use actix_web::{
+    dev::{Service, ServiceResponse},
+    http::StatusCode,
+    test, App, Error,
+};
+use pbudget::handler;
+use serde_json::{json, Value};
+
+use common::{authed, login, test_state};
+
+async fn post<S>(app: &S, uri: &str, token: &str, body: Value) -> Value
+where
+    S: Service<actix_http::Request, Response = ServiceResponse, Error = Error>,
+{
+    let req = authed("POST", uri, token).set_json(body).to_request();
+    let res = test::call_service(app, req).await;
+    assert_eq!(res.status(), StatusCode::OK, "POST {}", uri);
+    test::read_body_json(res).await
+}
+
+#[actix_web::test]
+async fn breakdown_reports_spent_against_allocations() {
+    let state = test_state().await;
+    let app = test::init_service(App::new().app_data(state.clone()).configure(handler::init)).await;
+    let token = login(&app, "kate").await;
+
+    let food = post(&app, "/api/categories", &token, json!({ "name": "Food" })).await;
+    let dining = post(
+        &app,
+        "/api/categories",
+        &token,
+        json!({ "name": "Dining out", "parent_id": food["id"] }),
+    )
+    .await;
+    let fun = post(&app, "/api/categories", &token, json!({ "name": "Fun" })).await;
+
+    let budget = post(
+        &app,
+        "/api/budget",
+        &token,
+        json!({ "name": "August", "total_amount": "1000.00" }),
+    )
+    .await;
+    let budget_uri = format!("/api/budget/{}", budget["id"].as_str().unwrap());
+
+    for (category, amount) in [(&food, "400.00"), (&dining, "100.00")] {
+        let req = authed(
+            "PUT",
+            &format!(
+                "{}/categories/{}",
+                budget_uri,
+                category["id"].as_str().unwrap()
+            ),
+            &token,
+        )
+        .set_json(json!({ "amount": amount }))
+        .to_request();
+        let res = test::call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::OK);
+    }
+
+    let expenses_uri = format!("{}/expenses", budget_uri);
+    for (category, amount) in [
+        (food["id"].clone(), "50.25"),
+        (dining["id"].clone(), "120.00"),
+        (fun["id"].clone(), "30.00"),
+        (Value::Null, "9.75"),
+    ] {
+        post(
+            &app,
+            &expenses_uri,
+            &token,
+            json!({ "amount": amount, "description": "x", "category_id": category }),
+        )
+        .await;
+    }
+
+    let req = authed("GET", &format!("{}/categories", budget_uri), &token).to_request();
+    let breakdown: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(breakdown["allocated"], "500.00");
+    assert_eq!(breakdown["unallocated"], "500.00");
+    assert_eq!(breakdown["spent"], "210.00");
+    assert_eq!(breakdown["uncategorized_spent"], "9.75");
+
+    let lines = breakdown["categories"].as_array().unwrap();
+    let line = |name: &str| lines.iter().find(|l| l["name"] == name).unwrap().clone();
+    assert_eq!(lines.len(), 3);
+    assert_eq!(line("Food")["spent"], "170.25");
+    assert_eq!(line("Food")["remaining"], "229.75");
+    assert_eq!(line("Dining out")["spent"], "120.00");
+    assert_eq!(line("Dining out")["remaining"], "-20.00");
+    assert_eq!(line("Fun")["allocated"], Value::Null);
+    assert_eq!(line("Fun")["spent"], "30.00");
+}
+
+#[actix_web::test]
+async fn breakdown_only_counts_the_current_period() {
+    let state = test_state().await;
+    let app = test::init_service(App::new().app_data(state.clone()).configure(handler::init)).await;
+    let token = login(&app, "kirk").await;
+
+    let food = post(&app, "/api/categories", &token, json!({ "name": "Food" })).await;
+    let budget = post(
+        &app,
+        "/api/budget",
+        &token,
+        json!({
+            "name": "Groceries",
+            "total_amount": "300.00",
+            "period": "monthly",
+            "starts_on": "2024-06-01",
+        }),
+    )
+    .await;
+    let budget_uri = format!("/api/budget/{}", budget["id"].as_str().unwrap());
+    let req = authed(
+        "PUT",
+        &format!("{}/categories/{}", budget_uri, food["id"].as_str().unwrap()),
+        &token,
+    )
+    .set_json(json!({ "amount": "200.00" }))
+    .to_request();
+    let res = test::call_service(&app, req).await;
+    assert_eq!(res.status(), StatusCode::OK);
+
+    // The test clock is fixed at 2024-08-15, so August is the current period.
+    let expenses_uri = format!("{}/expenses", budget_uri);
+    for (date, amount) in [
+        ("2024-06-20", "150.00"),
+        ("2024-07-31", "180.00"),
+        ("2024-08-01", "40.00"),
+        ("2024-08-15", "25.50"),
+    ] {
+        post(
+            &app,
+            &expenses_uri,
+            &token,
+            json!({ "amount": amount, "description": "x", "category_id": food["id"], "date": date }),
+        )
+        .await;
+    }
+
+    let req = authed("GET", &format!("{}/categories", budget_uri), &token).to_request();
+    let breakdown: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(breakdown["start"], "2024-08-01");
+    assert_eq!(breakdown["end"], "2024-08-31");
+    assert_eq!(breakdown["spent"], "65.50");
+    let line = &breakdown["categories"][0];
+    assert_eq!(line["name"], "Food");
+    assert_eq!(line["spent"], "65.50");
+    assert_eq!(line["remaining"], "134.50");
+}
+
+#[actix_web::test]
+async fn categories_form_a_tree_without_cycles() {
+    let state = test_state().await;
+    let app = test::init_service(App::new().app_data(state.clone()).configure(handler::init)).await;
+    let token = login(&app, "leo").await;
+
+    let home = post(&app, "/api/categories", &token, json!({ "name": "Home" })).await;
+    let repairs = post(
+        &app,
+        "/api/categories",
+        &token,
+        json!({ "name": "Repairs", "parent_id": home["id"] }),
+    )
+    .await;
+    let home_uri = format!("/api/categories/{}", home["id"].as_str().unwrap());
+    let repairs_uri = format!("/api/categories/{}", repairs["id"].as_str().unwrap());
+
+    let req = authed("POST", "/api/categories", &token)
+        .set_json(json!({ "name": "Repairs", "parent_id": home["id"] }))
+        .to_request();
+    let res = test::call_service(&app, req).await;
+    assert_eq!(res.status(), StatusCode::CONFLICT);
+
+    let req = authed("PUT", &home_uri, &token)
+        .set_json(json!({ "parent_id": repairs["id"] }))
+        .to_request();
+    let res = test::call_service(&app, req).await;
+    assert_eq!(res.status(), StatusCode::UNPROCESSABLE_ENTITY);
+
+    let res = test::call_service(&app, authed("DELETE", &home_uri, &token).to_request()).await;
+    assert_eq!(res.status(), StatusCode::CONFLICT);
+
+    let req = authed("PUT", &repairs_uri, &token)
+        .set_json(json!({ "parent_id": null, "name": "Maintenance" }))
+        .to_request();
+    let moved: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(moved["parent_id"], Value::Null);
+    assert_eq!(moved["name"], "Maintenance");
+
+    let res = test::call_service(&app, authed("DELETE", &home_uri, &token).to_request()).await;
+    assert_eq!(res.status(), StatusCode::OK);
+
+    let req = authed("GET", "/api/categories", &token).to_request();
+    let categories: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(categories, json!([moved]));
+}
+
+#[actix_web::test]
+async fn deleting_a_category_uncategorizes_its_expenses() {
+    let state = test_state().await;
+    let app = test::init_service(App::new().app_data(state.clone()).configure(handler::init)).await;
+    let token = login(&app, "mia").await;
+
+    let books = post(&app, "/api/categories", &token, json!({ "name": "Books" })).await;
+    let budget = post(
+        &app,
+        "/api/budget",
+        &token,
+        json!({ "name": "Hobbies", "total_amount": "60" }),
+    )
+    .await;
+    let expenses_uri = format!("/api/budget/{}/expenses", budget["id"].as_str().unwrap());
+    let expense = post(
+        &app,
+        &expenses_uri,
+        &token,
+        json!({ "amount": "15", "description": "Novel", "category_id": books["id"] }),
+    )
+    .await;
+    let expense_uri = format!("{}/{}", expenses_uri, expense["id"].as_str().unwrap());
+
+    // Cached with the category still set.
+    let req = authed("GET", &expense_uri, &token).to_request();
+    let cached: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(cached["category_id"], books["id"]);
+
+    let uri = format!("/api/categories/{}", books["id"].as_str().unwrap());
+    let res = test::call_service(&app, authed("DELETE", &uri, &token).to_request()).await;
+    assert_eq!(res.status(), StatusCode::OK);
+
+    let req = authed("GET", &expense_uri, &token).to_request();
+    let expense: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(expense["category_id"], Value::Null);
+}
+
+#[actix_web::test]
+async fn categories_belong_to_their_owner() {
+    let state = test_state().await;
+    let app = test::init_service(App::new().app_data(state.clone()).configure(handler::init)).await;
+    let owner = login(&app, "nina").await;
+    let other = login(&app, "oscar").await;
+
+    let travel = post(&app, "/api/categories", &owner, json!({ "name": "Travel" })).await;
+    let travel_uri = format!("/api/categories/{}", travel["id"].as_str().unwrap());
+
+    for method in ["GET", "DELETE"] {
+        let res = test::call_service(&app, authed(method, &travel_uri, &other).to_request()).await;
+        assert_eq!(res.status(), StatusCode::NOT_FOUND, "{}", method);
+    }
+
+    let budget = post(
+        &app,
+        "/api/budget",
+        &other,
+        json!({ "name": "Trips", "total_amount": "500" }),
+    )
+    .await;
+    let budget_uri = format!("/api/budget/{}", budget["id"].as_str().unwrap());
+
+    let req = authed("POST", &format!("{}/expenses", budget_uri), &other)
+        .set_json(json!({ "amount": "5", "description": "Bus", "category_id": travel["id"] }))
+        .to_request();
+    let res = test::call_service(&app, req).await;
+    assert_eq!(res.status(), StatusCode::UNPROCESSABLE_ENTITY);
+    let body: Value = test::read_body_json(res).await;
+    assert_eq!(body["error"]["details"][0]["field"], "category_id");
+
+    let req = authed(
+        "PUT",
+        &format!(
+            "{}/categories/{}",
+            budget_uri,
+            travel["id"].as_str().unwrap()
+        ),
+        &other,
+    )
+    .set_json(json!({ "amount": "100" }))
+    .to_request();
+    let res = test::call_service(&app, req).await;
+    assert_eq!(res.status(), StatusCode::NOT_FOUND);
+}
@@ -0,0 +1,118 @@
+use std::sync::Arc;
+
+use actix_web::{
+    body::MessageBody,
+    dev::{Service, ServiceResponse},
+    http::header,
+    test, web, Error,
+};
+use chrono::{TimeZone, Utc};
+use migration::{Migrator, MigratorTrait};
+use pbudget::utility::{
+    clock::FixedClock,
+    config::{
+        CacheBackend, CacheSettings, DatabaseSettings, ExchangeRateSettings, JwtSettings,
+        MailBackend, MailSettings, RedisSettings, SchedulerSettings, ServerSettings, Settings,
+    },
+    state::AppState,
+};
+use sea_orm::Database;
+use serde_json::{json, Value};
+
+pub fn test_settings() -> Settings {
+    Settings {
+        database: DatabaseSettings {
+            url: "sqlite::memory:".to_string(),
+        },
+        server: ServerSettings {
+            host: "127.0.0.1".to_string(),
+            port: 8080,
+            workers: Some(1),
+        },
+        redis: RedisSettings {
+            url: "redis://127.0.0.1:1".to_string(),
+            connect_timeout_ms: 100,
+            response_timeout_ms: 100,
+            retry_interval_secs: 60,
+        },
+        jwt: JwtSettings {
+            secret: "test-secret".to_string(),
+            expiry_secs: 3600,
+            refresh_expiry_secs: 30 * 86400,
+        },
+        cache: CacheSettings {
+            backend: CacheBackend::Memory,
+            memory_capacity: 100,
+            profile_ttl_secs: 60,
+            budget_ttl_secs: 60,
+            expense_ttl_secs: 60,
+        },
+        scheduler: SchedulerSettings { interval_secs: 60 },
+        exchange_rates: ExchangeRateSettings { file: None },
+        mail: MailSettings {
+            backend: MailBackend::Log,
+            from: "pbudget@example.com".to_string(),
+            smtp_host: "127.0.0.1".to_string(),
+            smtp_port: 25,
+            file: None,
+            verification_ttl_secs: 86400,
+            reset_ttl_secs: 3600,
+        },
+    }
+}
+
+pub async fn test_state() -> web::Data<AppState> {
+    state_with(test_settings()).await
+}
+
+/// State for a fresh in-memory database, with the test clock.
+pub async fn state_with(settings: Settings) -> web::Data<AppState> {
+    let db = Database::connect(&settings.database.url)
+        .await
+        .expect("in-memory database");
+    Migrator::up(&db, None).await.expect("migrations");
+
+    let clock = FixedClock(Utc.with_ymd_and_hms(2024, 8, 15, 12, 0, 0).unwrap());
+    web::Data::new(AppState::with_clock(db, settings, Arc::new(clock)).expect("state"))
+}
+
+/// Registers `username` and returns a bearer token for it.
+pub async fn login<S, B>(app: &S, username: &str) -> String
+where
+    S: Service<actix_http::Request, Response = ServiceResponse<B>, Error = Error>,
+    B: MessageBody,
+{
+    let credentials = json!({
+        "username": username,
+        "password": "correct horse battery staple",
+        "email": format!("{}@example.com", username),
+    });
+
+    let req = test::TestRequest::post()
+        .uri("/api/register")
+        .set_json(&credentials)
+        .to_request();
+    let res = test::call_service(app, req).await;
+    assert!(res.status().is_success(), "register: {}", res.status());
+
+    let req = test::TestRequest::post()
+        .uri("/api/login")
+        .set_json(&credentials)
+        .to_request();
+    let tokens: Value = test::call_and_read_body_json(app, req).await;
+    format!(
+        "Bearer {}",
+        tokens["access_token"].as_str().expect("access token")
+    )
+}
+
+pub fn authed(method: &str, uri: &str, token: &str) -> test::TestRequest {
+    let req = match method {
+        "GET" => test::TestRequest::get(),
+        "POST" => test::TestRequest::post(),
+        "PUT" => test::TestRequest::put(),
+        "DELETE" => test::TestRequest::delete(),
+        _ => unreachable!("unsupported method {}", method),
+    };
+    req.uri(uri).insert_header((header::AUTHORIZATION, token))
+}
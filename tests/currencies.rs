@@ -0,0 +1,183 @@
+mod common;
+
+use actix_web::{http::StatusCode, test, App};
+use entities::{money::Money, rate::Rate};
+use pbudget::{handler, utility::currency};
+use serde_json::{json, Value};
+
+use common::{authed, login, test_state};
+
+#[actix_web::test]
+async fn rates_convert_amounts_exactly() {
+    let rate: Rate = "1.0876".parse().unwrap();
+    assert_eq!(rate.to_string(), "1.0876");
+    let amount: Money = "100.00".parse().unwrap();
+    assert_eq!(rate.convert(amount).unwrap().to_string(), "108.76");
+    assert_eq!(
+        rate.convert_inverse("108.76".parse().unwrap())
+            .unwrap()
+            .to_string(),
+        "100.00"
+    );
+    // 0.005 rounds away from zero.
+    let rate: Rate = "0.5".parse().unwrap();
+    assert_eq!(
+        rate.convert("0.01".parse().unwrap()).unwrap().to_string(),
+        "0.01"
+    );
+
+    for invalid in ["0", "-1.2", "1.00000000001", "abc", ""] {
+        assert!(invalid.parse::<Rate>().is_err(), "{}", invalid);
+    }
+}
+
+#[actix_web::test]
+async fn exchange_rate_csv_is_validated_line_by_line() {
+    let rates = currency::parse_csv(
+        "base_currency,quote_currency,effective_on,rate\n\
+         # ECB reference rates\n\
+         EUR,USD,2024-08-01,1.0876\n\
+         \n\
+         USD, JPY, 2024-08-01, 149.5\n",
+    )
+    .unwrap();
+    assert_eq!(rates.len(), 2);
+    assert_eq!(rates[1].quote_currency, "JPY");
+
+    for (csv, line) in [
+        ("EUR,USD,2024-08-01\n", 1),
+        ("EUR,USD,2024-08-01,1.1\nEUR,XXX,2024-08-01,1.1\n", 2),
+        ("EUR,EUR,2024-08-01,1\n", 1),
+        ("EUR,USD,01/08/2024,1.1\n", 1),
+        ("\nEUR,USD,2024-08-01,0\n", 2),
+    ] {
+        let err = currency::parse_csv(csv).unwrap_err();
+        assert!(
+            err.to_string().contains(&format!("line {}", line)),
+            "{}: {}",
+            csv,
+            err
+        );
+    }
+}
+
+#[actix_web::test]
+async fn summaries_convert_expenses_at_the_rate_of_their_date() {
+    let state = test_state().await;
+    let rates = currency::parse_csv(
+        "EUR,USD,2024-07-01,1.10\n\
+         EUR,USD,2024-08-01,1.00\n\
+         USD,JPY,2024-01-01,150\n",
+    )
+    .unwrap();
+    currency::store_rates(&state.db, rates).await.unwrap();
+    // Loading a newer file replaces the rate for the same day.
+    let rates = currency::parse_csv("EUR,USD,2024-08-01,1.05\n").unwrap();
+    currency::store_rates(&state.db, rates).await.unwrap();
+
+    let app = test::init_service(App::new().app_data(state.clone()).configure(handler::init)).await;
+    let token = login(&app, "zelda").await;
+
+    let req = authed("POST", "/api/budget", &token)
+        .set_json(json!({ "name": "Travel", "total_amount": "1000", "starts_on": "2024-07-01" }))
+        .to_request();
+    let budget: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(budget["currency"], "USD");
+    let budget_uri = format!("/api/budget/{}", budget["id"].as_str().unwrap());
+
+    for (amount, currency, date, converted) in [
+        ("100", Some("EUR"), "2024-07-31", "110.00"),
+        ("100", Some("EUR"), "2024-08-02", "105.00"),
+        ("3000", Some("JPY"), "2024-08-05", "20.00"),
+        ("10", None, "2024-08-06", "10.00"),
+    ] {
+        let mut body = json!({ "amount": amount, "description": converted, "date": date });
+        if let Some(currency) = currency {
+            body["currency"] = json!(currency);
+        }
+        let req = authed("POST", &format!("{}/expenses", budget_uri), &token)
+            .set_json(body)
+            .to_request();
+        let expense: Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(expense["currency"], currency.unwrap_or("USD"));
+        assert_eq!(expense["amount"], format!("{}.00", amount));
+    }
+
+    let req = authed("GET", &format!("{}/summary", budget_uri), &token).to_request();
+    let summary: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(summary["currency"], "USD");
+    assert_eq!(summary["spent"], "135.00");
+
+    let req = authed("GET", &format!("{}/periods", budget_uri), &token).to_request();
+    let history: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(history[1]["spent"], "110.00");
+
+    let req = authed("GET", &format!("{}/categories", budget_uri), &token).to_request();
+    let breakdown: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(breakdown["spent"], "135.00");
+
+    let req = authed("GET", "/api/exchange-rates?base=EUR", &token).to_request();
+    let rates: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(
+        rates["data"],
+        json!([
+            { "base_currency": "EUR", "quote_currency": "USD", "effective_on": "2024-08-01", "rate": "1.05" },
+            { "base_currency": "EUR", "quote_currency": "USD", "effective_on": "2024-07-01", "rate": "1.1" },
+        ])
+    );
+
+    // Without a rate the budget cannot be summarized.
+    let req = authed("POST", &format!("{}/expenses", budget_uri), &token)
+        .set_json(
+            json!({ "amount": "5", "currency": "GBP", "description": "Tea", "date": "2024-08-07" }),
+        )
+        .to_request();
+    let res = test::call_service(&app, req).await;
+    assert_eq!(res.status(), StatusCode::OK);
+    let req = authed("GET", &format!("{}/summary", budget_uri), &token).to_request();
+    let res = test::call_service(&app, req).await;
+    assert_eq!(res.status(), StatusCode::CONFLICT);
+    let error: Value = test::read_body_json(res).await;
+    assert_eq!(
+        error["error"]["message"],
+        "no exchange rate from GBP to USD on or before 2024-08-07"
+    );
+}
+
+#[actix_web::test]
+async fn currencies_must_be_iso_4217_codes() {
+    let state = test_state().await;
+    let app = test::init_service(App::new().app_data(state.clone()).configure(handler::init)).await;
+    let token = login(&app, "aaron").await;
+
+    for currency in ["EURO", "eur", "XYZ"] {
+        let req = authed("POST", "/api/budget", &token)
+            .set_json(json!({ "name": "Trip", "total_amount": "100", "currency": currency }))
+            .to_request();
+        let res = test::call_service(&app, req).await;
+        assert_eq!(
+            res.status(),
+            StatusCode::UNPROCESSABLE_ENTITY,
+            "{}",
+            currency
+        );
+        let error: Value = test::read_body_json(res).await;
+        assert_eq!(error["error"]["details"][0]["field"], "currency");
+    }
+
+    let req = authed("POST", "/api/budget", &token)
+        .set_json(json!({ "name": "Trip", "total_amount": "100", "currency": "EUR" }))
+        .to_request();
+    let budget: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(budget["currency"], "EUR");
+
+    let req = authed(
+        "POST",
+        &format!("/api/budget/{}/expenses", budget["id"].as_str().unwrap()),
+        &token,
+    )
+    .set_json(json!({ "amount": "4", "description": "Coffee" }))
+    .to_request();
+    let expense: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(expense["currency"], "EUR");
+}
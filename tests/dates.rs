@@ -0,0 +1,123 @@
+mod common;
+
+use actix_web::{http::StatusCode, test, App};
+use chrono::{NaiveDate, TimeZone, Utc};
+use migration::{Migrator, MigratorTrait};
+use pbudget::handler;
+use sea_orm::{ConnectionTrait, Database, DbBackend, EntityTrait, Statement};
+use serde_json::{json, Value};
+use uuid::Uuid;
+
+use common::{authed, login, test_state};
+
+#[actix_web::test]
+async fn expenses_default_to_today_and_can_be_backdated() {
+    let state = test_state().await;
+    let app = test::init_service(App::new().app_data(state.clone()).configure(handler::init)).await;
+    let token = login(&app, "frank").await;
+
+    let req = authed("POST", "/api/budget", &token)
+        .set_json(json!({ "name": "Groceries", "total_amount": "300.00" }))
+        .to_request();
+    let budget: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(budget["created_at"], "2024-08-15T12:00:00Z");
+    let expenses_uri = format!("/api/budget/{}/expenses", budget["id"].as_str().unwrap());
+
+    for (date, expected) in [
+        (None, "2024-08-15"),
+        (Some("2024-08-01"), "2024-08-01"),
+        // Late evening in New York is already the next day in UTC.
+        (Some("2024-07-31T22:30:00-04:00"), "2024-07-31"),
+    ] {
+        let mut body = json!({ "amount": "12.00", "description": "Market" });
+        if let Some(date) = date {
+            body["date"] = json!(date);
+        }
+        let req = authed("POST", &expenses_uri, &token)
+            .set_json(body)
+            .to_request();
+        let expense: Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(expense["date"], expected);
+        assert_eq!(expense["created_at"], "2024-08-15T12:00:00Z");
+    }
+
+    let req = authed("POST", &expenses_uri, &token)
+        .set_json(json!({ "amount": "1.00", "description": "Market", "date": "yesterday" }))
+        .to_request();
+    let res = test::call_service(&app, req).await;
+    assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+}
+
+#[actix_web::test]
+async fn expense_dates_can_be_corrected() {
+    let state = test_state().await;
+    let app = test::init_service(App::new().app_data(state.clone()).configure(handler::init)).await;
+    let token = login(&app, "grace").await;
+
+    let req = authed("POST", "/api/budget", &token)
+        .set_json(json!({ "name": "Fuel", "total_amount": "100" }))
+        .to_request();
+    let budget: Value = test::call_and_read_body_json(&app, req).await;
+    let expenses_uri = format!("/api/budget/{}/expenses", budget["id"].as_str().unwrap());
+
+    let req = authed("POST", &expenses_uri, &token)
+        .set_json(json!({ "amount": "40", "description": "Fill up" }))
+        .to_request();
+    let expense: Value = test::call_and_read_body_json(&app, req).await;
+
+    let req = authed(
+        "PUT",
+        &format!("{}/{}", expenses_uri, expense["id"].as_str().unwrap()),
+        &token,
+    )
+    .set_json(json!({ "date": "2024-08-10" }))
+    .to_request();
+    let expense: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(expense["date"], "2024-08-10");
+    assert_eq!(expense["amount"], "40.00");
+}
+
+#[actix_web::test]
+async fn migration_normalizes_existing_timestamps() {
+    let db = Database::connect("sqlite::memory:").await.unwrap();
+    Migrator::up(&db, Some(4)).await.unwrap();
+
+    let user_id = Uuid::new_v4();
+    let budget_id = Uuid::new_v4();
+    for (sql, values) in [
+        (
+            "INSERT INTO users (id, username, password_hash, email) VALUES (?, 'heidi', '', 'heidi@example.com')",
+            vec![user_id.into()],
+        ),
+        (
+            "INSERT INTO budget (id, user_id, name, total_amount, created_at, updated_at) \
+             VALUES (?, ?, 'Rent', 100000, '2024-03-01 08:15:30.123456789', '2024-03-02 09:00:00')",
+            vec![budget_id.into(), user_id.into()],
+        ),
+    ] {
+        db.execute(Statement::from_sql_and_values(DbBackend::Sqlite, sql, values))
+            .await
+            .unwrap();
+    }
+
+    Migrator::up(&db, None).await.unwrap();
+
+    let budget = entities::budget::Entity::find_by_id(budget_id)
+        .one(&db)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(
+        budget.created_at,
+        Utc.with_ymd_and_hms(2024, 3, 1, 8, 15, 30).unwrap() + chrono::Duration::milliseconds(123)
+    );
+    assert_eq!(
+        budget.updated_at.date_naive(),
+        NaiveDate::from_ymd_opt(2024, 3, 2).unwrap()
+    );
+    // Later migrations derive the first budget period from the creation date.
+    assert_eq!(
+        budget.starts_on,
+        NaiveDate::from_ymd_opt(2024, 3, 1).unwrap()
+    );
+}
@@ -0,0 +1,261 @@
+mod common;
+
+use actix_web::{http::StatusCode, test, App};
+use pbudget::{
+    handler,
+    utility::{
+        config::MailBackend,
+        mailer::{Email, Mailer, SmtpMailer},
+    },
+};
+use serde_json::{json, Value};
+use tokio::{
+    io::{AsyncBufReadExt, AsyncWriteExt, BufReader},
+    net::TcpListener,
+};
+
+use common::{authed, login, state_with, test_settings, test_state};
+
+/// The token in the `n`th message mailed to `to`, counting from one: the
+/// only line that is a bare 43 character token. Mail is sent in the
+/// background, so this waits for it to arrive.
+async fn mailed_token(mail_file: &std::path::Path, to: &str, n: usize) -> String {
+    for _ in 0..100 {
+        let mail = std::fs::read_to_string(mail_file).unwrap_or_default();
+        let message = mail
+            .split("From: ")
+            .filter(|message| message.contains(&format!("To: {}\n", to)))
+            .nth(n - 1);
+        if let Some(message) = message {
+            return message
+                .lines()
+                .find(|line| {
+                    line.len() == 43
+                        && line
+                            .chars()
+                            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+                })
+                .expect("a token")
+                .to_string();
+        }
+        actix_web::rt::time::sleep(std::time::Duration::from_millis(20)).await;
+    }
+    panic!("no message {} to {}", n, to);
+}
+
+#[actix_web::test]
+async fn email_is_verified_and_passwords_reset_with_mailed_tokens() {
+    let mut settings = test_settings();
+    let mail_file = std::env::temp_dir().join(format!("pbudget-mail-{}.txt", uuid::Uuid::new_v4()));
+    settings.mail.backend = MailBackend::File;
+    settings.mail.file = Some(mail_file.clone());
+    let state = state_with(settings).await;
+    let app = test::init_service(App::new().app_data(state.clone()).configure(handler::init)).await;
+    let token = login(&app, "bea").await;
+
+    let profile: Value =
+        test::call_and_read_body_json(&app, authed("GET", "/api/profile", &token).to_request())
+            .await;
+    assert_eq!(profile["email_verified_at"], Value::Null);
+
+    let verify = |token: &str| {
+        test::TestRequest::post()
+            .uri("/api/verify-email")
+            .set_json(json!({ "token": token }))
+            .to_request()
+    };
+    let sent = mailed_token(&mail_file, "bea@example.com", 1).await;
+    // The answer gives nothing away to whoever holds the token, and the
+    // cached profile is not left stale.
+    let res = test::call_service(&app, verify(&sent)).await;
+    assert_eq!(res.status(), StatusCode::NO_CONTENT);
+    assert!(test::read_body(res).await.is_empty());
+    let profile: Value =
+        test::call_and_read_body_json(&app, authed("GET", "/api/profile", &token).to_request())
+            .await;
+    assert_eq!(profile["email_verified_at"], "2024-08-15T12:00:00Z");
+
+    // Tokens work once.
+    let res = test::call_service(&app, verify(&sent)).await;
+    assert_eq!(res.status(), StatusCode::UNPROCESSABLE_ENTITY);
+    let error: Value = test::read_body_json(res).await;
+    assert_eq!(error["error"]["details"][0]["field"], "token");
+
+    let req = authed("POST", "/api/verify-email/resend", &token).to_request();
+    let res = test::call_service(&app, req).await;
+    assert_eq!(res.status(), StatusCode::CONFLICT);
+
+    // A new address needs verifying again, and old links no longer count.
+    let req = authed("PUT", "/api/profile", &token)
+        .set_json(json!({ "email": "bea@example.org" }))
+        .to_request();
+    let user: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(user["email_verified_at"], Value::Null);
+    mailed_token(&mail_file, "bea@example.org", 1).await;
+    let req = authed("POST", "/api/verify-email/resend", &token).to_request();
+    let res = test::call_service(&app, req).await;
+    assert_eq!(res.status(), StatusCode::OK);
+    let sent = mailed_token(&mail_file, "bea@example.org", 2).await;
+    let res = test::call_service(&app, verify(&sent)).await;
+    assert_eq!(res.status(), StatusCode::NO_CONTENT);
+
+    let forgot = |email: &str| {
+        test::TestRequest::post()
+            .uri("/api/password/forgot")
+            .set_json(json!({ "email": email }))
+            .to_request()
+    };
+    let res = test::call_service(&app, forgot("nobody@example.org")).await;
+    assert_eq!(res.status(), StatusCode::OK);
+    let res = test::call_service(&app, forgot("bea@example.org")).await;
+    assert_eq!(res.status(), StatusCode::OK);
+    let reset_token = mailed_token(&mail_file, "bea@example.org", 3).await;
+
+    let reset = |token: &str, password: &str| {
+        test::TestRequest::post()
+            .uri("/api/password/reset")
+            .set_json(json!({ "token": token, "password": password }))
+            .to_request()
+    };
+    // A verification token is not a reset token.
+    let res = test::call_service(&app, reset(&sent, "brand new pass 1")).await;
+    assert_eq!(res.status(), StatusCode::UNPROCESSABLE_ENTITY);
+    let res = test::call_service(&app, reset(&reset_token, "brand new pass 1")).await;
+    assert_eq!(res.status(), StatusCode::OK);
+    let res = test::call_service(&app, reset(&reset_token, "another pass 2")).await;
+    assert_eq!(res.status(), StatusCode::UNPROCESSABLE_ENTITY);
+
+    // Resetting logs out every session, and only the new password works.
+    let res = test::call_service(&app, authed("GET", "/api/profile", &token).to_request()).await;
+    assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+    for (password, status) in [
+        ("correct horse battery staple", StatusCode::UNAUTHORIZED),
+        ("brand new pass 1", StatusCode::OK),
+    ] {
+        let req = test::TestRequest::post()
+            .uri("/api/login")
+            .set_json(json!({ "username": "bea", "password": password }))
+            .to_request();
+        let res = test::call_service(&app, req).await;
+        assert_eq!(res.status(), status);
+    }
+
+    std::fs::remove_file(&mail_file).ok();
+}
+
+#[actix_web::test]
+async fn email_tokens_and_addresses_are_checked() {
+    let state = test_state().await;
+    let app = test::init_service(App::new().app_data(state.clone()).configure(handler::init)).await;
+
+    for (uri, body, field) in [
+        (
+            "/api/password/forgot",
+            json!({ "email": "not an email" }),
+            "email",
+        ),
+        ("/api/verify-email", json!({ "token": "made-up" }), "token"),
+        (
+            "/api/password/reset",
+            json!({ "token": "made-up", "password": "brand new pass 1" }),
+            "token",
+        ),
+        (
+            "/api/password/reset",
+            json!({ "token": "made-up", "password": "short" }),
+            "password",
+        ),
+    ] {
+        let req = test::TestRequest::post()
+            .uri(uri)
+            .set_json(&body)
+            .to_request();
+        let res = test::call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::UNPROCESSABLE_ENTITY, "{}", body);
+        let error: Value = test::read_body_json(res).await;
+        assert_eq!(error["error"]["details"][0]["field"], field, "{}", body);
+    }
+
+    let req = test::TestRequest::post()
+        .uri("/api/verify-email/resend")
+        .to_request();
+    let res = test::call_service(&app, req).await;
+    assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+}
+
+#[actix_web::test]
+async fn smtp_mailer_talks_to_a_local_server() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+
+    // Accepts one message and hands back the conversation.
+    let server = tokio::spawn(async move {
+        let (stream, _) = listener.accept().await.unwrap();
+        let (reader, mut writer) = stream.into_split();
+        let mut reader = BufReader::new(reader);
+        let mut transcript = Vec::new();
+        writer.write_all(b"220 stand-in ready\r\n").await.unwrap();
+        let mut in_data = false;
+        loop {
+            let mut line = String::new();
+            if reader.read_line(&mut line).await.unwrap() == 0 {
+                break;
+            }
+            let line = line.trim_end_matches("\r\n").to_string();
+            let reply: &[u8] = if in_data {
+                if line == "." {
+                    in_data = false;
+                    b"250 queued\r\n"
+                } else {
+                    transcript.push(line);
+                    continue;
+                }
+            } else if line.starts_with("EHLO") {
+                b"250-stand-in\r\n250 8BITMIME\r\n"
+            } else if line == "DATA" {
+                in_data = true;
+                b"354 go ahead\r\n"
+            } else if line == "QUIT" {
+                transcript.push(line);
+                writer.write_all(b"221 bye\r\n").await.unwrap();
+                break;
+            } else {
+                b"250 ok\r\n"
+            };
+            transcript.push(line);
+            writer.write_all(reply).await.unwrap();
+        }
+        transcript
+    });
+
+    let mailer = SmtpMailer {
+        host: "127.0.0.1".to_string(),
+        port,
+        from: "pbudget@example.com".to_string(),
+    };
+    mailer
+        .send(&Email {
+            to: "cleo@example.com".to_string(),
+            subject: "Hello".to_string(),
+            body: "First line\n.starts with a dot".to_string(),
+        })
+        .await
+        .unwrap();
+
+    let transcript = server.await.unwrap();
+    assert_eq!(transcript[0], "EHLO localhost");
+    assert_eq!(transcript[1], "MAIL FROM:<pbudget@example.com>");
+    assert_eq!(transcript[2], "RCPT TO:<cleo@example.com>");
+    assert!(transcript.contains(&"Subject: Hello".to_string()));
+    assert!(transcript.contains(&"..starts with a dot".to_string()));
+    assert_eq!(transcript.last().unwrap(), "QUIT");
+
+    // Nothing listening: the error comes back to the caller.
+    let closed = SmtpMailer { port: 1, ..mailer };
+    let email = Email {
+        to: "cleo@example.com".to_string(),
+        subject: "Hello".to_string(),
+        body: String::new(),
+    };
+    assert!(closed.send(&email).await.is_err());
+}
@@ -0,0 +1,207 @@
+mod common;
+
+use actix_web::{http::StatusCode, test, App};
+use pbudget::{handler, utility::currency};
+use serde_json::{json, Value};
+
+use common::{authed, login, test_state};
+
+#[actix_web::test]
+async fn income_is_private_and_can_be_linked_to_a_budget() {
+    let state = test_state().await;
+    let app = test::init_service(App::new().app_data(state.clone()).configure(handler::init)).await;
+    let token = login(&app, "ursula").await;
+    let other = login(&app, "victor").await;
+
+    let req = authed("POST", "/api/budget", &token)
+        .set_json(json!({ "name": "Savings", "total_amount": "500", "currency": "EUR" }))
+        .to_request();
+    let budget: Value = test::call_and_read_body_json(&app, req).await;
+    let budget_id = budget["id"].as_str().unwrap().to_string();
+
+    let req = authed("POST", "/api/income", &token)
+        .set_json(json!({ "source": "Employer", "amount": "2500", "budget_id": budget_id }))
+        .to_request();
+    let income: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(income["amount"], "2500.00");
+    assert_eq!(income["date"], "2024-08-15");
+    // Linked income defaults to the budget's currency.
+    assert_eq!(income["currency"], "EUR");
+    let income_uri = format!("/api/income/{}", income["id"].as_str().unwrap());
+
+    let req = authed("POST", "/api/income", &token)
+        .set_json(json!({ "source": "Refund", "amount": "20", "date": "2024-08-01" }))
+        .to_request();
+    let refund: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(refund["currency"], "USD");
+    assert_eq!(refund["budget_id"], Value::Null);
+
+    let req = authed(
+        "GET",
+        &format!("/api/income?budget_id={}", budget_id),
+        &token,
+    )
+    .to_request();
+    let listed: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(listed["pagination"]["total_items"], 1);
+    assert_eq!(listed["data"][0]["source"], "Employer");
+
+    let req = authed("GET", "/api/income?sort=amount&order=desc&q=fun", &token).to_request();
+    let listed: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(listed["data"][0]["source"], "Refund");
+
+    // Someone else's income and budgets are out of reach.
+    let req = authed("GET", &income_uri, &other).to_request();
+    let res = test::call_service(&app, req).await;
+    assert_eq!(res.status(), StatusCode::NOT_FOUND);
+    let req = authed("POST", "/api/income", &other)
+        .set_json(json!({ "source": "Theft", "amount": "1", "budget_id": budget_id }))
+        .to_request();
+    let res = test::call_service(&app, req).await;
+    assert_eq!(res.status(), StatusCode::UNPROCESSABLE_ENTITY);
+    let error: Value = test::read_body_json(res).await;
+    assert_eq!(error["error"]["details"][0]["field"], "budget_id");
+
+    let req = authed("PUT", &income_uri, &token)
+        .set_json(json!({ "amount": "2600.50", "budget_id": null }))
+        .to_request();
+    let income: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(income["amount"], "2600.50");
+    assert_eq!(income["budget_id"], Value::Null);
+
+    let req = authed("PUT", &income_uri, &token)
+        .set_json(json!({ "budget_id": budget_id }))
+        .to_request();
+    let income: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(income["budget_id"], budget_id.as_str());
+
+    // Deleting the budget keeps the income but unlinks it.
+    let req = authed("DELETE", &format!("/api/budget/{}", budget_id), &token).to_request();
+    let res = test::call_service(&app, req).await;
+    assert_eq!(res.status(), StatusCode::OK);
+    let req = authed("GET", &income_uri, &token).to_request();
+    let income: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(income["budget_id"], Value::Null);
+
+    let req = authed("DELETE", &income_uri, &token).to_request();
+    let res = test::call_service(&app, req).await;
+    assert_eq!(res.status(), StatusCode::OK);
+    let req = authed("GET", &income_uri, &token).to_request();
+    let res = test::call_service(&app, req).await;
+    assert_eq!(res.status(), StatusCode::NOT_FOUND);
+}
+
+#[actix_web::test]
+async fn cash_flow_nets_income_against_expenses_per_period() {
+    let state = test_state().await;
+    let rates = currency::parse_csv("EUR,USD,2024-07-01,1.1\n").unwrap();
+    currency::store_rates(&state.db, rates).await.unwrap();
+    let app = test::init_service(App::new().app_data(state.clone()).configure(handler::init)).await;
+    let token = login(&app, "wendy").await;
+    let other = login(&app, "xavier").await;
+
+    let req = authed("POST", "/api/budget", &token)
+        .set_json(json!({ "name": "Living", "total_amount": "1000", "starts_on": "2024-07-01" }))
+        .to_request();
+    let budget: Value = test::call_and_read_body_json(&app, req).await;
+    let budget_id = budget["id"].as_str().unwrap().to_string();
+    for (date, amount) in [("2024-07-10", "100"), ("2024-08-05", "300")] {
+        let req = authed(
+            "POST",
+            &format!("/api/budget/{}/expenses", budget_id),
+            &token,
+        )
+        .set_json(json!({ "amount": amount, "description": "Rent", "date": date }))
+        .to_request();
+        let res = test::call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::OK);
+    }
+    for (date, amount, currency) in [("2024-07-25", "2000", "USD"), ("2024-08-01", "100", "EUR")] {
+        let req = authed("POST", "/api/income", &token)
+            .set_json(json!({
+                "source": "Employer",
+                "amount": amount,
+                "currency": currency,
+                "date": date,
+            }))
+            .to_request();
+        let res = test::call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::OK);
+    }
+    // Other users' money does not show up.
+    let req = authed("POST", "/api/income", &other)
+        .set_json(json!({ "source": "Lottery", "amount": "1000000", "date": "2024-08-01" }))
+        .to_request();
+    let res = test::call_service(&app, req).await;
+    assert_eq!(res.status(), StatusCode::OK);
+
+    let req = authed("GET", "/api/reports/cash-flow?from=2024-07-15", &token).to_request();
+    let report: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(
+        report,
+        json!({
+            "currency": "USD",
+            "period": "monthly",
+            "periods": [
+                {
+                    "start": "2024-07-01",
+                    "end": "2024-07-31",
+                    "income": "2000.00",
+                    "expenses": "100.00",
+                    "net": "1900.00",
+                },
+                {
+                    "start": "2024-08-01",
+                    "end": "2024-08-31",
+                    "income": "110.00",
+                    "expenses": "300.00",
+                    "net": "-190.00",
+                },
+            ],
+            "income": "2110.00",
+            "expenses": "400.00",
+            "net": "1710.00",
+        })
+    );
+
+    // By default the last twelve periods up to today are covered.
+    let req = authed("GET", "/api/reports/cash-flow?period=quarterly", &token).to_request();
+    let report: Value = test::call_and_read_body_json(&app, req).await;
+    let periods = report["periods"].as_array().unwrap();
+    assert_eq!(periods.len(), 12);
+    assert_eq!(periods[0]["start"], "2021-10-01");
+    assert_eq!(periods[11]["end"], "2024-09-30");
+    assert_eq!(periods[11]["net"], "1710.00");
+
+    let req = authed(
+        "GET",
+        "/api/reports/cash-flow?period=weekly&from=2024-08-01&to=2024-08-01&currency=EUR",
+        &token,
+    )
+    .to_request();
+    let report: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(report["periods"][0]["start"], "2024-07-29");
+    assert_eq!(report["periods"][0]["end"], "2024-08-04");
+    assert_eq!(report["income"], "100.00");
+
+    for (query, field) in [
+        ("period=custom", "period"),
+        ("from=2024-08-01&to=2024-07-01", "to"),
+        ("period=weekly&from=2020-01-01", "from"),
+        ("currency=XXX", "currency"),
+    ] {
+        let req = authed("GET", &format!("/api/reports/cash-flow?{}", query), &token).to_request();
+        let res = test::call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::UNPROCESSABLE_ENTITY, "{}", query);
+        let error: Value = test::read_body_json(res).await;
+        assert_eq!(error["error"]["details"][0]["field"], field, "{}", query);
+    }
+    let req = authed(
+        "GET",
+        &format!("/api/reports/cash-flow?budget_id={}", budget_id),
+        &other,
+    )
+    .to_request();
+    let res = test::call_service(&app, req).await;
+    assert_eq!(res.status(), StatusCode::UNPROCESSABLE_ENTITY);
+}
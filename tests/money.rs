@@ -0,0 +1,117 @@
+mod common;
+
+use actix_web::{http::StatusCode, test, App};
+use entities::money::{Money, MoneyError};
+use migration::{Migrator, MigratorTrait};
+use pbudget::handler;
+use sea_orm::{ConnectionTrait, Database, DbBackend, EntityTrait, Statement};
+use serde_json::{json, Value};
+use uuid::Uuid;
+
+use common::{authed, login, test_state};
+
+#[actix_web::test]
+async fn money_parses_and_formats_exactly() {
+    let a: Money = "0.10".parse().unwrap();
+    let b: Money = "0.20".parse().unwrap();
+    assert_eq!((a + b).to_string(), "0.30");
+    assert_eq!(Money::from_minor_units(1200).to_string(), "12.00");
+    assert_eq!("1.500".parse::<Money>().unwrap().minor_units(), 150);
+
+    assert_eq!("1.005".parse::<Money>(), Err(MoneyError::TooPrecise));
+    assert_eq!("abc".parse::<Money>(), Err(MoneyError::Invalid));
+}
+
+#[actix_web::test]
+async fn money_is_written_as_a_string_and_read_from_either() {
+    let amount: Money = serde_json::from_str("89.99").unwrap();
+    assert_eq!(amount.minor_units(), 8999);
+    assert_eq!(serde_json::to_string(&amount).unwrap(), "\"89.99\"");
+
+    let amount: Money = serde_json::from_str("\"89.99\"").unwrap();
+    assert_eq!(amount.minor_units(), 8999);
+
+    assert!(serde_json::from_str::<Money>("0.001").is_err());
+}
+
+#[actix_web::test]
+async fn amounts_round_trip_without_rounding_errors() {
+    let state = test_state().await;
+    let app = test::init_service(App::new().app_data(state.clone()).configure(handler::init)).await;
+    let token = login(&app, "dave").await;
+
+    let req = authed("POST", "/api/budget", &token)
+        .set_json(json!({ "name": "Coffee", "total_amount": "0.30" }))
+        .to_request();
+    let budget: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(budget["total_amount"], "0.30");
+    let expenses_uri = format!("/api/budget/{}/expenses", budget["id"].as_str().unwrap());
+
+    for amount in [json!(0.1), json!("0.20")] {
+        let req = authed("POST", &expenses_uri, &token)
+            .set_json(json!({ "amount": amount, "description": "Espresso" }))
+            .to_request();
+        let res = test::call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::OK);
+    }
+
+    let req = authed("GET", &expenses_uri, &token).to_request();
+    let expenses: Value = test::call_and_read_body_json(&app, req).await;
+    let total: Money = expenses["data"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|e| e["amount"].as_str().unwrap().parse::<Money>().unwrap())
+        .sum();
+    assert_eq!(total.to_string(), budget["total_amount"].as_str().unwrap());
+
+    let req = authed("POST", &expenses_uri, &token)
+        .set_json(json!({ "amount": "0.005", "description": "Sugar" }))
+        .to_request();
+    let res = test::call_service(&app, req).await;
+    assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+}
+
+#[actix_web::test]
+async fn migration_converts_existing_amounts_to_cents() {
+    let db = Database::connect("sqlite::memory:").await.unwrap();
+    Migrator::up(&db, Some(3)).await.unwrap();
+
+    let user_id = Uuid::new_v4();
+    let budget_id = Uuid::new_v4();
+    for (sql, values) in [
+        (
+            "INSERT INTO users (id, username, password_hash, email) VALUES (?, 'erin', '', 'erin@example.com')",
+            vec![user_id.into()],
+        ),
+        (
+            "INSERT INTO budget (id, user_id, name, total_amount, created_at, updated_at) \
+             VALUES (?, ?, 'Food', 12.3, '2024-01-01 10:00:00', '2024-01-01 10:00:00')",
+            vec![budget_id.into(), user_id.into()],
+        ),
+        (
+            "INSERT INTO expense (id, budget_id, amount, description, date, created_at, updated_at) \
+             VALUES (?, ?, 0.1 + 0.2, 'Snack', '2024-01-01', '2024-01-01 10:00:00', '2024-01-01 10:00:00')",
+            vec![Uuid::new_v4().into(), budget_id.into()],
+        ),
+    ] {
+        db.execute(Statement::from_sql_and_values(DbBackend::Sqlite, sql, values))
+            .await
+            .unwrap();
+    }
+
+    Migrator::up(&db, None).await.unwrap();
+
+    let budget = entities::budget::Entity::find_by_id(budget_id)
+        .one(&db)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(budget.total_amount.minor_units(), 1230);
+    let expense = entities::expense::Entity::find()
+        .one(&db)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(expense.amount.minor_units(), 30);
+}
@@ -0,0 +1,100 @@
+mod common;
+
+use actix_web::{http::StatusCode, test, App};
+use pbudget::handler;
+use serde_json::{json, Value};
+
+use common::{authed, login, test_state};
+
+#[actix_web::test]
+async fn users_cannot_touch_each_others_budgets_or_expenses() {
+    let state = test_state().await;
+    let app = test::init_service(App::new().app_data(state.clone()).configure(handler::init)).await;
+    let owner = login(&app, "ivan").await;
+    let intruder = login(&app, "judy").await;
+
+    let req = authed("POST", "/api/budget", &owner)
+        .set_json(json!({ "name": "Savings", "total_amount": "1000.00" }))
+        .to_request();
+    let budget: Value = test::call_and_read_body_json(&app, req).await;
+    let budget_uri = format!("/api/budget/{}", budget["id"].as_str().unwrap());
+    let expenses_uri = format!("{}/expenses", budget_uri);
+
+    let req = authed("POST", &expenses_uri, &owner)
+        .set_json(json!({ "amount": "25.00", "description": "Deposit fee" }))
+        .to_request();
+    let expense: Value = test::call_and_read_body_json(&app, req).await;
+    let expense_uri = format!("{}/{}", expenses_uri, expense["id"].as_str().unwrap());
+
+    // Warm the cache so the intruder cannot ride on cached entries either.
+    for uri in [&budget_uri, &expense_uri] {
+        let res = test::call_service(&app, authed("GET", uri, &owner).to_request()).await;
+        assert_eq!(res.status(), StatusCode::OK);
+    }
+
+    let budget_change = json!({ "name": "Mine now", "total_amount": "1.00" });
+    let expense_change = json!({ "amount": "9999.00", "description": "Mine now" });
+    let attempts = [
+        ("GET", &budget_uri, None),
+        ("PUT", &budget_uri, Some(&budget_change)),
+        ("DELETE", &budget_uri, None),
+        ("GET", &expenses_uri, None),
+        ("POST", &expenses_uri, Some(&expense_change)),
+        ("GET", &expense_uri, None),
+        ("PUT", &expense_uri, Some(&expense_change)),
+        ("DELETE", &expense_uri, None),
+    ];
+    for (method, uri, body) in attempts {
+        let mut req = authed(method, uri, &intruder);
+        if let Some(body) = body {
+            req = req.set_json(body);
+        }
+        let res = test::call_service(&app, req.to_request()).await;
+        assert_eq!(res.status(), StatusCode::NOT_FOUND, "{} {}", method, uri);
+        let body: Value = test::read_body_json(res).await;
+        assert_eq!(body["error"]["message"], "budget not found");
+    }
+
+    let req = authed("GET", &budget_uri, &owner).to_request();
+    let unchanged: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(unchanged, budget);
+
+    let req = authed("GET", &expenses_uri, &owner).to_request();
+    let expenses: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(expenses["data"], json!([expense]));
+
+    let req = authed("GET", "/api/budget", &intruder).to_request();
+    let budgets: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(budgets["data"], json!([]));
+}
+
+#[actix_web::test]
+async fn expenses_are_scoped_to_the_budget_in_the_path() {
+    let state = test_state().await;
+    let app = test::init_service(App::new().app_data(state.clone()).configure(handler::init)).await;
+    let token = login(&app, "mallory").await;
+
+    let mut budget_uris = Vec::new();
+    for name in ["Food", "Fun"] {
+        let req = authed("POST", "/api/budget", &token)
+            .set_json(json!({ "name": name, "total_amount": "50" }))
+            .to_request();
+        let budget: Value = test::call_and_read_body_json(&app, req).await;
+        budget_uris.push(format!("/api/budget/{}", budget["id"].as_str().unwrap()));
+    }
+
+    let req = authed("POST", &format!("{}/expenses", budget_uris[0]), &token)
+        .set_json(json!({ "amount": "5", "description": "Lunch" }))
+        .to_request();
+    let expense: Value = test::call_and_read_body_json(&app, req).await;
+
+    let wrong_uri = format!(
+        "{}/expenses/{}",
+        budget_uris[1],
+        expense["id"].as_str().unwrap()
+    );
+    for method in ["GET", "DELETE"] {
+        let res = test::call_service(&app, authed(method, &wrong_uri, &token).to_request()).await;
+        assert_eq!(res.status(), StatusCode::NOT_FOUND, "{}", method);
+    }
+}
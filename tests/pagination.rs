@@ -0,0 +1,202 @@
+mod common;
+
+use actix_web::{
+    http::{header, StatusCode},
+    test, App,
+};
+use pbudget::handler;
+use serde_json::{json, Value};
+
+use common::{authed, login, test_state};
+
+fn field(items: &Value, name: &str) -> Vec<String> {
+    items["data"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|item| item[name].as_str().unwrap().to_string())
+        .collect()
+}
+
+#[actix_web::test]
+async fn expenses_are_paginated_sorted_and_filtered() {
+    let state = test_state().await;
+    let app = test::init_service(App::new().app_data(state.clone()).configure(handler::init)).await;
+    let token = login(&app, "xavier").await;
+
+    let req = authed("POST", "/api/budget", &token)
+        .set_json(json!({ "name": "Living", "total_amount": "1000" }))
+        .to_request();
+    let budget: Value = test::call_and_read_body_json(&app, req).await;
+    let expenses_uri = format!("/api/budget/{}/expenses", budget["id"].as_str().unwrap());
+
+    for (description, amount, date) in [
+        ("Rent share", "400", "2024-08-01"),
+        ("Groceries", "62.10", "2024-08-03"),
+        ("Coffee beans", "14", "2024-08-07"),
+        ("Groceries", "48.95", "2024-08-10"),
+        ("100% juice", "3.50", "2024-08-12"),
+    ] {
+        let req = authed("POST", &expenses_uri, &token)
+            .set_json(json!({ "amount": amount, "description": description, "date": date }))
+            .to_request();
+        let res = test::call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::OK);
+    }
+
+    let uri = format!("{}?sort=amount&order=desc&limit=2", expenses_uri);
+    let res = test::call_service(&app, authed("GET", &uri, &token).to_request()).await;
+    assert_eq!(res.status(), StatusCode::OK);
+    let link = res
+        .headers()
+        .get(header::LINK)
+        .unwrap()
+        .to_str()
+        .unwrap()
+        .to_string();
+    assert_eq!(
+        link,
+        format!(
+            "<{0}?sort=amount&order=desc&limit=2&page=1>; rel=\"first\", \
+             <{0}?sort=amount&order=desc&limit=2&page=2>; rel=\"next\", \
+             <{0}?sort=amount&order=desc&limit=2&page=3>; rel=\"last\"",
+            expenses_uri
+        )
+    );
+    let page: Value = test::read_body_json(res).await;
+    assert_eq!(field(&page, "amount"), ["400.00", "62.10"]);
+    assert_eq!(
+        page["pagination"],
+        json!({ "page": 1, "limit": 2, "total_items": 5, "total_pages": 3 })
+    );
+
+    let uri = format!("{}?page=3&sort=amount&order=desc&limit=2", expenses_uri);
+    let res = test::call_service(&app, authed("GET", &uri, &token).to_request()).await;
+    let link = res
+        .headers()
+        .get(header::LINK)
+        .unwrap()
+        .to_str()
+        .unwrap()
+        .to_string();
+    assert!(link.contains("page=2>; rel=\"prev\""), "{}", link);
+    assert!(!link.contains("rel=\"next\""), "{}", link);
+    let page: Value = test::read_body_json(res).await;
+    assert_eq!(field(&page, "amount"), ["3.50"]);
+
+    // Dates sort oldest first by default.
+    let req = authed("GET", &expenses_uri, &token).to_request();
+    let page: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(
+        field(&page, "date"),
+        [
+            "2024-08-01",
+            "2024-08-03",
+            "2024-08-07",
+            "2024-08-10",
+            "2024-08-12"
+        ]
+    );
+
+    for (query, expected) in [
+        (
+            "from=2024-08-03&to=2024-08-10",
+            vec!["62.10", "14.00", "48.95"],
+        ),
+        (
+            "min_amount=14&max_amount=62.10",
+            vec!["62.10", "14.00", "48.95"],
+        ),
+        ("q=groc", vec!["62.10", "48.95"]),
+        ("q=groc&max_amount=50", vec!["48.95"]),
+        // Wildcards in the search term match literally.
+        ("q=0%25", vec!["3.50"]),
+        ("q=_", vec![]),
+    ] {
+        let uri = format!("{}?{}", expenses_uri, query);
+        let page: Value =
+            test::call_and_read_body_json(&app, authed("GET", &uri, &token).to_request()).await;
+        assert_eq!(field(&page, "amount"), expected, "{}", query);
+    }
+
+    for (query, status, field) in [
+        ("limit=0", StatusCode::UNPROCESSABLE_ENTITY, Some("limit")),
+        ("limit=101", StatusCode::UNPROCESSABLE_ENTITY, Some("limit")),
+        ("page=0", StatusCode::UNPROCESSABLE_ENTITY, Some("page")),
+        (
+            "page=18446744073709551615&limit=100",
+            StatusCode::UNPROCESSABLE_ENTITY,
+            Some("page"),
+        ),
+        (
+            "from=2024-08-10&to=2024-08-01",
+            StatusCode::UNPROCESSABLE_ENTITY,
+            Some("to"),
+        ),
+        (
+            "min_amount=10&max_amount=5",
+            StatusCode::UNPROCESSABLE_ENTITY,
+            Some("max_amount"),
+        ),
+        ("sort=description", StatusCode::BAD_REQUEST, None),
+        ("min_amount=cheap", StatusCode::BAD_REQUEST, None),
+    ] {
+        let uri = format!("{}?{}", expenses_uri, query);
+        let res = test::call_service(&app, authed("GET", &uri, &token).to_request()).await;
+        assert_eq!(res.status(), status, "{}", query);
+        if let Some(field) = field {
+            let error: Value = test::read_body_json(res).await;
+            assert_eq!(error["error"]["details"][0]["field"], field, "{}", query);
+        }
+    }
+}
+
+#[actix_web::test]
+async fn budgets_are_paginated_sorted_and_filtered() {
+    let state = test_state().await;
+    let app = test::init_service(App::new().app_data(state.clone()).configure(handler::init)).await;
+    let token = login(&app, "yvonne").await;
+
+    for (name, amount, starts_on) in [
+        ("Groceries", "300", "2024-01-01"),
+        ("Holiday", "1500", "2024-06-01"),
+        ("Gifts", "200", "2024-11-01"),
+    ] {
+        let req = authed("POST", "/api/budget", &token)
+            .set_json(json!({ "name": name, "total_amount": amount, "starts_on": starts_on }))
+            .to_request();
+        let res = test::call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::OK);
+    }
+
+    for (query, expected) in [
+        ("", vec!["Groceries", "Holiday", "Gifts"]),
+        ("sort=amount", vec!["Gifts", "Groceries", "Holiday"]),
+        (
+            "sort=date&order=desc",
+            vec!["Gifts", "Holiday", "Groceries"],
+        ),
+        ("q=G&sort=amount&order=desc", vec!["Groceries", "Gifts"]),
+        ("from=2024-03-01", vec!["Holiday", "Gifts"]),
+        ("min_amount=250&max_amount=1000", vec!["Groceries"]),
+        ("limit=1&page=2", vec!["Holiday"]),
+        ("page=4", vec![]),
+    ] {
+        let uri = format!("/api/budget?{}", query);
+        let page: Value =
+            test::call_and_read_body_json(&app, authed("GET", &uri, &token).to_request()).await;
+        assert_eq!(field(&page, "name"), expected, "{}", query);
+    }
+
+    // The last page whose offset still fits the database is empty, not an
+    // error; one more is rejected.
+    let uri = "/api/budget?page=92233720368547759&limit=100";
+    let page: Value =
+        test::call_and_read_body_json(&app, authed("GET", uri, &token).to_request()).await;
+    assert_eq!(field(&page, "name"), Vec::<&str>::new());
+    let uri = "/api/budget?page=92233720368547760&limit=100";
+    let res = test::call_service(&app, authed("GET", uri, &token).to_request()).await;
+    assert_eq!(res.status(), StatusCode::UNPROCESSABLE_ENTITY);
+    let error: Value = test::read_body_json(res).await;
+    assert_eq!(error["error"]["details"][0]["field"], "page");
+}
@@ -0,0 +1,201 @@
+mod common;
+
+use actix_web::{http::StatusCode, test, App};
+use chrono::{Days, NaiveDate, TimeZone, Utc};
+use entities::{budget, money::Money, sea_orm_active_enums::BudgetPeriod};
+use pbudget::{handler, utility::period};
+use serde_json::{json, Value};
+use uuid::Uuid;
+
+use common::{authed, login, test_state};
+
+fn budget(period: BudgetPeriod, starts_on: NaiveDate) -> budget::Model {
+    let now = Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap();
+    budget::Model {
+        id: Uuid::new_v4(),
+        user_id: Uuid::new_v4(),
+        name: "Test".to_string(),
+        total_amount: Money::from_minor_units(10_000),
+        currency: "USD".to_string(),
+        period,
+        starts_on,
+        ends_on: None,
+        rollover: false,
+        created_at: now,
+        updated_at: now,
+    }
+}
+
+fn date(y: i32, m: u32, d: u32) -> NaiveDate {
+    NaiveDate::from_ymd_opt(y, m, d).unwrap()
+}
+
+#[actix_web::test]
+async fn every_date_falls_in_exactly_the_period_it_is_indexed_to() {
+    for kind in [
+        BudgetPeriod::Weekly,
+        BudgetPeriod::Monthly,
+        BudgetPeriod::Quarterly,
+        BudgetPeriod::Yearly,
+    ] {
+        let budget = budget(kind, date(2023, 1, 31));
+        let mut day = budget.starts_on;
+        while day < date(2026, 1, 1) {
+            let n = period::index_of(&budget, day);
+            let p = period::nth(&budget, n).unwrap();
+            assert!(p.start <= day && day <= p.end, "{:?} {} {:?}", kind, day, p);
+            day = day + Days::new(1);
+        }
+    }
+
+    let monthly = budget(BudgetPeriod::Monthly, date(2024, 1, 31));
+    assert_eq!(
+        period::nth(&monthly, 1).unwrap(),
+        period::Period {
+            start: date(2024, 2, 29),
+            end: date(2024, 3, 30)
+        }
+    );
+    assert_eq!(period::nth(&monthly, 2).unwrap().start, date(2024, 3, 31));
+}
+
+#[actix_web::test]
+async fn periods_track_remaining_amounts_with_rollover() {
+    let state = test_state().await;
+    let app = test::init_service(App::new().app_data(state.clone()).configure(handler::init)).await;
+    let token = login(&app, "sybil").await;
+
+    let req = authed("POST", "/api/budget", &token)
+        .set_json(json!({
+            "name": "Eating out",
+            "total_amount": "100",
+            "period": "monthly",
+            "starts_on": "2024-06-01",
+            "rollover": true,
+        }))
+        .to_request();
+    let budget: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(budget["period"], "monthly");
+    let budget_uri = format!("/api/budget/{}", budget["id"].as_str().unwrap());
+
+    for (date, amount) in [
+        ("2024-05-30", "999"),
+        ("2024-06-10", "80"),
+        ("2024-07-05", "150"),
+        ("2024-08-02", "10"),
+        ("2024-09-01", "5"),
+    ] {
+        let req = authed("POST", &format!("{}/expenses", budget_uri), &token)
+            .set_json(json!({ "amount": amount, "description": "Meal", "date": date }))
+            .to_request();
+        let res = test::call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::OK);
+    }
+
+    // The test clock is fixed at 2024-08-15.
+    let req = authed("GET", &format!("{}/periods/current", budget_uri), &token).to_request();
+    let current: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(
+        current,
+        json!({
+            "start": "2024-08-01",
+            "end": "2024-08-31",
+            "allowance": "100.00",
+            "carried_over": "-30.00",
+            "available": "70.00",
+            "spent": "10.00",
+            "remaining": "60.00",
+        })
+    );
+
+    let req = authed("GET", &format!("{}/periods", budget_uri), &token).to_request();
+    let history: Value = test::call_and_read_body_json(&app, req).await;
+    let summary = |p: &Value| {
+        (
+            p["start"].as_str().unwrap().to_string(),
+            p["carried_over"].as_str().unwrap().to_string(),
+            p["remaining"].as_str().unwrap().to_string(),
+        )
+    };
+    let history: Vec<_> = history.as_array().unwrap().iter().map(summary).collect();
+    assert_eq!(
+        history,
+        [
+            ("2024-08-01".into(), "-30.00".into(), "60.00".into()),
+            ("2024-07-01".into(), "20.00".into(), "-30.00".into()),
+            ("2024-06-01".into(), "0.00".into(), "20.00".into()),
+        ]
+    );
+
+    let req = authed("GET", &format!("{}/periods?limit=1", budget_uri), &token).to_request();
+    let history: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(history.as_array().unwrap().len(), 1);
+
+    let req = authed("PUT", &budget_uri, &token)
+        .set_json(json!({ "rollover": false }))
+        .to_request();
+    let res = test::call_service(&app, req).await;
+    assert_eq!(res.status(), StatusCode::OK);
+
+    let req = authed("GET", &format!("{}/periods/current", budget_uri), &token).to_request();
+    let current: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(current["available"], "100.00");
+    assert_eq!(current["remaining"], "90.00");
+}
+
+#[actix_web::test]
+async fn custom_periods_need_an_end_date() {
+    let state = test_state().await;
+    let app = test::init_service(App::new().app_data(state.clone()).configure(handler::init)).await;
+    let token = login(&app, "trent").await;
+
+    for (body, field) in [
+        (
+            json!({ "period": "custom", "starts_on": "2024-08-01" }),
+            "ends_on",
+        ),
+        (
+            json!({ "period": "custom", "starts_on": "2024-08-01", "ends_on": "2024-07-01" }),
+            "ends_on",
+        ),
+        (
+            json!({ "period": "weekly", "ends_on": "2024-09-01" }),
+            "ends_on",
+        ),
+    ] {
+        let mut body = body;
+        body["name"] = json!("Holiday");
+        body["total_amount"] = json!("1500");
+        let req = authed("POST", "/api/budget", &token)
+            .set_json(&body)
+            .to_request();
+        let res = test::call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::UNPROCESSABLE_ENTITY, "{}", body);
+        let error: Value = test::read_body_json(res).await;
+        assert_eq!(error["error"]["details"][0]["field"], field);
+    }
+
+    let req = authed("POST", "/api/budget", &token)
+        .set_json(json!({
+            "name": "Holiday",
+            "total_amount": "1500",
+            "period": "custom",
+            "starts_on": "2024-08-01",
+            "ends_on": "2024-08-21",
+        }))
+        .to_request();
+    let budget: Value = test::call_and_read_body_json(&app, req).await;
+    let budget_uri = format!("/api/budget/{}", budget["id"].as_str().unwrap());
+
+    let req = authed("GET", &format!("{}/periods", budget_uri), &token).to_request();
+    let history: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(history.as_array().unwrap().len(), 1);
+    assert_eq!(history[0]["end"], "2024-08-21");
+
+    // Switching to a recurring period drops the custom end date.
+    let req = authed("PUT", &budget_uri, &token)
+        .set_json(json!({ "period": "weekly" }))
+        .to_request();
+    let budget: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(budget["ends_on"], Value::Null);
+}
@@ -0,0 +1,272 @@
+mod common;
+
+use actix_web::{
+    body::MessageBody,
+    dev::{Service, ServiceResponse},
+    http::StatusCode,
+    test, App, Error,
+};
+use chrono::{NaiveDate, TimeZone, Utc};
+use entities::{expense, recurring_expense};
+use pbudget::{
+    handler,
+    utility::{recurring, scheduler},
+};
+use sea_orm::{ColumnTrait, EntityTrait, PaginatorTrait, QueryFilter};
+use serde_json::{json, Value};
+use uuid::Uuid;
+
+use common::{authed, login, test_state};
+
+fn date(y: i32, m: u32, d: u32) -> NaiveDate {
+    NaiveDate::from_ymd_opt(y, m, d).unwrap()
+}
+
+async fn expense_dates<S, B>(app: &S, uri: &str, token: &str) -> Vec<String>
+where
+    S: Service<actix_http::Request, Response = ServiceResponse<B>, Error = Error>,
+    B: MessageBody,
+{
+    let req = authed("GET", &format!("{}?limit=100", uri), token).to_request();
+    let page: Value = test::call_and_read_body_json(app, req).await;
+    page["data"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|e| e["date"].as_str().unwrap().to_string())
+        .collect()
+}
+
+#[actix_web::test]
+async fn templates_catch_up_on_missed_occurrences_exactly_once() {
+    let state = test_state().await;
+    let app = test::init_service(App::new().app_data(state.clone()).configure(handler::init)).await;
+    let token = login(&app, "bruno").await;
+
+    let req = authed("POST", "/api/budget", &token)
+        .set_json(json!({ "name": "Home", "total_amount": "3000", "starts_on": "2024-01-01" }))
+        .to_request();
+    let budget: Value = test::call_and_read_body_json(&app, req).await;
+    let budget_uri = format!("/api/budget/{}", budget["id"].as_str().unwrap());
+    let expenses_uri = format!("{}/expenses", budget_uri);
+
+    // The test clock is fixed at 2024-08-15.
+    let req = authed("POST", &format!("{}/recurring", budget_uri), &token)
+        .set_json(json!({
+            "amount": "1200",
+            "description": "Rent",
+            "frequency": "monthly",
+            "starts_on": "2024-05-31",
+        }))
+        .to_request();
+    let rent: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(rent["occurrences"], 3);
+    assert_eq!(rent["next_occurrence"], "2024-08-31");
+    assert_eq!(rent["currency"], "USD");
+    assert_eq!(
+        expense_dates(&app, &expenses_uri, &token).await,
+        ["2024-05-31", "2024-06-30", "2024-07-31"]
+    );
+
+    // Running again on the same day creates nothing new.
+    let now = state.clock.now();
+    assert_eq!(
+        recurring::materialize_due(&state.db, now.date_naive(), now)
+            .await
+            .unwrap(),
+        0
+    );
+
+    // After six weeks of downtime both missed occurrences are created.
+    let later = Utc.with_ymd_and_hms(2024, 9, 30, 6, 0, 0).unwrap();
+    assert_eq!(
+        recurring::materialize_due(&state.db, later.date_naive(), later)
+            .await
+            .unwrap(),
+        2
+    );
+    assert_eq!(
+        recurring::materialize_due(&state.db, later.date_naive(), later)
+            .await
+            .unwrap(),
+        0
+    );
+    assert_eq!(
+        expense_dates(&app, &expenses_uri, &token).await,
+        [
+            "2024-05-31",
+            "2024-06-30",
+            "2024-07-31",
+            "2024-08-31",
+            "2024-09-30"
+        ]
+    );
+
+    // A stale copy of the template cannot create its occurrences again.
+    let mut stale = recurring_expense::Entity::find_by_id(
+        Uuid::parse_str(rent["id"].as_str().unwrap()).unwrap(),
+    )
+    .one(&state.db)
+    .await
+    .unwrap()
+    .unwrap();
+    stale.occurrences = 3;
+    assert_eq!(
+        recurring::materialize(&state.db, &stale, later.date_naive(), later)
+            .await
+            .unwrap(),
+        0
+    );
+
+    let rent_uri = format!("{}/recurring/{}", budget_uri, rent["id"].as_str().unwrap());
+    let req = authed("PUT", &rent_uri, &token)
+        .set_json(json!({ "ends_on": "2024-06-30" }))
+        .to_request();
+    let ended: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(ended["next_occurrence"], Value::Null);
+
+    let req = authed("GET", &format!("{}?limit=1", expenses_uri), &token).to_request();
+    let page: Value = test::call_and_read_body_json(&app, req).await;
+    let expense_uri = format!(
+        "{}/{}",
+        expenses_uri,
+        page["data"][0]["id"].as_str().unwrap()
+    );
+    let req = authed("GET", &expense_uri, &token).to_request();
+    let created: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(created["recurring_expense_id"], rent["id"]);
+
+    let res = test::call_service(&app, authed("DELETE", &rent_uri, &token).to_request()).await;
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(expense_dates(&app, &expenses_uri, &token).await.len(), 5);
+    // The cached copy from before does not point at the deleted template.
+    let req = authed("GET", &expense_uri, &token).to_request();
+    let kept: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(kept["recurring_expense_id"], Value::Null);
+    let res = test::call_service(&app, authed("GET", &rent_uri, &token).to_request()).await;
+    assert_eq!(res.status(), StatusCode::NOT_FOUND);
+}
+
+#[actix_web::test]
+async fn templates_stop_after_count_or_end_date() {
+    let state = test_state().await;
+    let app = test::init_service(App::new().app_data(state.clone()).configure(handler::init)).await;
+    let token = login(&app, "carla").await;
+
+    let req = authed("POST", "/api/budget", &token)
+        .set_json(json!({ "name": "Subscriptions", "total_amount": "100" }))
+        .to_request();
+    let budget: Value = test::call_and_read_body_json(&app, req).await;
+    let recurring_uri = format!("/api/budget/{}/recurring", budget["id"].as_str().unwrap());
+
+    let req = authed("POST", &recurring_uri, &token)
+        .set_json(json!({
+            "amount": "5",
+            "description": "Gym",
+            "frequency": "weekly",
+            "interval": 2,
+            "starts_on": "2024-06-01",
+            "count": 3,
+        }))
+        .to_request();
+    let gym: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(gym["occurrences"], 3);
+    assert_eq!(gym["next_occurrence"], Value::Null);
+
+    let req = authed("POST", &recurring_uri, &token)
+        .set_json(json!({
+            "amount": "1",
+            "description": "Paper",
+            "frequency": "daily",
+            "starts_on": "2024-08-20",
+            "ends_on": "2024-08-22",
+        }))
+        .to_request();
+    let paper: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(paper["occurrences"], 0);
+    assert_eq!(paper["next_occurrence"], "2024-08-20");
+
+    let later = Utc.with_ymd_and_hms(2025, 1, 1, 0, 0, 0).unwrap();
+    recurring::materialize_due(&state.db, later.date_naive(), later)
+        .await
+        .unwrap();
+    let paper_id = Uuid::parse_str(paper["id"].as_str().unwrap()).unwrap();
+    let created = expense::Entity::find()
+        .filter(expense::Column::RecurringExpenseId.eq(paper_id))
+        .count(&state.db)
+        .await
+        .unwrap();
+    assert_eq!(created, 3);
+
+    for (body, field) in [
+        (json!({ "count": 2, "ends_on": "2024-09-01" }), "count"),
+        (json!({ "ends_on": "2024-05-01" }), "ends_on"),
+        (json!({ "interval": 0 }), "interval"),
+    ] {
+        let mut body = body;
+        body["amount"] = json!("1");
+        body["description"] = json!("x");
+        body["frequency"] = json!("monthly");
+        body["starts_on"] = json!("2024-06-01");
+        let req = authed("POST", &recurring_uri, &token)
+            .set_json(&body)
+            .to_request();
+        let res = test::call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::UNPROCESSABLE_ENTITY, "{}", body);
+        let error: Value = test::read_body_json(res).await;
+        assert_eq!(error["error"]["details"][0]["field"], field);
+    }
+}
+
+#[actix_web::test]
+async fn scheduler_materializes_due_templates_in_the_background() {
+    let state = test_state().await;
+    let app = test::init_service(App::new().app_data(state.clone()).configure(handler::init)).await;
+    let token = login(&app, "dora").await;
+
+    let req = authed("POST", "/api/budget", &token)
+        .set_json(json!({ "name": "Bills", "total_amount": "500" }))
+        .to_request();
+    let budget: Value = test::call_and_read_body_json(&app, req).await;
+    let budget_id = Uuid::parse_str(budget["id"].as_str().unwrap()).unwrap();
+
+    // A template left behind by a server that went down before it was due.
+    let now = state.clock.now();
+    let template = recurring_expense::Model {
+        id: Uuid::new_v4(),
+        budget_id,
+        amount: "30".parse().unwrap(),
+        currency: "USD".to_string(),
+        description: "Phone".to_string(),
+        category_id: None,
+        frequency: entities::sea_orm_active_enums::Frequency::Monthly,
+        interval: 1,
+        starts_on: date(2024, 7, 1),
+        ends_on: None,
+        count: None,
+        occurrences: 0,
+        next_occurrence: Some(date(2024, 7, 1)),
+        created_at: now,
+        updated_at: now,
+    };
+    recurring_expense::Entity::insert(recurring_expense::ActiveModel::from(template))
+        .exec(&state.db)
+        .await
+        .unwrap();
+
+    let handle = scheduler::spawn(state.clone());
+    let mut created = 0;
+    for _ in 0..50 {
+        created = expense::Entity::find()
+            .filter(expense::Column::BudgetId.eq(budget_id))
+            .count(&state.db)
+            .await
+            .unwrap();
+        if created > 0 {
+            break;
+        }
+        actix_web::rt::time::sleep(std::time::Duration::from_millis(20)).await;
+    }
+    handle.abort();
+    assert_eq!(created, 2);
+}
@@ -0,0 +1,188 @@
+mod common;
+
+use actix_web::{test, App};
+use pbudget::handler;
+use serde_json::{json, Value};
+
+use common::{authed, login, test_state};
+
+#[actix_web::test]
+async fn every_route_resolves_its_dependencies() {
+    let state = test_state().await;
+    let app = test::init_service(App::new().app_data(state).configure(handler::init)).await;
+    let token = login(&app, "alice").await;
+
+    let req = authed("POST", "/api/budget", &token)
+        .set_json(json!({ "name": "Groceries", "total_amount": 400.0 }))
+        .to_request();
+    let budget: Value = test::call_and_read_body_json(&app, req).await;
+    let budget_id = budget["id"].as_str().unwrap().to_string();
+
+    let req = authed(
+        "POST",
+        &format!("/api/budget/{}/expenses", budget_id),
+        &token,
+    )
+    .set_json(json!({ "amount": 12.5, "description": "Bread" }))
+    .to_request();
+    let expense: Value = test::call_and_read_body_json(&app, req).await;
+    let expense_id = expense["id"].as_str().unwrap().to_string();
+
+    let budget_uri = format!("/api/budget/{}", budget_id);
+    let expenses_uri = format!("{}/expenses", budget_uri);
+    let expense_uri = format!("{}/{}", expenses_uri, expense_id);
+
+    let routes = [
+        ("GET", "/api/profile".to_string(), None),
+        (
+            "PUT",
+            "/api/profile".to_string(),
+            Some(json!({ "email": "a@example.org" })),
+        ),
+        ("GET", "/api/budget".to_string(), None),
+        (
+            "POST",
+            "/api/budget".to_string(),
+            Some(json!({ "name": "Rent", "total_amount": 900.0 })),
+        ),
+        ("GET", budget_uri.clone(), None),
+        (
+            "POST",
+            budget_uri.clone(),
+            Some(json!({ "name": "Fuel", "total_amount": 80.0 })),
+        ),
+        ("PUT", budget_uri.clone(), Some(json!({ "name": "Food" }))),
+        ("GET", expenses_uri.clone(), None),
+        (
+            "POST",
+            expenses_uri.clone(),
+            Some(json!({ "amount": 3.0, "description": "Milk" })),
+        ),
+        ("GET", expense_uri.clone(), None),
+        ("PUT", expense_uri.clone(), Some(json!({ "amount": 13.0 }))),
+        ("DELETE", expense_uri.clone(), None),
+        ("DELETE", budget_uri.clone(), None),
+    ];
+
+    for (method, uri, body) in routes {
+        let req = authed(method, &uri, &token);
+        let req = match body {
+            Some(body) => req.set_json(body),
+            None => req,
+        };
+        let res = test::call_service(&app, req.to_request()).await;
+        assert!(
+            res.status().is_success(),
+            "{} {} responded {}",
+            method,
+            uri,
+            res.status()
+        );
+    }
+}
+
+#[actix_web::test]
+async fn protected_routes_require_a_token() {
+    let state = test_state().await;
+    let app = test::init_service(App::new().app_data(state).configure(handler::init)).await;
+
+    let req = test::TestRequest::get().uri("/api/profile").to_request();
+    let res = test::call_service(&app, req).await;
+    assert_eq!(res.status(), 401);
+    let body: Value = test::read_body_json(res).await;
+    assert_eq!(body["error"]["code"], "unauthorized");
+}
+
+#[actix_web::test]
+async fn failures_are_reported_as_json_errors() {
+    let state = test_state().await;
+    let app = test::init_service(App::new().app_data(state).configure(handler::init)).await;
+    let token = login(&app, "bob").await;
+
+    let req = test::TestRequest::post()
+        .uri("/api/register")
+        .set_json(
+            json!({ "username": "bob", "password": "whatever 123", "email": "b@example.com" }),
+        )
+        .to_request();
+    let res = test::call_service(&app, req).await;
+    assert_eq!(res.status(), 409);
+    let body: Value = test::read_body_json(res).await;
+    assert_eq!(body["error"]["code"], "conflict");
+
+    let req = test::TestRequest::post()
+        .uri("/api/login")
+        .set_json(json!({ "username": "bob", "password": "wrong" }))
+        .to_request();
+    let res = test::call_service(&app, req).await;
+    assert_eq!(res.status(), 401);
+
+    let req = authed("POST", "/api/budget", &token)
+        .insert_header(("content-type", "application/json"))
+        .set_payload("{not json")
+        .to_request();
+    let res = test::call_service(&app, req).await;
+    assert_eq!(res.status(), 400);
+    let body: Value = test::read_body_json(res).await;
+    assert_eq!(body["error"]["code"], "bad_request");
+
+    let req = authed("GET", "/api/budget/not-a-uuid", &token).to_request();
+    let res = test::call_service(&app, req).await;
+    assert_eq!(res.status(), 400);
+
+    let missing = format!("/api/budget/{}", uuid::Uuid::new_v4());
+    let res = test::call_service(&app, authed("GET", &missing, &token).to_request()).await;
+    assert_eq!(res.status(), 404);
+    let body: Value = test::read_body_json(res).await;
+    assert_eq!(body["error"]["code"], "not_found");
+    assert_eq!(body["error"]["message"], "budget not found");
+}
+
+#[actix_web::test]
+async fn invalid_payloads_are_rejected_with_field_errors() {
+    let state = test_state().await;
+    let app = test::init_service(App::new().app_data(state).configure(handler::init)).await;
+    let token = login(&app, "dave").await;
+
+    let req = test::TestRequest::post()
+        .uri("/api/register")
+        .set_json(json!({ "username": "", "password": "short", "email": "nope" }))
+        .to_request();
+    let res = test::call_service(&app, req).await;
+    assert_eq!(res.status(), 422);
+    let body: Value = test::read_body_json(res).await;
+    assert_eq!(body["error"]["code"], "validation_failed");
+    let fields: Vec<&str> = body["error"]["details"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|d| d["field"].as_str().unwrap())
+        .collect();
+    assert!(fields.contains(&"username"));
+    assert!(fields.contains(&"password"));
+    assert!(fields.contains(&"email"));
+
+    let req = authed("POST", "/api/budget", &token)
+        .set_json(json!({ "name": "  ", "total_amount": -5.0 }))
+        .to_request();
+    let res = test::call_service(&app, req).await;
+    assert_eq!(res.status(), 422);
+
+    let req = authed("POST", "/api/budget", &token)
+        .set_json(json!({ "name": "Misc", "total_amount": 10.0 }))
+        .to_request();
+    let budget: Value = test::call_and_read_body_json(&app, req).await;
+
+    let uri = format!("/api/budget/{}/expenses", budget["id"].as_str().unwrap());
+    let req = authed("POST", &uri, &token)
+        .set_json(json!({ "amount": 1.0, "description": "x".repeat(501) }))
+        .to_request();
+    let res = test::call_service(&app, req).await;
+    assert_eq!(res.status(), 422);
+
+    let req = authed("POST", &uri, &token)
+        .set_json(json!({ "amount": 1.0, "description": "x".repeat(100 * 1024) }))
+        .to_request();
+    let res = test::call_service(&app, req).await;
+    assert_eq!(res.status(), 413);
+}
@@ -0,0 +1,241 @@
+mod common;
+
+use actix_web::{http::StatusCode, test, App};
+use pbudget::handler;
+use serde_json::{json, Value};
+
+use common::{authed, login, test_state};
+
+#[actix_web::test]
+async fn split_lines_count_towards_their_own_budget_and_category() {
+    let state = test_state().await;
+    let app = test::init_service(App::new().app_data(state.clone()).configure(handler::init)).await;
+    let token = login(&app, "bianca").await;
+    let other = login(&app, "carlos").await;
+
+    let mut ids = Vec::new();
+    for (uri, body) in [
+        (
+            "/api/budget",
+            json!({ "name": "Groceries", "total_amount": "400", "starts_on": "2024-08-01" }),
+        ),
+        (
+            "/api/budget",
+            json!({ "name": "Household", "total_amount": "200", "starts_on": "2024-08-01" }),
+        ),
+        ("/api/categories", json!({ "name": "Food" })),
+        ("/api/categories", json!({ "name": "Cleaning" })),
+    ] {
+        let req = authed("POST", uri, &token).set_json(body).to_request();
+        let created: Value = test::call_and_read_body_json(&app, req).await;
+        ids.push(created["id"].as_str().unwrap().to_string());
+    }
+    let [groceries, household, food, cleaning] = &ids[..] else {
+        unreachable!()
+    };
+    let expenses_uri = format!("/api/budget/{}/expenses", groceries);
+    let spent = |budget: &str| authed("GET", &format!("/api/budget/{}/summary", budget), &token);
+
+    let req = authed("POST", &expenses_uri, &token)
+        .set_json(json!({
+            "amount": "100",
+            "description": "Supermarket",
+            "date": "2024-08-10",
+            "splits": [
+                { "amount": "70", "category_id": food },
+                { "amount": "30", "budget_id": household, "category_id": cleaning },
+            ],
+        }))
+        .to_request();
+    let receipt: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(receipt["splits"][0]["budget_id"], groceries.as_str());
+    assert_eq!(receipt["splits"][1]["amount"], "30.00");
+    let receipt_uri = format!("{}/{}", expenses_uri, receipt["id"].as_str().unwrap());
+    let req = authed("POST", &expenses_uri, &token)
+        .set_json(json!({ "amount": "20", "description": "Snacks", "date": "2024-08-11" }))
+        .to_request();
+    let res = test::call_service(&app, req).await;
+    assert_eq!(res.status(), StatusCode::OK);
+
+    let summary: Value = test::call_and_read_body_json(&app, spent(groceries).to_request()).await;
+    assert_eq!(summary["spent"], "90.00");
+    let summary: Value = test::call_and_read_body_json(&app, spent(household).to_request()).await;
+    assert_eq!(summary["spent"], "30.00");
+
+    let req = authed(
+        "GET",
+        &format!("/api/budget/{}/categories", groceries),
+        &token,
+    )
+    .to_request();
+    let breakdown: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(breakdown["spent"], "90.00");
+    assert_eq!(breakdown["uncategorized_spent"], "20.00");
+    assert_eq!(breakdown["categories"][0]["name"], "Food");
+    assert_eq!(breakdown["categories"][0]["spent"], "70.00");
+
+    let cash_flow = |budget: Option<&str>| {
+        let uri = match budget {
+            Some(budget) => format!(
+                "/api/reports/cash-flow?from=2024-08-01&budget_id={}",
+                budget
+            ),
+            None => "/api/reports/cash-flow?from=2024-08-01".to_string(),
+        };
+        authed("GET", &uri, &token).to_request()
+    };
+    let report: Value = test::call_and_read_body_json(&app, cash_flow(Some(household))).await;
+    assert_eq!(report["expenses"], "30.00");
+    let report: Value = test::call_and_read_body_json(&app, cash_flow(None)).await;
+    assert_eq!(report["expenses"], "120.00");
+
+    // Changing the amount needs new lines that add up to it.
+    let req = authed("PUT", &receipt_uri, &token)
+        .set_json(json!({ "amount": "110" }))
+        .to_request();
+    let res = test::call_service(&app, req).await;
+    assert_eq!(res.status(), StatusCode::UNPROCESSABLE_ENTITY);
+    let req = authed("PUT", &receipt_uri, &token)
+        .set_json(json!({
+            "amount": "110",
+            "splits": [
+                { "amount": "60", "category_id": food },
+                { "amount": "50", "budget_id": household },
+            ],
+        }))
+        .to_request();
+    let receipt: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(receipt["splits"].as_array().unwrap().len(), 2);
+    let summary: Value = test::call_and_read_body_json(&app, spent(household).to_request()).await;
+    assert_eq!(summary["spent"], "50.00");
+
+    // Deleting a budget that was split into charges its line back to the
+    // expense's own budget.
+    let req = authed("DELETE", &format!("/api/budget/{}", household), &token).to_request();
+    let res = test::call_service(&app, req).await;
+    assert_eq!(res.status(), StatusCode::OK);
+    let req = authed("GET", &receipt_uri, &token).to_request();
+    let receipt: Value = test::call_and_read_body_json(&app, req).await;
+    let lines: Vec<_> = receipt["splits"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|line| (line["budget_id"].clone(), line["amount"].clone()))
+        .collect();
+    assert_eq!(
+        lines,
+        [
+            (json!(groceries), json!("60.00")),
+            (json!(groceries), json!("50.00")),
+        ]
+    );
+    let summary: Value = test::call_and_read_body_json(&app, spent(groceries).to_request()).await;
+    assert_eq!(summary["spent"], "130.00");
+
+    for (body, field) in [
+        (
+            json!({ "splits": [{ "amount": "60" }, { "amount": "30" }] }),
+            "splits",
+        ),
+        (
+            json!({ "splits": [{ "amount": "110", "budget_id": household }] }),
+            "splits[0].budget_id",
+        ),
+        (
+            json!({ "splits": [{ "amount": "0" }, { "amount": "110" }] }),
+            "splits",
+        ),
+    ] {
+        let req = authed("PUT", &receipt_uri, &token)
+            .set_json(&body)
+            .to_request();
+        let res = test::call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::UNPROCESSABLE_ENTITY, "{}", body);
+        let error: Value = test::read_body_json(res).await;
+        assert_eq!(error["error"]["details"][0]["field"], field, "{}", body);
+    }
+
+    // Nobody else's budgets can be split into.
+    let req = authed("POST", "/api/budget", &other)
+        .set_json(json!({ "name": "Theirs", "total_amount": "10" }))
+        .to_request();
+    let theirs: Value = test::call_and_read_body_json(&app, req).await;
+    let req = authed("PUT", &receipt_uri, &token)
+        .set_json(json!({ "splits": [{ "amount": "110", "budget_id": theirs["id"] }] }))
+        .to_request();
+    let res = test::call_service(&app, req).await;
+    assert_eq!(res.status(), StatusCode::UNPROCESSABLE_ENTITY);
+
+    let req = authed("DELETE", &receipt_uri, &token).to_request();
+    let res = test::call_service(&app, req).await;
+    assert_eq!(res.status(), StatusCode::OK);
+}
+
+#[actix_web::test]
+async fn deleting_a_budget_keeps_lines_charged_to_other_budgets() {
+    let state = test_state().await;
+    let app = test::init_service(App::new().app_data(state.clone()).configure(handler::init)).await;
+    let token = login(&app, "dmitri").await;
+
+    let mut budgets = Vec::new();
+    for name in ["Groceries", "Household", "Garden"] {
+        let req = authed("POST", "/api/budget", &token)
+            .set_json(json!({ "name": name, "total_amount": "400", "starts_on": "2024-08-01" }))
+            .to_request();
+        let created: Value = test::call_and_read_body_json(&app, req).await;
+        budgets.push(created["id"].as_str().unwrap().to_string());
+    }
+    let [groceries, household, garden] = &budgets[..] else {
+        unreachable!()
+    };
+    let spent = |budget: &str| authed("GET", &format!("/api/budget/{}/summary", budget), &token);
+
+    let req = authed(
+        "POST",
+        &format!("/api/budget/{}/expenses", groceries),
+        &token,
+    )
+    .set_json(json!({
+        "amount": "100",
+        "description": "Hardware store",
+        "date": "2024-08-10",
+        "splits": [
+            { "amount": "50" },
+            { "amount": "30", "budget_id": household },
+            { "amount": "20", "budget_id": garden },
+        ],
+    }))
+    .to_request();
+    let receipt: Value = test::call_and_read_body_json(&app, req).await;
+    let receipt_uri = format!(
+        "/api/budget/{}/expenses/{}",
+        groceries,
+        receipt["id"].as_str().unwrap()
+    );
+
+    let req = authed("DELETE", &format!("/api/budget/{}", household), &token).to_request();
+    let res = test::call_service(&app, req).await;
+    assert_eq!(res.status(), StatusCode::OK);
+
+    let summary: Value = test::call_and_read_body_json(&app, spent(garden).to_request()).await;
+    assert_eq!(summary["spent"], "20.00");
+    let summary: Value = test::call_and_read_body_json(&app, spent(groceries).to_request()).await;
+    assert_eq!(summary["spent"], "80.00");
+
+    let req = authed("GET", &receipt_uri, &token).to_request();
+    let receipt: Value = test::call_and_read_body_json(&app, req).await;
+    let lines: Vec<_> = receipt["splits"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|line| (line["budget_id"].clone(), line["amount"].clone()))
+        .collect();
+    assert_eq!(
+        lines,
+        [
+            (json!(groceries), json!("50.00")),
+            (json!(groceries), json!("30.00")),
+            (json!(garden), json!("20.00")),
+        ]
+    );
+}
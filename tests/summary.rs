@@ -0,0 +1,94 @@
+mod common;
+
+use actix_web::{http::StatusCode, test, App};
+use pbudget::handler;
+use serde_json::{json, Value};
+
+use common::{authed, login, test_state};
+
+#[actix_web::test]
+async fn summary_reports_burn_rate_for_the_current_period() {
+    let state = test_state().await;
+    let app = test::init_service(App::new().app_data(state.clone()).configure(handler::init)).await;
+    let token = login(&app, "uma").await;
+
+    let req = authed("POST", "/api/budget", &token)
+        .set_json(json!({
+            "name": "Household",
+            "total_amount": "300",
+            "starts_on": "2024-08-01",
+        }))
+        .to_request();
+    let budget: Value = test::call_and_read_body_json(&app, req).await;
+    let budget_uri = format!("/api/budget/{}", budget["id"].as_str().unwrap());
+
+    for (date, amount) in [
+        ("2024-07-20", "100"),
+        ("2024-08-02", "30"),
+        ("2024-08-10", "45.50"),
+        ("2024-09-01", "12"),
+    ] {
+        let req = authed("POST", &format!("{}/expenses", budget_uri), &token)
+            .set_json(json!({ "amount": amount, "description": "Shop", "date": date }))
+            .to_request();
+        let res = test::call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::OK);
+    }
+
+    // The test clock is fixed at 2024-08-15, the 15th of 31 days.
+    let req = authed("GET", &format!("{}/summary", budget_uri), &token).to_request();
+    let summary: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(
+        summary,
+        json!({
+            "budget_id": budget["id"],
+            "currency": "USD",
+            "start": "2024-08-01",
+            "end": "2024-08-31",
+            "available": "300.00",
+            "spent": "75.50",
+            "remaining": "224.50",
+            "percent_used": 25.17,
+            "average_daily_spend": "5.03",
+            "projected_spend": "156.03",
+            "days_elapsed": 15,
+            "days_remaining": 16,
+        })
+    );
+
+    let other = login(&app, "victor").await;
+    let req = authed("GET", &format!("{}/summary", budget_uri), &other).to_request();
+    let res = test::call_service(&app, req).await;
+    assert_eq!(res.status(), StatusCode::NOT_FOUND);
+}
+
+#[actix_web::test]
+async fn summary_of_a_budget_that_has_not_started_yet() {
+    let state = test_state().await;
+    let app = test::init_service(App::new().app_data(state.clone()).configure(handler::init)).await;
+    let token = login(&app, "wendy").await;
+
+    let req = authed("POST", "/api/budget", &token)
+        .set_json(json!({
+            "name": "Holiday",
+            "total_amount": "1000",
+            "period": "custom",
+            "starts_on": "2024-09-01",
+            "ends_on": "2024-09-10",
+        }))
+        .to_request();
+    let budget: Value = test::call_and_read_body_json(&app, req).await;
+
+    let req = authed(
+        "GET",
+        &format!("/api/budget/{}/summary", budget["id"].as_str().unwrap()),
+        &token,
+    )
+    .to_request();
+    let summary: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(summary["spent"], "0.00");
+    assert_eq!(summary["percent_used"], 0.0);
+    assert_eq!(summary["average_daily_spend"], "0.00");
+    assert_eq!(summary["days_elapsed"], 0);
+    assert_eq!(summary["days_remaining"], 10);
+}
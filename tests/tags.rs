@@ -0,0 +1,171 @@
+mod common;
+
+use actix_web::{http::StatusCode, test, App};
+use pbudget::handler;
+use serde_json::{json, Value};
+
+use common::{authed, login, test_state};
+
+#[actix_web::test]
+async fn expenses_can_be_tagged_and_filtered_by_tag() {
+    let state = test_state().await;
+    let app = test::init_service(App::new().app_data(state.clone()).configure(handler::init)).await;
+    let token = login(&app, "peggy").await;
+
+    let req = authed("POST", "/api/budget", &token)
+        .set_json(json!({ "name": "Conference", "total_amount": "2000" }))
+        .to_request();
+    let budget: Value = test::call_and_read_body_json(&app, req).await;
+    let expenses_uri = format!("/api/budget/{}/expenses", budget["id"].as_str().unwrap());
+
+    let mut ids = Vec::new();
+    for (description, tags) in [
+        (
+            "Flight",
+            json!(["trip-berlin", "reimbursable", "trip-berlin"]),
+        ),
+        ("Hotel", json!(["trip-berlin"])),
+        ("Souvenir", json!([])),
+    ] {
+        let req = authed("POST", &expenses_uri, &token)
+            .set_json(json!({ "amount": "100", "description": description, "tags": tags }))
+            .to_request();
+        let expense: Value = test::call_and_read_body_json(&app, req).await;
+        ids.push(expense["id"].as_str().unwrap().to_string());
+        if description == "Flight" {
+            assert_eq!(expense["tags"], json!(["reimbursable", "trip-berlin"]));
+        }
+    }
+
+    let descriptions = |expenses: &Value| -> Vec<String> {
+        let mut d: Vec<String> = expenses["data"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|e| e["description"].as_str().unwrap().to_string())
+            .collect();
+        d.sort();
+        d
+    };
+
+    let req = authed("GET", &format!("{}?tag=trip-berlin", expenses_uri), &token).to_request();
+    let expenses: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(descriptions(&expenses), ["Flight", "Hotel"]);
+
+    let req = authed("GET", &format!("{}?tag=reimbursable", expenses_uri), &token).to_request();
+    let expenses: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(descriptions(&expenses), ["Flight"]);
+
+    let req = authed("GET", &expenses_uri, &token).to_request();
+    let expenses: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(expenses["data"].as_array().unwrap().len(), 3);
+
+    // Replacing the tags of the hotel moves it out of the trip filter.
+    let hotel_uri = format!("{}/{}", expenses_uri, ids[1]);
+    let req = authed("PUT", &hotel_uri, &token)
+        .set_json(json!({ "tags": ["reimbursable"] }))
+        .to_request();
+    let hotel: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(hotel["tags"], json!(["reimbursable"]));
+
+    let req = authed("GET", &format!("{}?tag=trip-berlin", expenses_uri), &token).to_request();
+    let expenses: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(descriptions(&expenses), ["Flight"]);
+
+    let req = authed("GET", "/api/tags", &token).to_request();
+    let tags: Value = test::call_and_read_body_json(&app, req).await;
+    let names: Vec<&str> = tags
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|t| t["name"].as_str().unwrap())
+        .collect();
+    assert_eq!(names, ["reimbursable", "trip-berlin"]);
+
+    let req = authed("POST", &expenses_uri, &token)
+        .set_json(json!({ "amount": "1", "description": "x", "tags": ["has space"] }))
+        .to_request();
+    let res = test::call_service(&app, req).await;
+    assert_eq!(res.status(), StatusCode::UNPROCESSABLE_ENTITY);
+}
+
+#[actix_web::test]
+async fn renaming_and_deleting_tags_updates_expenses() {
+    let state = test_state().await;
+    let app = test::init_service(App::new().app_data(state.clone()).configure(handler::init)).await;
+    let token = login(&app, "quinn").await;
+
+    let req = authed("POST", "/api/tags", &token)
+        .set_json(json!({ "name": "work" }))
+        .to_request();
+    let tag: Value = test::call_and_read_body_json(&app, req).await;
+    let tag_uri = format!("/api/tags/{}", tag["id"].as_str().unwrap());
+
+    let req = authed("POST", "/api/tags", &token)
+        .set_json(json!({ "name": "work" }))
+        .to_request();
+    let res = test::call_service(&app, req).await;
+    assert_eq!(res.status(), StatusCode::CONFLICT);
+
+    let req = authed("POST", "/api/budget", &token)
+        .set_json(json!({ "name": "Office", "total_amount": "300" }))
+        .to_request();
+    let budget: Value = test::call_and_read_body_json(&app, req).await;
+    let req = authed(
+        "POST",
+        &format!("/api/budget/{}/expenses", budget["id"].as_str().unwrap()),
+        &token,
+    )
+    .set_json(json!({ "amount": "20", "description": "Desk lamp", "tags": ["work"] }))
+    .to_request();
+    let expense: Value = test::call_and_read_body_json(&app, req).await;
+    let expense_uri = format!(
+        "/api/budget/{}/expenses/{}",
+        budget["id"].as_str().unwrap(),
+        expense["id"].as_str().unwrap()
+    );
+
+    // Warm the cache.
+    let req = authed("GET", &expense_uri, &token).to_request();
+    let cached: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(cached["tags"], json!(["work"]));
+
+    let req = authed("PUT", &tag_uri, &token)
+        .set_json(json!({ "name": "office" }))
+        .to_request();
+    let renamed: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(renamed["name"], "office");
+
+    let req = authed("GET", &expense_uri, &token).to_request();
+    let fetched: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(fetched["tags"], json!(["office"]));
+
+    let res = test::call_service(&app, authed("DELETE", &tag_uri, &token).to_request()).await;
+    assert_eq!(res.status(), StatusCode::OK);
+
+    let req = authed("GET", &expense_uri, &token).to_request();
+    let fetched: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(fetched["tags"], json!([]));
+
+    let other = login(&app, "rupert").await;
+    let res = test::call_service(&app, authed("GET", &tag_uri, &other).to_request()).await;
+    assert_eq!(res.status(), StatusCode::NOT_FOUND);
+
+    // Deleting the expense's budget also removes its tag links.
+    let req = authed("PUT", &expense_uri, &token)
+        .set_json(json!({ "tags": ["furniture"] }))
+        .to_request();
+    let res = test::call_service(&app, req).await;
+    assert_eq!(res.status(), StatusCode::OK);
+    let res = test::call_service(
+        &app,
+        authed(
+            "DELETE",
+            &format!("/api/budget/{}", budget["id"].as_str().unwrap()),
+            &token,
+        )
+        .to_request(),
+    )
+    .await;
+    assert_eq!(res.status(), StatusCode::OK);
+}
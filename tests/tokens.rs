@@ -0,0 +1,129 @@
+mod common;
+
+use std::sync::Arc;
+
+use actix_web::{http::StatusCode, test, web, App};
+use chrono::{TimeZone, Utc};
+use pbudget::{
+    handler,
+    utility::{clock::FixedClock, state::AppState},
+};
+use serde_json::{json, Value};
+
+use common::{authed, login, test_settings, test_state};
+
+#[actix_web::test]
+async fn refresh_tokens_rotate_and_reuse_revokes_the_family() {
+    let state = test_state().await;
+    let app = test::init_service(App::new().app_data(state.clone()).configure(handler::init)).await;
+
+    login(&app, "walter").await;
+
+    let req = test::TestRequest::post()
+        .uri("/api/login")
+        .set_json(json!({ "username": "walter", "password": "correct horse battery staple" }))
+        .to_request();
+    let tokens: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(tokens["token_type"], "Bearer");
+    assert_eq!(tokens["expires_in"], 3600);
+    assert_eq!(tokens["access_token_expires_at"], "2024-08-15T13:00:00Z");
+    assert_eq!(tokens["refresh_token_expires_at"], "2024-09-14T12:00:00Z");
+
+    // An hour later the access token has expired but the refresh token works.
+    let later = FixedClock(Utc.with_ymd_and_hms(2024, 8, 15, 13, 0, 0).unwrap());
+    let later = web::Data::new(
+        AppState::with_clock(state.db.clone(), test_settings(), Arc::new(later)).unwrap(),
+    );
+    let app = test::init_service(App::new().app_data(later).configure(handler::init)).await;
+
+    let access = format!("Bearer {}", tokens["access_token"].as_str().unwrap());
+    let res = test::call_service(&app, authed("GET", "/api/profile", &access).to_request()).await;
+    assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+
+    let refresh = |token: &Value| {
+        test::TestRequest::post()
+            .uri("/api/token/refresh")
+            .set_json(json!({ "refresh_token": token }))
+            .to_request()
+    };
+    let res = test::call_service(&app, refresh(&tokens["refresh_token"])).await;
+    assert_eq!(res.status(), StatusCode::OK);
+    let rotated: Value = test::read_body_json(res).await;
+    assert_ne!(rotated["refresh_token"], tokens["refresh_token"]);
+    assert_eq!(rotated["access_token_expires_at"], "2024-08-15T14:00:00Z");
+
+    let access = format!("Bearer {}", rotated["access_token"].as_str().unwrap());
+    let profile: Value =
+        test::call_and_read_body_json(&app, authed("GET", "/api/profile", &access).to_request())
+            .await;
+    assert_eq!(profile["username"], "walter");
+
+    // Replaying the first refresh token revokes the one it was rotated into.
+    let res = test::call_service(&app, refresh(&tokens["refresh_token"])).await;
+    assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+    let res = test::call_service(&app, refresh(&rotated["refresh_token"])).await;
+    assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+
+    let res = test::call_service(&app, refresh(&json!("not-a-token"))).await;
+    assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+}
+
+#[actix_web::test]
+async fn logout_revokes_sessions_before_they_expire() {
+    let state = test_state().await;
+    let app = test::init_service(App::new().app_data(state.clone()).configure(handler::init)).await;
+    let first = login(&app, "xena").await;
+
+    let sign_in = || {
+        test::TestRequest::post()
+            .uri("/api/login")
+            .set_json(json!({ "username": "xena", "password": "correct horse battery staple" }))
+            .to_request()
+    };
+    let second: Value = test::call_and_read_body_json(&app, sign_in()).await;
+    let second_access = format!("Bearer {}", second["access_token"].as_str().unwrap());
+    let profile = |token: &str| authed("GET", "/api/profile", token).to_request();
+
+    let res = test::call_service(&app, authed("POST", "/api/logout", &first).to_request()).await;
+    assert_eq!(res.status(), StatusCode::OK);
+    let res = test::call_service(&app, profile(&first)).await;
+    assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+    let body: Value = test::read_body_json(res).await;
+    assert_eq!(body["error"]["message"], "token has been revoked");
+
+    // The other login is untouched.
+    let res = test::call_service(&app, profile(&second_access)).await;
+    assert_eq!(res.status(), StatusCode::OK);
+
+    let third: Value = test::call_and_read_body_json(&app, sign_in()).await;
+    let third_access = format!("Bearer {}", third["access_token"].as_str().unwrap());
+    let res = test::call_service(
+        &app,
+        authed("POST", "/api/logout/all", &second_access).to_request(),
+    )
+    .await;
+    assert_eq!(res.status(), StatusCode::OK);
+    for token in [&second_access, &third_access] {
+        let res = test::call_service(&app, profile(token)).await;
+        assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+    }
+    let req = test::TestRequest::post()
+        .uri("/api/token/refresh")
+        .set_json(json!({ "refresh_token": third["refresh_token"] }))
+        .to_request();
+    let res = test::call_service(&app, req).await;
+    assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+
+    // Changing the password logs out every session, including the current one.
+    let fourth: Value = test::call_and_read_body_json(&app, sign_in()).await;
+    let fourth_access = format!("Bearer {}", fourth["access_token"].as_str().unwrap());
+    let res = test::call_service(&app, profile(&fourth_access)).await;
+    assert_eq!(res.status(), StatusCode::OK);
+    let req = authed("PUT", "/api/profile", &fourth_access)
+        .set_json(json!({ "password": "another horse 42" }))
+        .to_request();
+    let res = test::call_service(&app, req).await;
+    assert_eq!(res.status(), StatusCode::OK);
+    let res = test::call_service(&app, profile(&fourth_access)).await;
+    assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+}
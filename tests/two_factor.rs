@@ -0,0 +1,150 @@
+mod common;
+
+use actix_web::{http::StatusCode, test, App};
+use chrono::{Duration, TimeZone, Utc};
+use pbudget::{handler, utility::totp};
+use serde_json::{json, Value};
+
+use common::{authed, login, test_state};
+
+#[actix_web::test]
+async fn codes_match_the_rfc_6238_test_vectors() {
+    // The RFC's SHA-1 seed, "12345678901234567890", in base32.
+    let secret = "GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ";
+    for (time, code) in [
+        (59, "287082"),
+        (1111111109, "081804"),
+        (1234567890, "005924"),
+        (2000000000, "279037"),
+    ] {
+        let at = Utc.timestamp_opt(time, 0).unwrap();
+        assert_eq!(totp::code(secret, at).unwrap(), code, "{}", time);
+        assert!(totp::verify(secret, code, at, None).is_some());
+    }
+    let at = Utc.timestamp_opt(59, 0).unwrap();
+    assert!(totp::verify(secret, "287082", at, Some(1)).is_none());
+}
+
+#[actix_web::test]
+async fn login_asks_for_a_code_once_totp_is_confirmed() {
+    let state = test_state().await;
+    let app = test::init_service(App::new().app_data(state.clone()).configure(handler::init)).await;
+    let token = login(&app, "ava").await;
+    let now = state.clock.now();
+
+    let sign_in = || {
+        test::TestRequest::post()
+            .uri("/api/login")
+            .set_json(json!({ "username": "ava", "password": "correct horse battery staple" }))
+            .to_request()
+    };
+    let second_step = |challenge: &Value, code: &str| {
+        test::TestRequest::post()
+            .uri("/api/login/2fa")
+            .set_json(json!({ "challenge_token": challenge["challenge_token"], "code": code }))
+            .to_request()
+    };
+
+    let req = authed("POST", "/api/2fa/totp", &token).to_request();
+    let enrollment: Value = test::call_and_read_body_json(&app, req).await;
+    let secret = enrollment["secret"].as_str().unwrap();
+    assert_eq!(
+        enrollment["otpauth_uri"],
+        format!(
+            "otpauth://totp/pbudget:ava?secret={}&issuer=pbudget&algorithm=SHA1&digits=6&period=30",
+            secret
+        )
+    );
+
+    // Until it is confirmed, logging in works as before.
+    let tokens: Value = test::call_and_read_body_json(&app, sign_in()).await;
+    assert!(tokens["access_token"].is_string());
+
+    let req = authed("POST", "/api/2fa/totp/confirm", &token)
+        .set_json(json!({ "code": totp::code(secret, now - Duration::hours(1)).unwrap() }))
+        .to_request();
+    let res = test::call_service(&app, req).await;
+    assert_eq!(res.status(), StatusCode::UNPROCESSABLE_ENTITY);
+
+    let confirmation_code = totp::code(secret, now).unwrap();
+    let req = authed("POST", "/api/2fa/totp/confirm", &token)
+        .set_json(json!({ "code": confirmation_code }))
+        .to_request();
+    let confirmed: Value = test::call_and_read_body_json(&app, req).await;
+    let recovery_codes = confirmed["recovery_codes"].as_array().unwrap();
+    assert_eq!(recovery_codes.len(), 10);
+
+    let challenge: Value = test::call_and_read_body_json(&app, sign_in()).await;
+    assert_eq!(challenge["two_factor_required"], true);
+    assert_eq!(challenge["challenge_expires_at"], "2024-08-15T12:05:00Z");
+    assert!(challenge.get("access_token").is_none());
+
+    // A code works only once, even within its 30 seconds.
+    let res = test::call_service(&app, second_step(&challenge, &confirmation_code)).await;
+    assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+    let next_code = totp::code(secret, now + Duration::seconds(30)).unwrap();
+    let tokens: Value =
+        test::call_and_read_body_json(&app, second_step(&challenge, &next_code)).await;
+    let access = format!("Bearer {}", tokens["access_token"].as_str().unwrap());
+    let res = test::call_service(&app, authed("GET", "/api/profile", &access).to_request()).await;
+    assert_eq!(res.status(), StatusCode::OK);
+    let res = test::call_service(&app, second_step(&challenge, &next_code)).await;
+    assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+
+    // Recovery codes stand in for a code, once each.
+    let recovery = recovery_codes[0].as_str().unwrap().to_uppercase();
+    let challenge: Value = test::call_and_read_body_json(&app, sign_in()).await;
+    let res = test::call_service(&app, second_step(&challenge, &recovery)).await;
+    assert_eq!(res.status(), StatusCode::OK);
+    let challenge: Value = test::call_and_read_body_json(&app, sign_in()).await;
+    let res = test::call_service(&app, second_step(&challenge, &recovery)).await;
+    assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+
+    // Too many wrong codes and the password has to be sent again.
+    for _ in 0..4 {
+        let res = test::call_service(&app, second_step(&challenge, "nope")).await;
+        assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+    }
+    let res = test::call_service(
+        &app,
+        second_step(&challenge, recovery_codes[1].as_str().unwrap()),
+    )
+    .await;
+    assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+    let body: Value = test::read_body_json(res).await;
+    assert_eq!(
+        body["error"]["message"],
+        "invalid or expired challenge token"
+    );
+
+    // Sending wrong codes in parallel does not get more guesses.
+    let challenge: Value = test::call_and_read_body_json(&app, sign_in()).await;
+    let responses = futures::future::join_all(
+        (0..10).map(|_| test::call_service(&app, second_step(&challenge, "nope"))),
+    )
+    .await;
+    assert!(responses
+        .iter()
+        .all(|res| res.status() == StatusCode::UNAUTHORIZED));
+    let res = test::call_service(
+        &app,
+        second_step(&challenge, recovery_codes[1].as_str().unwrap()),
+    )
+    .await;
+    assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+
+    let status: Value =
+        test::call_and_read_body_json(&app, authed("GET", "/api/2fa", &token).to_request()).await;
+    assert_eq!(
+        status,
+        json!({ "enabled": true, "recovery_codes_remaining": 9 })
+    );
+
+    let req = authed("DELETE", "/api/2fa/totp", &token)
+        .set_json(json!({ "code": recovery_codes[1] }))
+        .to_request();
+    let res = test::call_service(&app, req).await;
+    assert_eq!(res.status(), StatusCode::OK);
+    let tokens: Value = test::call_and_read_body_json(&app, sign_in()).await;
+    assert!(tokens["access_token"].is_string());
+}